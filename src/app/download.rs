@@ -0,0 +1,371 @@
+use chrono::{DateTime, Local};
+
+use crate::bandwidth;
+use crate::build_info::BuildInfo;
+use crate::event::DownloadEvent;
+use crate::install_journal::Recovery;
+use crate::metered;
+use crate::profile;
+use crate::troubleshoot::Problem;
+use crate::ui::{AppState, DownloadQuestion, Notice, format_file_size};
+
+/// Abbreviated build hash for log messages
+pub(super) fn short_hash(hash: &str) -> &str {
+    hash.get(..12).unwrap_or(hash)
+}
+
+// Version, size and publish date of the build, as far as the server told
+fn describe_newer_build(info: &BuildInfo, installed: Option<DateTime<Local>>) -> String {
+    let mut details = Vec::new();
+    if let Some(version) = &info.version {
+        details.push(format!("version {version}"));
+    }
+    if let Some(size) = info.size {
+        details.push(format_file_size(size));
+    }
+    if let Some(published) = info.published {
+        details.push(format!("published {}", published.format("%Y-%m-%d %H:%M")));
+    }
+    // Hashes only say the builds differ, the dates tell which one is newer
+    let summary = match (installed, info.published) {
+        (Some(installed), Some(published)) if published < installed => {
+            "The published build is older than the installed one"
+        }
+        _ => "There is a newer version",
+    };
+    let text = if details.is_empty() {
+        format!("{summary}.")
+    } else {
+        format!("{summary}: {}.", details.join(", "))
+    };
+    match installed {
+        Some(installed) => format!(
+            "Installed build from {}. {text}",
+            installed.format("%Y-%m-%d")
+        ),
+        None => text,
+    }
+}
+
+/// Handle hashing and game download events
+pub fn handle(app_state: &mut AppState, event: DownloadEvent) {
+    match event {
+        DownloadEvent::AccessingOnlineHash => {
+            app_state.log.remote_hash_msg = Some("accessing".into());
+        }
+        DownloadEvent::OfflineError(err) => {
+            app_state.log.remote_hash_msg = Some("unavailable".into());
+            app_state.log.add_warning("No internet connection", err);
+        }
+        DownloadEvent::ServerError(err) => {
+            app_state.log.remote_hash_msg = Some("unavailable".into());
+            app_state.log.add_warning("Build server", err);
+        }
+        DownloadEvent::CheckDelayed(delay) => {
+            app_state
+                .log
+                .add_text(format!("Checking for a new build in {}s", delay.as_secs()));
+        }
+        DownloadEvent::ServerBusy { reason, retry_in } => {
+            app_state.log.add_warning(
+                "Build server",
+                format!("{reason}, retrying in {}s", retry_in.as_secs()),
+            );
+        }
+        DownloadEvent::CaptivePortal(login_url) => {
+            app_state.log.remote_hash_msg = Some("unavailable".into());
+            let page = login_url.map_or_else(
+                || "any web page, e.g. http://neverssl.com".to_string(),
+                |url| format!("{url} (or any web page, e.g. http://neverssl.com)"),
+            );
+            let text = format!(
+                "The network needs a login before it lets the launcher through, \
+                 as hotel and airport Wi-Fi often do. Open {page} in a browser, \
+                 log in and restart the launcher. Until then the installed game is used."
+            );
+            app_state
+                .log
+                .add_warning("Network login required", text.clone());
+            app_state.notice = Some(Notice {
+                title: "Network login required".into(),
+                text,
+            });
+        }
+        DownloadEvent::RemoteHash(hash_value) => {
+            app_state.log.remote_hash_msg = Some(hash_value);
+        }
+        DownloadEvent::ComputingLocalHash => {
+            app_state.log.local_hash_msg = Some("Computing".into());
+        }
+        DownloadEvent::LocalHash(hash_value) => {
+            app_state.log.local_hash_msg = Some(hash_value);
+        }
+        DownloadEvent::HashAreEqual => {
+            app_state.log.add_success(
+                "Hashes are the same",
+                "You have the latest version of the game.",
+            );
+        }
+        DownloadEvent::NewerBuild { info, installed } => {
+            app_state.log.add_titled(
+                "Hashes are different",
+                describe_newer_build(&info, installed),
+            );
+        }
+        DownloadEvent::BuildFoundInCache(hash) => {
+            app_state.log.add_titled(
+                "Download cache",
+                format!("build {} is already downloaded", short_hash(&hash)),
+            );
+        }
+        DownloadEvent::StartDownloadingBinary(total_download_size) => {
+            app_state.log.start_download(total_download_size);
+        }
+        DownloadEvent::DownloadResumed(offset) => {
+            app_state.log.resume_download(offset);
+        }
+        DownloadEvent::DownloadProgress(downloaded) => {
+            app_state.log.set_download_progress(downloaded);
+        }
+        DownloadEvent::RemoteBinaryDownloaded => {
+            app_state.log.mark_download_complete();
+        }
+        DownloadEvent::BinaryDownloadError(err) => {
+            app_state.log.set_download_error(err);
+            app_state.offer_troubleshooting(Problem::Download);
+        }
+        DownloadEvent::HashMismatch {
+            expected,
+            actual,
+            retry_in,
+        } => {
+            let mismatch = format!(
+                "expected build {}, downloaded {}",
+                short_hash(&expected),
+                short_hash(&actual)
+            );
+            let text = match retry_in {
+                Some(delay) => format!("{mismatch}, retrying in {}s", delay.as_secs()),
+                None => format!("{mismatch}, giving up"),
+            };
+            app_state
+                .log
+                .add_warning("Download doesn't match the published hash", text);
+        }
+        DownloadEvent::DownloadVerificationFailed { expected, actual } => {
+            app_state.log.add_error(
+                "Verification failed",
+                format!(
+                    "build {} has hash {}, the installed game was kept",
+                    short_hash(&expected),
+                    short_hash(&actual)
+                ),
+            );
+        }
+        DownloadEvent::NotABuild { reason, retry_in } => {
+            let text = match retry_in {
+                Some(delay) => format!("{reason}, retrying in {}s", delay.as_secs()),
+                None => format!("{reason}, giving up"),
+            };
+            app_state
+                .log
+                .add_warning("Download isn't a game build", text);
+        }
+        DownloadEvent::InstallModified {
+            installed,
+            restorable,
+        } => {
+            let artifact = profile::current().artifact();
+            let text = if restorable {
+                app_state.restore_popup = Some(installed);
+                format!(
+                    "{artifact} was modified since it was installed, e.g. by mods or corruption"
+                )
+            } else {
+                format!(
+                    "{artifact} was modified since it was installed and the original {} \
+                     isn't cached, it's replaced with the next update",
+                    short_hash(&installed)
+                )
+            };
+            app_state.log.add_warning("Modified game files", text);
+        }
+        DownloadEvent::ConfirmDownload { reason, answer } => {
+            app_state.download_popup = Some(DownloadQuestion { reason, answer });
+        }
+        DownloadEvent::ConnectionMetered(metered) => {
+            app_state.log.connection_msg = metered.map(|metered| {
+                match (metered, metered::is_allowed()) {
+                    (true, false) => "metered, updates are downloaded after asking",
+                    (true, true) => "metered",
+                    (false, _) => "not metered",
+                }
+                .to_string()
+            });
+        }
+        DownloadEvent::MonthlyUsage(bytes) => {
+            let used = format_file_size(bytes);
+            app_state.log.monthly_usage_msg = Some(match bandwidth::monthly_cap() {
+                Some(cap) => format!("{used} of {}", format_file_size(cap)),
+                None => used,
+            });
+        }
+        DownloadEvent::InstallRestored(hash) => {
+            app_state.log.add_success(
+                "Game restored",
+                format!("Restored the original build {}", short_hash(&hash)),
+            );
+        }
+        DownloadEvent::InstallRecovered { hash, recovery } => {
+            let text = match recovery {
+                Recovery::Completed => {
+                    format!("Finished installing build {}", short_hash(&hash))
+                }
+                Recovery::RolledBack => format!(
+                    "Build {} wasn't fully installed, the previous build is kept",
+                    short_hash(&hash)
+                ),
+            };
+            app_state.log.add_warning("Interrupted install", text);
+        }
+        DownloadEvent::KeepingInstalledBuild(hash) => {
+            app_state.log.add_warning(
+                "Game update",
+                format!(
+                    "Update failed, playing the installed build {}",
+                    short_hash(&hash)
+                ),
+            );
+        }
+        DownloadEvent::PinnedBuildKept(hash) => {
+            app_state.log.add_titled(
+                "Game update",
+                format!(
+                    "staying on build {} picked from the version list",
+                    short_hash(&hash)
+                ),
+            );
+        }
+        DownloadEvent::VersionPicked(Some(hash)) => {
+            app_state.log.add_success(
+                "Game version",
+                format!(
+                    "Installed build {}, updates are skipped until you pick the latest one",
+                    short_hash(&hash)
+                ),
+            );
+        }
+        DownloadEvent::VersionPicked(None) => {
+            app_state.log.add_titled(
+                "Game version",
+                "Following updates again, the latest build is installed on next start",
+            );
+        }
+        DownloadEvent::CrashedBuildSkipped(hash) => {
+            app_state.log.add_warning(
+                "Game update",
+                format!(
+                    "build {} crashed on start last time, keeping the installed one",
+                    short_hash(&hash)
+                ),
+            );
+        }
+        DownloadEvent::NoLocalBinaryFound => {
+            app_state.log.add_text("Local game binary not found");
+        }
+        DownloadEvent::GameBinaryUpdated => {}
+        DownloadEvent::Prefetched { channel, hash } => {
+            app_state.log.add_titled(
+                "Prefetch",
+                format!(
+                    "build {} from {channel} is cached, switch to it in the version list",
+                    short_hash(&hash)
+                ),
+            );
+        }
+        DownloadEvent::PrefetchFailed { channel, error } => {
+            app_state
+                .log
+                .add_warning("Prefetch", format!("{channel}: {error}"));
+        }
+        DownloadEvent::GameUpdateStaged(hash) => {
+            app_state.log.add_titled(
+                "Game update",
+                format!(
+                    "build {} downloaded, it will be installed when the game exits",
+                    short_hash(&hash)
+                ),
+            );
+        }
+        DownloadEvent::GameUpdateInstalled(hash) => {
+            app_state.log.add_success(
+                "Game update",
+                format!("Updated to build {} for next launch", short_hash(&hash)),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::troubleshoot::{Check, Outcome};
+    use crate::ui::log::Entry;
+
+    #[test]
+    fn test_offline_error_marks_remote_hash_unavailable() {
+        let mut app_state = AppState::init();
+        handle(&mut app_state, DownloadEvent::AccessingOnlineHash);
+        assert_eq!(app_state.log.remote_hash_msg.as_deref(), Some("accessing"));
+        handle(
+            &mut app_state,
+            DownloadEvent::OfflineError("no route to host".into()),
+        );
+        assert_eq!(
+            app_state.log.remote_hash_msg.as_deref(),
+            Some("unavailable")
+        );
+    }
+
+    #[test]
+    fn test_download_error_offers_troubleshooting() {
+        let mut app_state = AppState::init();
+        handle(
+            &mut app_state,
+            DownloadEvent::BinaryDownloadError("connection reset".into()),
+        );
+        assert_eq!(app_state.troubleshoot_offer, Some(Problem::Download));
+        assert_eq!(app_state.start_troubleshooting(), Some(Problem::Download));
+        app_state.record_check(Check::DiskSpace, Outcome::Passed("20480 MiB free".into()));
+        let wizard = app_state.wizard.as_ref().unwrap();
+        assert_eq!(wizard.results[0], (Check::Connectivity, None));
+        assert_eq!(
+            wizard.results[1],
+            (
+                Check::DiskSpace,
+                Some(Outcome::Passed("20480 MiB free".into()))
+            )
+        );
+    }
+
+    #[test]
+    fn test_differing_hashes_are_aligned_and_marked() {
+        let mut app_state = AppState::init();
+        handle(&mut app_state, DownloadEvent::RemoteHash("abc123".into()));
+        handle(&mut app_state, DownloadEvent::LocalHash("abd12".into()));
+        let diff: Vec<_> = app_state
+            .log
+            .entries()
+            .into_iter()
+            .filter_map(|entry| match entry {
+                Entry::HashDiff { text, marks, .. } => Some((text, marks)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(diff.len(), 2);
+        assert_eq!(diff[0].0, "abc123");
+        assert_eq!(diff[0].1, [false, false, true, false, false, true]);
+        assert_eq!(diff[1].0, " abd12");
+        assert_eq!(diff[1].1, [false, false, false, true, false, false]);
+    }
+}
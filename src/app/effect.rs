@@ -0,0 +1,265 @@
+use crate::bus;
+use crate::capture;
+use crate::event::{DownloadEvent, Event};
+use crate::install_record;
+use crate::launcher;
+use crate::notes;
+use crate::priority;
+use crate::profile;
+use crate::session_log;
+use crate::troubleshoot::{self, Problem};
+use crate::ui::{AppState, UpdateStatus, VersionsMenu};
+use crate::versions;
+use color_eyre::Result;
+use ratatui::prelude::*;
+use std::process::Command;
+use std::sync::mpsc;
+use std::thread;
+
+use super::AppExit;
+
+/// Work outside of the app state that handling an event asks for. Handlers
+/// only update the state and return these, the main loop runs them after
+pub enum Effect {
+    Exit(AppExit),
+    /// Queue another event, e.g. the next step of the update flow
+    Send(Event),
+    ResizeTerminal,
+    /// Game output lines were added to the panes
+    OutputDelivered(usize),
+    /// A console line for the game's stdin
+    WriteToGame(String),
+    /// Ask the running game to quit, killing it if it doesn't
+    StopGame,
+    /// Start the installed build on the launcher thread
+    LaunchGame,
+    /// Stop capturing the running game's output and let it run on its own
+    ReleaseGame,
+    /// Tell the launcher thread whether it may download
+    AnswerDownload(mpsc::Sender<bool>, bool),
+    /// Tell the launcher thread to start the game now, or not at all
+    AnswerLaunch(mpsc::Sender<bool>, bool),
+    /// Shut the computer down through logind, queued for after a download
+    PowerOff,
+    /// Run the troubleshooting checks for an error in the background
+    Troubleshoot(Problem),
+    /// Open the list of downloaded builds
+    ShowVersions,
+    /// Install and pin a downloaded build, or follow updates again with `None`
+    PickVersion(Option<String>),
+    /// Launch this program from the install from now on, or the build with `None`
+    SetLaunchTarget(Option<String>),
+    /// Put the original build with this hash back over a modified one
+    RestoreInstall(String),
+    /// Save game output lines as a snippet or append them to the notes
+    SaveSelection {
+        lines: Vec<String>,
+        append: bool,
+    },
+    /// Save the game's last error lines after it exited like this
+    SaveCrashSnippet(String),
+    /// Write the logs of this session to a file
+    ExportLogs {
+        launcher: Vec<String>,
+        stdout: Vec<String>,
+        stderr: Vec<String>,
+    },
+    DownloadLauncherUpdate(String),
+    ApplyLauncherUpdate(String),
+    StageLauncherUpdate(String),
+    DiscardLauncherUpdate(String),
+}
+
+/// Run an effect, results that matter to the UI are written back into the state.
+/// Returns how the launcher should exit, if the effect ends the main loop
+pub fn run(
+    terminal: &mut Terminal<impl Backend>,
+    app_state: &mut AppState,
+    tx: &bus::Sender,
+    effect: Effect,
+) -> Result<Option<AppExit>> {
+    match effect {
+        Effect::Exit(exit) => return Ok(Some(exit)),
+        Effect::Send(event) => {
+            let _ = tx.send(event);
+        }
+        Effect::ResizeTerminal => {
+            terminal.autoresize()?;
+        }
+        Effect::OutputDelivered(count) => capture::delivered(count),
+        Effect::WriteToGame(line) => {
+            if let Some(stdin) = &app_state.game_stdin
+                && stdin.send(line).is_err()
+            {
+                app_state.game_input_closed();
+            }
+        }
+        Effect::PowerOff => {
+            app_state.log.add_text("Shutting down the PC...");
+            match Command::new("systemctl").arg("poweroff").status() {
+                Ok(status) if status.success() => {}
+                Ok(status) => app_state
+                    .log
+                    .add_error("Shutdown failed", format!("systemctl poweroff: {status}")),
+                Err(e) => app_state
+                    .log
+                    .add_error("Shutdown failed", format!("Can't run systemctl: {e}")),
+            }
+        }
+        Effect::StopGame => {
+            launcher::stop_game();
+            app_state
+                .log
+                .add_text("Asking the game to quit, it's killed if it doesn't in a few seconds...");
+        }
+        Effect::LaunchGame => {
+            let tx = tx.clone();
+            thread::spawn(move || launcher::launch_installed(tx));
+        }
+        Effect::ReleaseGame => match launcher::release_game() {
+            Ok(()) => {
+                app_state.game_stdin = None;
+                app_state.stdin_input = None;
+                app_state.log.add_warning(
+                    "Game released",
+                    "its output is no longer recorded, the launcher can be closed",
+                );
+            }
+            Err(e) => app_state.log.add_error("Release failed", format!("{e}")),
+        },
+        Effect::AnswerDownload(answer, allowed) | Effect::AnswerLaunch(answer, allowed) => {
+            let _ = answer.send(allowed);
+        }
+        Effect::Troubleshoot(problem) => troubleshoot::start(problem, tx),
+        // Kiosk machines stay on the build the launcher picks
+        Effect::ShowVersions if app_state.kiosk => {}
+        Effect::ShowVersions => {
+            app_state.versions_menu = Some(VersionsMenu {
+                builds: versions::list(),
+                installed: install_record::installed_hash(),
+                pinned: versions::pinned(),
+                selected: 0,
+            });
+        }
+        Effect::PickVersion(hash) => {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                let picked = match &hash {
+                    Some(hash) => launcher::install_version(hash, &tx),
+                    None => versions::pin(None),
+                };
+                let event = match picked {
+                    Ok(()) => DownloadEvent::VersionPicked(hash).into(),
+                    Err(e) => Event::LauncherError(format!("Failed to switch versions: {e}")),
+                };
+                let _ = tx.send(event);
+            });
+        }
+        Effect::RestoreInstall(hash) => {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                let event = match launcher::restore_install(&hash, &tx) {
+                    Ok(()) => DownloadEvent::InstallRestored(hash).into(),
+                    Err(e) => Event::LauncherError(format!("Failed to restore the game: {e}")),
+                };
+                let _ = tx.send(event);
+            });
+        }
+        Effect::SetLaunchTarget(target) => {
+            let name = target
+                .as_deref()
+                .unwrap_or_else(|| profile::current().artifact());
+            app_state
+                .log
+                .add_titled("Launch target", format!("{name} is launched from now on"));
+            launcher::set_launch_target(target);
+        }
+        Effect::SaveCrashSnippet(status) => {
+            let lines = capture::recent_errors();
+            if let Some(last) = lines.last() {
+                match notes::save_crash_snippet(&status, &lines) {
+                    // The last line usually names the problem, the rest is in the file
+                    Ok(path) => app_state.log.add_warning(
+                        "Crash snippet",
+                        format!("{last} ({} lines in {})", lines.len(), path.display()),
+                    ),
+                    Err(e) => app_state
+                        .log
+                        .add_error("Error", format!("Failed to save crash snippet: {e}")),
+                }
+            }
+        }
+        Effect::SaveSelection { lines, append } => {
+            let saved = if append {
+                notes::append_to_notes(&lines)
+            } else {
+                notes::save_snippet(&lines)
+            };
+            match saved {
+                Ok(path) => {
+                    app_state.stdout_selection = None;
+                    app_state
+                        .log
+                        .add_success("Selection saved", path.display().to_string());
+                }
+                Err(e) => app_state
+                    .log
+                    .add_error("Error", format!("Failed to save selection: {e}")),
+            }
+        }
+        Effect::ExportLogs {
+            launcher,
+            stdout,
+            stderr,
+        } => match session_log::export(&launcher, &stdout, &stderr) {
+            Ok(path) => app_state
+                .log
+                .add_success("Logs exported", path.display().to_string()),
+            Err(e) => app_state
+                .log
+                .add_error("Error", format!("Failed to export logs: {e}")),
+        },
+        Effect::DownloadLauncherUpdate(version) => {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                priority::lower_current_thread();
+                if let Err(e) = crate::update::update_launcher(&version, &tx) {
+                    let _ = tx.send(Event::LauncherError(format!(
+                        "Failed to update launcher: {e}"
+                    )));
+                }
+            });
+        }
+        Effect::ApplyLauncherUpdate(version) => match crate::update::apply_update(&version, tx) {
+            Ok(()) => return Ok(Some(AppExit::Restart)),
+            Err(e) => {
+                app_state.update_status = UpdateStatus::Downloaded;
+                app_state
+                    .log
+                    .add_error("Error", format!("Failed to apply update: {e}"));
+            }
+        },
+        Effect::StageLauncherUpdate(version) => {
+            match crate::update::stage_update_for_next_start(&version) {
+                Ok(()) => {
+                    app_state.update_status = UpdateStatus::Staged;
+                    app_state.log.launcher_status_msg =
+                        Some(format!("{version} will be applied on next start"));
+                }
+                Err(e) => {
+                    app_state
+                        .log
+                        .add_error("Error", format!("Failed to stage update: {e}"));
+                }
+            }
+        }
+        Effect::DiscardLauncherUpdate(version) => {
+            if let Err(e) = crate::update::discard_update(&version) {
+                app_state
+                    .log
+                    .add_error("Error", format!("Failed to discard update: {e}"));
+            }
+        }
+    }
+    Ok(None)
+}
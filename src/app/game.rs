@@ -0,0 +1,263 @@
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+
+use crate::event::GameEvent;
+use crate::profile;
+use crate::session_log;
+use crate::troubleshoot::Problem;
+use crate::ui::log::OutputLine;
+use crate::ui::{AppState, LaunchCountdown, QueuedAction, format_file_size};
+
+use super::download::short_hash;
+use super::effect::Effect;
+
+/// Handle the game process starting, exiting and printing output
+pub fn handle(app_state: &mut AppState, event: GameEvent) -> Vec<Effect> {
+    match event {
+        GameEvent::Countdown { until, answer } => {
+            if app_state.take_queued_action(QueuedAction::LaunchGame) {
+                return vec![Effect::AnswerLaunch(answer, true)];
+            }
+            if app_state.take_queued_action(QueuedAction::PowerOff) {
+                return vec![Effect::AnswerLaunch(answer, false), Effect::PowerOff];
+            }
+            app_state.launch_countdown = Some(LaunchCountdown { until, answer });
+        }
+        GameEvent::ReadyToPlay(play) => {
+            app_state.launch_countdown = None;
+            app_state.log.add_success("Ready", "The game is up to date");
+            if app_state.take_queued_action(QueuedAction::LaunchGame) {
+                let _ = play.send(());
+                return Vec::new();
+            }
+            app_state.ready_to_play = Some(play);
+            if app_state.take_queued_action(QueuedAction::PowerOff) {
+                return vec![Effect::PowerOff];
+            }
+        }
+        GameEvent::Launching => {
+            app_state.launch_again = false;
+            app_state.launch_countdown = None;
+            app_state.ready_to_play = None;
+            app_state.game_launched = true;
+            app_state.log.add_text("Launching the game...");
+            if let Some(path) = session_log::mirror_path() {
+                app_state
+                    .log
+                    .add_titled("Game output", format!("tail -f {}", path.display()));
+            }
+        }
+        GameEvent::ServerRestarting(exit_status) => {
+            app_state.log.add_warning(
+                "Server",
+                format!("{exit_status}, restarting in a few seconds"),
+            );
+        }
+        GameEvent::FallingBack { crashed, good } => {
+            app_state.log.add_warning(
+                "Game crashed on start",
+                format!(
+                    "build {} failed right after launch, running the last working build {}",
+                    short_hash(&crashed),
+                    short_hash(&good)
+                ),
+            );
+        }
+        GameEvent::StartupStalled(timeout) => {
+            app_state.log.add_warning(
+                "Game start",
+                format!(
+                    "No output {}s after launch, the game appears to have failed to start",
+                    timeout.as_secs()
+                ),
+            );
+            app_state.stalled_popup = true;
+        }
+        GameEvent::Environment(report) => {
+            // The full list goes to the session log, the overrides are what usually matters
+            let mut summary = format!(
+                "working directory {}, {} inherited variables",
+                report.working_dir.display(),
+                report.inherited.len()
+            );
+            if !report.overrides.is_empty() {
+                summary = format!("{summary}, {}", report.overrides.join(", "));
+            }
+            app_state.log.add_titled("Game environment", summary);
+            let args = if report.args.is_empty() {
+                "none".to_string()
+            } else {
+                report.command_line()
+            };
+            app_state.log.add_titled("Game arguments", args);
+        }
+        GameEvent::ExecutionError(err) => {
+            app_state.log.add_error("Execution error", err);
+            app_state.offer_troubleshooting(Problem::Launch);
+        }
+        GameEvent::Exited(status) => {
+            app_state.game_launched = false;
+            app_state.game_stdin = None;
+            app_state.stdin_input = None;
+            app_state.release_popup = false;
+            app_state.terminate_popup = false;
+            // A crashed server is restarted by the launcher thread
+            app_state.launch_again = status.success() || !profile::current().restarts_on_crash();
+            if status.success() {
+                app_state
+                    .log
+                    .add_success("Game exited", describe_exit(status));
+            } else {
+                app_state
+                    .log
+                    .add_warning("Game exited", describe_exit(status));
+                return vec![Effect::SaveCrashSnippet(describe_exit(status))];
+            }
+        }
+        GameEvent::StdinReady(stdin) => {
+            app_state.game_stdin = Some(stdin);
+        }
+        GameEvent::Output(lines) => {
+            // It's alive after all
+            app_state.stalled_popup = false;
+            let count = lines.len();
+            for line in &lines {
+                app_state.fps.record_line(line);
+            }
+            app_state
+                .game_stdout
+                .extend(lines.into_iter().map(OutputLine::Text));
+            return vec![Effect::OutputDelivered(count)];
+        }
+        GameEvent::ErrorOutput(lines) => {
+            app_state.stalled_popup = false;
+            let count = lines.len();
+            for line in lines {
+                app_state.push_game_stderr(line);
+            }
+            return vec![Effect::OutputDelivered(count)];
+        }
+        GameEvent::ServerLatency(report) => {
+            app_state.log.latency_msg = report.map(|report| {
+                let latency = report.latency.map_or_else(
+                    || "unreachable".to_string(),
+                    |latency| format!("{} ms", latency.as_millis()),
+                );
+                format!("{latency}, {}% loss", report.loss_percent)
+            });
+        }
+        GameEvent::Usage(Some(report)) => {
+            app_state.log.usage_msg = Some(format!(
+                "{} RAM, {:.0}% CPU",
+                format_file_size(report.rss),
+                report.cpu_percent
+            ));
+            app_state.game_usage = Some(report);
+        }
+        // Kept in the log, and so in the session log, for "it uses 9 GB" reports
+        GameEvent::Usage(None) => {
+            app_state.log.usage_msg = None;
+            if let Some(report) = app_state.game_usage.take() {
+                app_state.log.add_titled(
+                    "Game resources",
+                    format!(
+                        "peak {} RAM, {:.0}% CPU",
+                        format_file_size(report.peak_rss),
+                        report.peak_cpu_percent
+                    ),
+                );
+            }
+        }
+        GameEvent::OutputDropped(count) => {
+            app_state.log.add_warning(
+                "Game output",
+                format!("{count} lines dropped, the game prints faster than they can be shown"),
+            );
+        }
+    }
+    Vec::new()
+}
+
+fn describe_exit(status: ExitStatus) -> String {
+    match (status.code(), status.signal()) {
+        (Some(0), _) => "normally".to_string(),
+        (Some(code), _) => format!("with code {code}"),
+        (None, Some(signal)) => format!("killed by signal {signal}"),
+        (None, None) => format!("{status}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_batches_are_appended_in_order() {
+        let mut app_state = AppState::init();
+        let effects = handle(
+            &mut app_state,
+            GameEvent::Output(vec!["a".into(), "b".into()]),
+        );
+        assert!(matches!(effects.as_slice(), [Effect::OutputDelivered(2)]));
+        handle(&mut app_state, GameEvent::Output(vec!["c".into()]));
+        let lines: Vec<_> = app_state
+            .game_stdout
+            .iter()
+            .map(|line| match line {
+                OutputLine::Text(text) => text.as_str(),
+                OutputLine::Marker(_) => "marker",
+            })
+            .collect();
+        assert_eq!(lines, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_late_output_dismisses_stalled_start() {
+        let mut app_state = AppState::init();
+        handle(
+            &mut app_state,
+            GameEvent::StartupStalled(std::time::Duration::from_secs(30)),
+        );
+        assert!(app_state.stalled_popup);
+        handle(&mut app_state, GameEvent::ErrorOutput(vec!["slow".into()]));
+        assert!(!app_state.stalled_popup);
+    }
+
+    #[test]
+    fn test_exit_is_reported_and_offers_another_launch() {
+        let mut app_state = AppState::init();
+        handle(&mut app_state, GameEvent::Launching);
+        assert!(!app_state.launch_again);
+        let effects = handle(
+            &mut app_state,
+            GameEvent::Exited(ExitStatus::from_raw(1 << 8)),
+        );
+        assert!(
+            matches!(effects.as_slice(), [Effect::SaveCrashSnippet(status)] if status == "with code 1")
+        );
+        assert!(app_state.launch_again);
+        assert!(!app_state.game_launched);
+        assert_eq!(describe_exit(ExitStatus::from_raw(1 << 8)), "with code 1");
+        assert_eq!(describe_exit(ExitStatus::from_raw(9)), "killed by signal 9");
+    }
+
+    #[test]
+    fn test_queued_power_off_skips_the_launch() {
+        let mut app_state = AppState::init();
+        app_state.toggle_queued_action(QueuedAction::LaunchGame);
+        app_state.toggle_queued_action(QueuedAction::PowerOff);
+        let (answer, _answered) = std::sync::mpsc::channel();
+        let effects = handle(
+            &mut app_state,
+            GameEvent::Countdown {
+                until: std::time::Instant::now(),
+                answer,
+            },
+        );
+        assert!(matches!(
+            effects.as_slice(),
+            [Effect::AnswerLaunch(_, false), Effect::PowerOff]
+        ));
+        assert!(app_state.queued_actions.is_empty());
+    }
+}
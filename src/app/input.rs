@@ -0,0 +1,862 @@
+use crate::event::{Event, InputEvent, UpdateEvent};
+use crate::profile;
+use crate::ui::log::OutputLine;
+use crate::ui::{
+    AppState, DisplayMode, ExitPopupState, TerminalFocus, UpdateChoice, UpdateStatus,
+    launcher_log_lines,
+};
+use crossterm::event::KeyCode;
+use gilrs::{Axis, Button};
+
+use super::AppExit;
+use super::effect::Effect;
+
+/// Handle user input, mostly navigation and popups
+pub fn handle(app_state: &mut AppState, event: InputEvent) -> Vec<Effect> {
+    let mut effects = Vec::new();
+    match event {
+        InputEvent::Key(event) => {
+            app_state.keyboard_input_used();
+            if handle_keyboard_input(app_state, &mut effects, event.code) {
+                effects.push(Effect::Exit(AppExit::Quit));
+            }
+        }
+        InputEvent::ControllerInput(button) => {
+            app_state.controller_input_used();
+            if app_state.terminal_focus == TerminalFocus::Focused
+                && handle_controller_input(app_state, &mut effects, button)
+            {
+                effects.push(Effect::Exit(AppExit::Quit));
+            }
+        }
+        InputEvent::ControllerHold(button) => {
+            app_state.controller_input_used();
+            if app_state.terminal_focus == TerminalFocus::Focused {
+                handle_controller_hold(app_state, &mut effects, button);
+            }
+        }
+        InputEvent::ControllerChord(button) => {
+            app_state.controller_input_used();
+            if app_state.terminal_focus == TerminalFocus::Focused
+                && button == Button::Start
+                && app_state.update_popup.is_none()
+                && app_state.actions_menu.is_none()
+                && app_state.launch_countdown.is_none()
+                && !popup_open(app_state)
+            {
+                export_logs(app_state, &mut effects);
+            }
+        }
+        InputEvent::ControllerAxisMoved(axis, value) => {
+            app_state.controller_input_used();
+            if app_state.terminal_focus == TerminalFocus::Focused {
+                handle_controller_axis(app_state, axis, value);
+            }
+        }
+        InputEvent::TerminalFocusChanged(focused) => {
+            app_state.set_terminal_focus(focused);
+        }
+        InputEvent::Resize => effects.push(Effect::ResizeTerminal),
+        InputEvent::TrayQuitRequested if !app_state.kiosk => {
+            effects.push(Effect::Exit(AppExit::Quit));
+        }
+        InputEvent::TrayQuitRequested => {}
+        InputEvent::TrayLaunchRequested => {
+            answer_countdown(app_state, &mut effects, true);
+            play(app_state);
+            if app_state.launch_again {
+                launch_again(app_state, &mut effects);
+            }
+        }
+        InputEvent::Tick => {}
+    }
+    effects
+}
+
+/// Handle keyboard input based on current app state
+/// Returns true if the application should exit
+fn handle_keyboard_input(
+    app_state: &mut AppState,
+    effects: &mut Vec<Effect>,
+    key: KeyCode,
+) -> bool {
+    if app_state.exit_popup == ExitPopupState::Visible {
+        match key {
+            // Confirm exit
+            KeyCode::Enter | KeyCode::Char('y') => {
+                return true;
+            }
+            // Cancel exit
+            KeyCode::Esc | KeyCode::Char('n' | 'q') => {
+                app_state.hide_exit_popup();
+            }
+            _ => {}
+        }
+    } else if app_state.notice.is_some() {
+        if matches!(key, KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q')) {
+            app_state.notice = None;
+        }
+    } else if app_state.troubleshoot_offer.is_some() {
+        match key {
+            KeyCode::Enter | KeyCode::Char('y') => start_troubleshooting(app_state, effects),
+            KeyCode::Esc | KeyCode::Char('n' | 'q') => app_state.troubleshoot_offer = None,
+            _ => {}
+        }
+    } else if app_state.wizard.is_some() {
+        // The checks keep running, their results land in the log
+        if matches!(key, KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q')) {
+            app_state.wizard = None;
+        }
+    } else if let Some(line) = &mut app_state.stdin_input {
+        match key {
+            // The prompt stays open for the next command
+            KeyCode::Enter => {
+                let line = std::mem::take(line);
+                if app_state.echo_console_line(&line) {
+                    effects.push(Effect::WriteToGame(line));
+                }
+            }
+            KeyCode::Esc => {
+                app_state.stdin_input = None;
+            }
+            key => edit_text(line, key),
+        }
+    } else if let Some(name) = &mut app_state.marker_input {
+        match key {
+            KeyCode::Enter => {
+                let name = name.trim().to_string();
+                app_state.add_marker(name);
+            }
+            KeyCode::Esc => {
+                app_state.marker_input = None;
+            }
+            key => edit_text(name, key),
+        }
+    } else if let Some(pane) = app_state.clear_popup {
+        match key {
+            KeyCode::Enter | KeyCode::Char('y') => {
+                app_state.clear_pane(pane);
+                app_state.hide_clear_popup();
+            }
+            KeyCode::Esc | KeyCode::Char('n' | 'q') => {
+                app_state.hide_clear_popup();
+            }
+            _ => {}
+        }
+    } else if app_state.terminate_popup {
+        match key {
+            KeyCode::Enter | KeyCode::Char('y') => {
+                app_state.terminate_popup = false;
+                effects.push(Effect::StopGame);
+            }
+            KeyCode::Esc | KeyCode::Char('n' | 'q') => {
+                app_state.terminate_popup = false;
+            }
+            _ => {}
+        }
+    } else if app_state.release_popup {
+        match key {
+            KeyCode::Enter | KeyCode::Char('y') => {
+                app_state.release_popup = false;
+                effects.push(Effect::ReleaseGame);
+            }
+            KeyCode::Esc | KeyCode::Char('n' | 'q') => {
+                app_state.release_popup = false;
+            }
+            _ => {}
+        }
+    } else if app_state.stalled_popup {
+        match key {
+            KeyCode::Enter | KeyCode::Char('y') => {
+                app_state.stalled_popup = false;
+                effects.push(Effect::StopGame);
+            }
+            // Keep waiting
+            KeyCode::Esc | KeyCode::Char('n' | 'q') => {
+                app_state.stalled_popup = false;
+            }
+            _ => {}
+        }
+    } else if let Some(menu) = &mut app_state.versions_menu {
+        match key {
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Tab => menu.next(),
+            KeyCode::Up | KeyCode::Char('k') | KeyCode::BackTab => menu.prev(),
+            KeyCode::Enter => pick_version(app_state, effects),
+            KeyCode::Esc | KeyCode::Char('q' | 'v') => app_state.versions_menu = None,
+            _ => {}
+        }
+    } else if let Some(selected) = app_state.targets_menu {
+        let count = profile::current().launch_targets().len();
+        match key {
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Tab => {
+                app_state.targets_menu = Some((selected + 1) % count);
+            }
+            KeyCode::Up | KeyCode::Char('k') | KeyCode::BackTab => {
+                app_state.targets_menu = Some((selected + count - 1) % count);
+            }
+            KeyCode::Enter => pick_target(app_state, effects),
+            KeyCode::Esc | KeyCode::Char('q' | 't') => app_state.targets_menu = None,
+            _ => {}
+        }
+    } else if let Some(selected) = app_state.actions_menu {
+        match key {
+            KeyCode::Down | KeyCode::Up | KeyCode::Char('j' | 'k') | KeyCode::Tab => {
+                app_state.actions_menu = Some(selected.other());
+            }
+            KeyCode::Enter | KeyCode::Char(' ') => app_state.toggle_queued_action(selected),
+            KeyCode::Esc | KeyCode::Char('q' | 'a') => app_state.actions_menu = None,
+            _ => {}
+        }
+    } else if app_state.launch_countdown.is_some() {
+        match key {
+            KeyCode::Enter | KeyCode::Char('y') => answer_countdown(app_state, effects, true),
+            KeyCode::Esc | KeyCode::Char('n' | 'q' | 'x') => {
+                answer_countdown(app_state, effects, false);
+            }
+            _ => {}
+        }
+    } else if app_state.download_popup.is_some() {
+        match key {
+            KeyCode::Enter | KeyCode::Char('y') => answer_download(app_state, effects, true),
+            KeyCode::Esc | KeyCode::Char('n' | 'q') => answer_download(app_state, effects, false),
+            _ => {}
+        }
+    } else if let Some(hash) = &app_state.restore_popup {
+        match key {
+            KeyCode::Enter | KeyCode::Char('y') => {
+                effects.push(Effect::RestoreInstall(hash.clone()));
+                app_state.restore_popup = None;
+            }
+            // Keep the modified build
+            KeyCode::Esc | KeyCode::Char('n' | 'q') => {
+                app_state.restore_popup = None;
+            }
+            _ => {}
+        }
+    } else if let Some(choice) = app_state.update_popup {
+        match key {
+            KeyCode::Down | KeyCode::Right | KeyCode::Char('j') | KeyCode::Tab => {
+                app_state.next_update_choice();
+            }
+            KeyCode::Up | KeyCode::Left | KeyCode::Char('k') | KeyCode::BackTab => {
+                app_state.prev_update_choice();
+            }
+            KeyCode::Enter => {
+                confirm_update_choice(app_state, effects, choice);
+            }
+            // Decide later, the update stays downloaded
+            KeyCode::Esc | KeyCode::Char('q') => {
+                app_state.hide_update_popup();
+            }
+            _ => {}
+        }
+    } else if let DisplayMode::Fullscreen(_) = app_state.display_mode {
+        // In fullscreen mode, Escape/h/q return to normal view
+        match key {
+            // Drop a stdout selection before leaving
+            KeyCode::Esc if app_state.stdout_selection.is_some() => {
+                app_state.toggle_stdout_selection();
+            }
+            KeyCode::Esc | KeyCode::Char('h' | 'q') => {
+                app_state.exit_fullscreen();
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                app_state.scroll_up();
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                app_state.scroll_down();
+            }
+            KeyCode::Char('c') => {
+                app_state.show_clear_popup();
+            }
+            KeyCode::Char('p') => {
+                app_state.toggle_previous_session();
+            }
+            KeyCode::Char('f') => {
+                app_state.cycle_severity_filter();
+            }
+            KeyCode::Char('v') => {
+                app_state.toggle_stdout_selection();
+            }
+            KeyCode::Char('m') => {
+                app_state.marker_input = Some(String::new());
+            }
+            KeyCode::Char('i') => {
+                app_state.open_stdin_prompt();
+            }
+            KeyCode::Char('s') if app_state.stdout_selection.is_some() => {
+                save_stdout_selection(app_state, effects, false);
+            }
+            KeyCode::Char('s') => {
+                export_logs(app_state, effects);
+            }
+            KeyCode::Char('a') => {
+                save_stdout_selection(app_state, effects, true);
+            }
+            KeyCode::F(12) => {
+                app_state.show_perf = !app_state.show_perf;
+            }
+            _ => {}
+        }
+    } else {
+        // In normal mode
+        match key {
+            // Show exit confirmation popup
+            KeyCode::Char('q') | KeyCode::Esc => {
+                app_state.show_exit_popup();
+            }
+            KeyCode::Enter if app_state.ready_to_play.is_some() => play(app_state),
+            KeyCode::Enter if app_state.launch_again => launch_again(app_state, effects),
+            // Enter fullscreen with Enter/l
+            KeyCode::Enter | KeyCode::Char('l') => {
+                app_state.enter_fullscreen(20); // Default visible height, will be updated in draw
+            }
+            // Navigation with arrow keys and j/k
+            KeyCode::Right | KeyCode::Down | KeyCode::Char('j') | KeyCode::Tab => {
+                app_state.next_log();
+            }
+            KeyCode::Left | KeyCode::Up | KeyCode::Char('k') | KeyCode::BackTab => {
+                app_state.prev_log();
+            }
+            // Request launcher update
+            KeyCode::Char('u') => {
+                request_launcher_update(app_state, effects);
+            }
+            // Ask to clear the focused pane
+            KeyCode::Char('c') => {
+                app_state.show_clear_popup();
+            }
+            // Hide launcher log entries below a severity
+            KeyCode::Char('f') => {
+                app_state.cycle_severity_filter();
+            }
+            // Name and drop a marker into the logs
+            KeyCode::Char('m') => {
+                app_state.marker_input = Some(String::new());
+            }
+            // Queue what happens once the download completes
+            KeyCode::Char('a') => {
+                app_state.show_actions_menu();
+            }
+            // Pick an older downloaded build
+            KeyCode::Char('v') => {
+                effects.push(Effect::ShowVersions);
+            }
+            // Launch another program from the install, e.g. the editor
+            KeyCode::Char('t') => {
+                app_state.show_targets_menu();
+            }
+            // Type commands for the game's stdin
+            KeyCode::Char('i') => {
+                app_state.open_stdin_prompt();
+            }
+            // Dump the logs of this session to a file
+            KeyCode::Char('s') => {
+                export_logs(app_state, effects);
+            }
+            // Ask to stop the running game
+            KeyCode::Char('x') if app_state.game_launched => {
+                app_state.terminate_popup = true;
+            }
+            // Let the game run on its own, e.g. to close the terminal
+            KeyCode::Char('r') if app_state.game_stdin.is_some() && !app_state.kiosk => {
+                app_state.release_popup = true;
+            }
+            // Version and where the settings come from
+            KeyCode::Char('?') => {
+                app_state.show_about();
+            }
+            // Event loop measurements for debugging stutters
+            KeyCode::F(12) => {
+                app_state.show_perf = !app_state.show_perf;
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Handle controller input based on current app state
+/// Returns true if the application should exit
+fn handle_controller_input(
+    app_state: &mut AppState,
+    effects: &mut Vec<Effect>,
+    button: Button,
+) -> bool {
+    if app_state.exit_popup == ExitPopupState::Visible {
+        // Handle controller input while exit popup is active
+        match button {
+            // Confirm exit with A button
+            Button::South => {
+                return true;
+            }
+            // Cancel exit with B button
+            Button::East => {
+                app_state.hide_exit_popup();
+            }
+            _ => {}
+        }
+    } else if app_state.notice.is_some() {
+        if matches!(button, Button::South | Button::East) {
+            app_state.notice = None;
+        }
+    } else if app_state.troubleshoot_offer.is_some() {
+        match button {
+            Button::South => start_troubleshooting(app_state, effects),
+            Button::East => app_state.troubleshoot_offer = None,
+            _ => {}
+        }
+    } else if app_state.wizard.is_some() {
+        if matches!(button, Button::South | Button::East) {
+            app_state.wizard = None;
+        }
+    } else if let Some(pane) = app_state.clear_popup {
+        match button {
+            Button::South => {
+                app_state.clear_pane(pane);
+                app_state.hide_clear_popup();
+            }
+            Button::East => {
+                app_state.hide_clear_popup();
+            }
+            _ => {}
+        }
+    } else if app_state.terminate_popup {
+        match button {
+            Button::South => {
+                app_state.terminate_popup = false;
+                effects.push(Effect::StopGame);
+            }
+            Button::East => {
+                app_state.terminate_popup = false;
+            }
+            _ => {}
+        }
+    } else if app_state.release_popup {
+        match button {
+            Button::South => {
+                app_state.release_popup = false;
+                effects.push(Effect::ReleaseGame);
+            }
+            Button::East => {
+                app_state.release_popup = false;
+            }
+            _ => {}
+        }
+    } else if app_state.stalled_popup {
+        match button {
+            Button::South => {
+                app_state.stalled_popup = false;
+                effects.push(Effect::StopGame);
+            }
+            Button::East => {
+                app_state.stalled_popup = false;
+            }
+            _ => {}
+        }
+    } else if let Some(menu) = &mut app_state.versions_menu {
+        match button {
+            Button::DPadDown => menu.next(),
+            Button::DPadUp => menu.prev(),
+            Button::South => pick_version(app_state, effects),
+            Button::East | Button::LeftThumb => app_state.versions_menu = None,
+            _ => {}
+        }
+    } else if let Some(selected) = app_state.targets_menu {
+        let count = profile::current().launch_targets().len();
+        match button {
+            Button::DPadDown => app_state.targets_menu = Some((selected + 1) % count),
+            Button::DPadUp => app_state.targets_menu = Some((selected + count - 1) % count),
+            Button::South => pick_target(app_state, effects),
+            Button::East => app_state.targets_menu = None,
+            _ => {}
+        }
+    } else if let Some(selected) = app_state.actions_menu {
+        match button {
+            Button::DPadDown | Button::DPadUp => {
+                app_state.actions_menu = Some(selected.other());
+            }
+            Button::South => app_state.toggle_queued_action(selected),
+            Button::East | Button::RightThumb => app_state.actions_menu = None,
+            _ => {}
+        }
+    } else if app_state.launch_countdown.is_some() {
+        match button {
+            Button::South => answer_countdown(app_state, effects, true),
+            Button::East | Button::West => answer_countdown(app_state, effects, false),
+            _ => {}
+        }
+    } else if app_state.download_popup.is_some() {
+        match button {
+            Button::South => answer_download(app_state, effects, true),
+            Button::East => answer_download(app_state, effects, false),
+            _ => {}
+        }
+    } else if let Some(hash) = &app_state.restore_popup {
+        match button {
+            Button::South => {
+                effects.push(Effect::RestoreInstall(hash.clone()));
+                app_state.restore_popup = None;
+            }
+            Button::East => {
+                app_state.restore_popup = None;
+            }
+            _ => {}
+        }
+    } else if let Some(choice) = app_state.update_popup {
+        match button {
+            Button::DPadDown | Button::DPadRight => {
+                app_state.next_update_choice();
+            }
+            Button::DPadUp | Button::DPadLeft => {
+                app_state.prev_update_choice();
+            }
+            Button::South => {
+                confirm_update_choice(app_state, effects, choice);
+            }
+            Button::East => {
+                app_state.hide_update_popup();
+            }
+            _ => {}
+        }
+    } else if let DisplayMode::Fullscreen(_) = app_state.display_mode {
+        // In fullscreen mode, East (B) returns to normal view
+        match button {
+            Button::East => {
+                app_state.exit_fullscreen();
+            }
+            // Scrolling only in fullscreen mode
+            Button::DPadUp => {
+                app_state.scroll_up();
+            }
+            Button::DPadDown => {
+                app_state.scroll_down();
+            }
+            // Shoulder buttons for log navigation in fullscreen mode
+            Button::LeftTrigger => {
+                app_state.prev_log();
+            }
+            Button::RightTrigger => {
+                app_state.next_log();
+            }
+            // Triggers for scrolling to the max
+            Button::LeftTrigger2 => {
+                app_state.scroll_to_top();
+            }
+            Button::RightTrigger2 => {
+                app_state.scroll_to_bottom();
+            }
+            Button::West => {
+                app_state.show_clear_popup();
+            }
+            Button::North => {
+                app_state.toggle_previous_session();
+            }
+            Button::Select => {
+                app_state.cycle_severity_filter();
+            }
+            Button::Start => {
+                app_state.add_marker(String::new());
+            }
+            _ => {}
+        }
+    } else {
+        // In normal mode
+        match button {
+            // Show exit confirmation with East (B) button
+            Button::East => {
+                app_state.show_exit_popup();
+            }
+            Button::South if app_state.ready_to_play.is_some() => play(app_state),
+            Button::South if app_state.launch_again => launch_again(app_state, effects),
+            // Enter fullscreen with South (A) button
+            Button::South => {
+                app_state.enter_fullscreen(20); // Default visible height, will be updated in draw
+            }
+            // Request launcher update with North (Y) button
+            Button::North => {
+                request_launcher_update(app_state, effects);
+            }
+            // Ask to clear the focused pane with West (X) button
+            Button::West => {
+                app_state.show_clear_popup();
+            }
+            Button::Select => {
+                app_state.cycle_severity_filter();
+            }
+            // Drop a numbered marker into the logs
+            Button::Start => {
+                app_state.add_marker(String::new());
+            }
+            Button::RightThumb => {
+                app_state.show_actions_menu();
+            }
+            Button::LeftThumb => {
+                effects.push(Effect::ShowVersions);
+            }
+            // D-pad navigation
+            Button::DPadRight | Button::DPadDown | Button::RightTrigger => {
+                app_state.next_log();
+            }
+            Button::DPadLeft | Button::DPadUp | Button::LeftTrigger => {
+                app_state.prev_log();
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Typing into a single line text field
+fn edit_text(text: &mut String, key: KeyCode) {
+    match key {
+        KeyCode::Char(c) => text.push(c),
+        KeyCode::Backspace => {
+            text.pop();
+        }
+        _ => {}
+    }
+}
+
+/// Write the selected stdout lines to a new snippet file, or append them to
+/// the notes file, and report where they went
+fn save_stdout_selection(app_state: &AppState, effects: &mut Vec<Effect>, append: bool) {
+    if let Some(lines) = app_state.selected_stdout() {
+        let lines = lines.iter().map(ToString::to_string).collect();
+        effects.push(Effect::SaveSelection { lines, append });
+    }
+}
+
+/// Write the launcher log and all game output, including what the panes
+/// don't show, to a new export file
+fn export_logs(app_state: &AppState, effects: &mut Vec<Effect>) {
+    let lines = |output: &[OutputLine]| output.iter().map(ToString::to_string).collect();
+    effects.push(Effect::ExportLogs {
+        launcher: launcher_log_lines(&app_state.log),
+        stdout: lines(&app_state.game_stdout),
+        stderr: lines(&app_state.game_stderr),
+    });
+}
+
+/// Start downloading an available update, or reopen the choice popup
+/// for an update that is already downloaded
+fn request_launcher_update(app_state: &mut AppState, effects: &mut Vec<Effect>) {
+    if app_state.launcher_update_available.is_none() {
+        return;
+    }
+    match app_state.update_status {
+        UpdateStatus::NotRequested => {
+            effects.push(Effect::Send(UpdateEvent::Requested.into()));
+        }
+        UpdateStatus::Downloaded => app_state.show_update_popup(),
+        _ => {}
+    }
+}
+
+/// Route the selected update popup option through its event
+fn confirm_update_choice(
+    app_state: &mut AppState,
+    effects: &mut Vec<Effect>,
+    choice: UpdateChoice,
+) {
+    app_state.hide_update_popup();
+    let event = match choice {
+        UpdateChoice::ApplyAndRestart => UpdateEvent::ApplyNow,
+        UpdateChoice::ApplyOnNextStart => UpdateEvent::ApplyOnNextStart,
+        UpdateChoice::Discard => UpdateEvent::Discard,
+    };
+    effects.push(Effect::Send(event.into()));
+}
+
+// Dropping the sender would tell the launcher thread the launcher is closing
+fn play(app_state: &mut AppState) {
+    if let Some(play) = app_state.ready_to_play.take() {
+        let _ = play.send(());
+    }
+}
+
+fn launch_again(app_state: &mut AppState, effects: &mut Vec<Effect>) {
+    app_state.launch_again = false;
+    effects.push(Effect::Send(Event::RequestGameLaunch));
+}
+
+fn answer_countdown(app_state: &mut AppState, effects: &mut Vec<Effect>, launch_now: bool) {
+    if let Some(countdown) = app_state.launch_countdown.take() {
+        effects.push(Effect::AnswerLaunch(countdown.answer, launch_now));
+    }
+}
+
+fn pick_version(app_state: &mut AppState, effects: &mut Vec<Effect>) {
+    if let Some(menu) = app_state.versions_menu.take() {
+        effects.push(Effect::PickVersion(menu.choice()));
+    }
+}
+
+fn pick_target(app_state: &mut AppState, effects: &mut Vec<Effect>) {
+    if let Some(selected) = app_state.targets_menu.take() {
+        // The first target is the game build, launched without a choice
+        app_state.launch_target = profile::current()
+            .launch_targets()
+            .get(selected)
+            .filter(|_| selected > 0)
+            .map(|target| (*target).to_string());
+        effects.push(Effect::SetLaunchTarget(app_state.launch_target.clone()));
+    }
+}
+
+fn start_troubleshooting(app_state: &mut AppState, effects: &mut Vec<Effect>) {
+    if let Some(problem) = app_state.start_troubleshooting() {
+        effects.push(Effect::Troubleshoot(problem));
+    }
+}
+
+fn answer_download(app_state: &mut AppState, effects: &mut Vec<Effect>, allowed: bool) {
+    if let Some(question) = app_state.download_popup.take() {
+        effects.push(Effect::AnswerDownload(question.answer, allowed));
+    }
+}
+
+/// Handle a controller button held down instead of tapped
+fn handle_controller_hold(app_state: &mut AppState, effects: &mut Vec<Effect>, button: Button) {
+    if button == Button::Select
+        && app_state.display_mode == DisplayMode::Normal
+        && app_state.update_popup.is_none()
+        && app_state.actions_menu.is_none()
+        && app_state.launch_countdown.is_none()
+        && !popup_open(app_state)
+    {
+        effects.push(Effect::ShowVersions);
+    }
+}
+
+/// Handle controller analog stick movement
+fn handle_controller_axis(app_state: &mut AppState, axis: gilrs::Axis, value: f32) {
+    // Only handle axis events without a popup and based on app display mode
+    if popup_open(app_state) {
+        return;
+    }
+
+    match axis {
+        Axis::LeftStickX if app_state.display_mode == DisplayMode::Normal => {
+            if value > 0.0 {
+                // Right movement
+                app_state.next_log();
+            } else {
+                // Left movement
+                app_state.prev_log();
+            }
+        }
+        Axis::LeftStickY => {
+            // Only handle vertical scrolling in fullscreen mode
+            if let DisplayMode::Fullscreen(_) = app_state.display_mode {
+                if value > 0.0 {
+                    // Up movement
+                    app_state.scroll_up();
+                } else {
+                    // Down movement
+                    app_state.scroll_down();
+                }
+            } else if value > 0.0 {
+                // Up movement
+                app_state.next_log();
+            } else {
+                // Down movement
+                app_state.prev_log();
+            }
+        }
+        _ => {}
+    }
+}
+
+// A popup or prompt is waiting for input, the stick doesn't move behind it
+fn popup_open(app_state: &AppState) -> bool {
+    app_state.exit_popup == ExitPopupState::Visible
+        || app_state.clear_popup.is_some()
+        || app_state.stalled_popup
+        || app_state.release_popup
+        || app_state.terminate_popup
+        || app_state.restore_popup.is_some()
+        || app_state.download_popup.is_some()
+        || app_state.notice.is_some()
+        || app_state.troubleshoot_offer.is_some()
+        || app_state.wizard.is_some()
+        || app_state.versions_menu.is_some()
+        || app_state.targets_menu.is_some()
+        || app_state.marker_input.is_some()
+        || app_state.stdin_input.is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyEvent;
+
+    fn press(app_state: &mut AppState, key: KeyCode) -> Vec<Effect> {
+        handle(app_state, InputEvent::Key(KeyEvent::from(key)))
+    }
+
+    #[test]
+    fn test_quit_needs_confirmation() {
+        let mut app_state = AppState::init();
+        assert!(press(&mut app_state, KeyCode::Char('q')).is_empty());
+        assert!(app_state.exit_popup == ExitPopupState::Visible);
+        assert!(matches!(
+            press(&mut app_state, KeyCode::Char('y')).as_slice(),
+            [Effect::Exit(AppExit::Quit)]
+        ));
+    }
+
+    #[test]
+    fn test_kiosk_mode_can_not_be_quit() {
+        let mut app_state = AppState::init();
+        app_state.kiosk = true;
+        press(&mut app_state, KeyCode::Char('q'));
+        assert!(app_state.exit_popup == ExitPopupState::Hidden);
+        assert!(handle(&mut app_state, InputEvent::TrayQuitRequested).is_empty());
+        press(&mut app_state, KeyCode::Char('?'));
+        assert!(app_state.notice.is_some());
+    }
+
+    #[test]
+    fn test_enter_after_exit_requests_a_launch() {
+        let mut app_state = AppState::init();
+        app_state.launch_again = true;
+        assert!(matches!(
+            press(&mut app_state, KeyCode::Enter).as_slice(),
+            [Effect::Send(Event::RequestGameLaunch)]
+        ));
+        assert!(!app_state.launch_again);
+    }
+
+    #[test]
+    fn test_tray_launch_starts_the_waiting_game() {
+        let mut app_state = AppState::init();
+        let (play, played) = std::sync::mpsc::channel();
+        app_state.ready_to_play = Some(play);
+        handle(&mut app_state, InputEvent::TrayLaunchRequested);
+        assert!(played.try_recv().is_ok());
+        assert!(app_state.ready_to_play.is_none());
+
+        app_state.launch_again = true;
+        assert!(matches!(
+            handle(&mut app_state, InputEvent::TrayLaunchRequested).as_slice(),
+            [Effect::Send(Event::RequestGameLaunch)]
+        ));
+    }
+
+    #[test]
+    fn test_releasing_the_game_needs_confirmation() {
+        let mut app_state = AppState::init();
+        press(&mut app_state, KeyCode::Char('r'));
+        assert!(!app_state.release_popup, "no game is running");
+
+        let (stdin, _lines) = std::sync::mpsc::channel();
+        app_state.game_stdin = Some(stdin);
+        assert!(press(&mut app_state, KeyCode::Char('r')).is_empty());
+        assert!(matches!(
+            press(&mut app_state, KeyCode::Char('y')).as_slice(),
+            [Effect::ReleaseGame]
+        ));
+        assert!(!app_state.release_popup);
+    }
+}
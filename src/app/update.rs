@@ -0,0 +1,146 @@
+use crate::event::UpdateEvent;
+use crate::ui::AppState;
+use crate::ui::UpdateStatus;
+
+use super::effect::Effect;
+
+/// Handle the launcher update flow, from the version check to applying it
+pub fn handle(app_state: &mut AppState, event: UpdateEvent) -> Vec<Effect> {
+    let mut effects = Vec::new();
+    match event {
+        UpdateEvent::ManagedExternally => {
+            app_state.log.launcher_status_msg = Some(format!(
+                "{} - updates managed by your package manager",
+                crate::VERSION
+            ));
+        }
+        UpdateEvent::Disabled => {
+            app_state.log.launcher_status_msg = Some(format!(
+                "{} - updates turned off in the settings",
+                crate::VERSION
+            ));
+        }
+        UpdateEvent::ReadOnlyInstall(user_path) => {
+            app_state.log.launcher_status_msg = Some(format!(
+                "{} - on a read-only filesystem, copy it to {} to get updates",
+                crate::VERSION,
+                user_path.display()
+            ));
+        }
+        UpdateEvent::Checking => {
+            app_state.log.launcher_status_msg = Some("checking for a newer version".into());
+        }
+        UpdateEvent::Available(version) => {
+            // Get the current version from our crate
+            let current_version = crate::VERSION;
+            app_state.log.launcher_status_msg = Some(format!(
+                "an update is available {current_version} -> {version}"
+            ));
+            app_state.launcher_update_available = Some(version);
+        }
+        UpdateEvent::NoUpdateAvailable => {
+            // Include the current version in the status message
+            let current_version = crate::VERSION;
+            app_state.log.launcher_status_msg =
+                Some(format!("already at the latest version - {current_version}"));
+        }
+        UpdateEvent::Queued => {
+            app_state.log.launcher_status_msg =
+                Some("update queued until the game download finishes".into());
+        }
+        UpdateEvent::StartDownloading => {
+            // Create a download entry specifically for the launcher update
+            app_state.log.launcher_update = Some(crate::ui::log::Download::new(None));
+            if let Some(version) = &app_state.launcher_update_available {
+                app_state.log.launcher_status_msg = Some(format!("downloading {version}"));
+            }
+        }
+        UpdateEvent::DownloadProgress(downloaded, total) => {
+            if let Some(download) = &mut app_state.log.launcher_update {
+                // Update the download progress
+                download.set_progress(downloaded);
+
+                // If we haven't set the total yet and it's now available, set it
+                if download.total().is_none() && total.is_some() {
+                    download.set_total(total);
+                }
+            }
+        }
+        UpdateEvent::Verifying => {
+            if let Some(download) = &mut app_state.log.launcher_update {
+                download.mark_verifying();
+            }
+        }
+        UpdateEvent::Downloaded => {
+            if let Some(download) = &mut app_state.log.launcher_update {
+                download.mark_complete();
+            }
+            app_state.update_status = UpdateStatus::Downloaded;
+            // Restarting would cut the running game off from its output pipes
+            if app_state.auto_restart && !app_state.game_launched {
+                effects.push(Effect::Send(UpdateEvent::ApplyNow.into()));
+            } else {
+                app_state.show_update_popup();
+            }
+        }
+        UpdateEvent::Applying => {
+            app_state.log.launcher_status_msg = Some("applying update...".into());
+        }
+        UpdateEvent::Applied => {
+            app_state.update_status = UpdateStatus::Applied;
+            app_state.log.launcher_status_msg = Some("update applied, restarting...".into());
+        }
+        UpdateEvent::ApplyNow => {
+            if let Some(version) = &app_state.launcher_update_available {
+                effects.push(Effect::ApplyLauncherUpdate(version.clone()));
+            }
+        }
+        UpdateEvent::ApplyOnNextStart => {
+            if let Some(version) = &app_state.launcher_update_available {
+                effects.push(Effect::StageLauncherUpdate(version.clone()));
+            }
+        }
+        UpdateEvent::Discard => {
+            if let Some(version) = &app_state.launcher_update_available {
+                effects.push(Effect::DiscardLauncherUpdate(version.clone()));
+                app_state.update_status = UpdateStatus::NotRequested;
+                app_state.log.launcher_update = None;
+                app_state.log.launcher_status_msg = Some(format!("update to {version} discarded"));
+            }
+        }
+        UpdateEvent::Requested => {
+            // Start the update process if an update is available and not already in progress
+            if let Some(version) = &app_state.launcher_update_available
+                && app_state.update_status == UpdateStatus::NotRequested
+            {
+                // Mark that an update is in progress
+                app_state.update_status = UpdateStatus::Requested;
+                effects.push(Effect::DownloadLauncherUpdate(version.clone()));
+            }
+        }
+    }
+    effects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Event;
+
+    #[test]
+    fn test_downloaded_update_restarts_automatically_before_launch() {
+        let mut app_state = AppState::init();
+        app_state.auto_restart = true;
+        handle(&mut app_state, UpdateEvent::Available("1.2.3".into()));
+        assert_eq!(
+            app_state.launcher_update_available.as_deref(),
+            Some("1.2.3")
+        );
+        let effects = handle(&mut app_state, UpdateEvent::Downloaded);
+        assert!(app_state.update_status == UpdateStatus::Downloaded);
+        assert!(matches!(
+            effects.as_slice(),
+            [Effect::Send(Event::Update(UpdateEvent::ApplyNow))]
+        ));
+    }
+}
@@ -0,0 +1,74 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+use crate::dirs;
+
+const USAGE_FILE: &str = "bandwidth.json";
+
+/// Bytes downloaded in a calendar month, kept in `$XDG_STATE_HOME/GRAV` so
+/// players on a mobile hotspot can keep an eye on their data plan
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Usage {
+    /// Like `2024-05`
+    month: String,
+    bytes: u64,
+}
+
+static USAGE: Mutex<Option<Usage>> = Mutex::new(None);
+/// Downloads past this many bytes a month need a confirmation, 0 means no cap
+static MONTHLY_CAP: AtomicU64 = AtomicU64::new(0);
+
+fn current_month() -> String {
+    Local::now().format("%Y-%m").to_string()
+}
+
+fn load() -> Usage {
+    dirs::base()
+        .find_state_file(USAGE_FILE)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|saved| serde_json::from_str(&saved).ok())
+        .unwrap_or_default()
+}
+
+/// Run `f` on this month's usage, starting from zero in a new month
+fn with_usage<T>(f: impl FnOnce(&mut Usage) -> T) -> T {
+    let mut usage = USAGE.lock().unwrap_or_else(PoisonError::into_inner);
+    let usage = usage.get_or_insert_with(load);
+    let month = current_month();
+    if usage.month != month {
+        *usage = Usage { month, bytes: 0 };
+    }
+    f(usage)
+}
+
+/// Count downloaded bytes toward this month's usage
+pub fn add(bytes: u64) {
+    with_usage(|usage| {
+        usage.bytes += bytes;
+        // Best effort, the count is only informational
+        if let Ok(path) = dirs::base().place_state_file(USAGE_FILE)
+            && let Ok(saved) = serde_json::to_string(usage)
+        {
+            let _ = fs::write(path, saved);
+        }
+    });
+}
+
+pub fn this_month() -> u64 {
+    with_usage(|usage| usage.bytes)
+}
+
+pub fn set_monthly_cap(bytes: u64) {
+    MONTHLY_CAP.store(bytes, Ordering::Relaxed);
+}
+
+pub fn monthly_cap() -> Option<u64> {
+    Some(MONTHLY_CAP.load(Ordering::Relaxed)).filter(|cap| *cap != 0)
+}
+
+pub fn over_cap() -> bool {
+    monthly_cap().is_some_and(|cap| this_month() >= cap)
+}
@@ -0,0 +1,64 @@
+use chrono::{DateTime, FixedOffset};
+use reqwest::blocking::Client;
+use reqwest::header::{CONTENT_LENGTH, LAST_MODIFIED};
+use std::time::Duration;
+use tracing::info_span;
+
+/// The details are a nicety, they aren't waited for long
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest version string taken from the server
+const MAX_VERSION_LEN: usize = 64;
+
+/// What is known about a published build before downloading it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildInfo {
+    /// From an optional `<build>.version` file next to the build
+    pub version: Option<String>,
+    pub size: Option<u64>,
+    pub published: Option<DateTime<FixedOffset>>,
+}
+
+/// Ask the build server about the build at `url` without downloading it.
+/// Anything the server doesn't answer is left out
+pub fn fetch(url: &str) -> BuildInfo {
+    let _span = info_span!("fetch_build_info", url).entered();
+    let Ok(client) = Client::builder().timeout(REQUEST_TIMEOUT).build() else {
+        return BuildInfo::default();
+    };
+    let mut info = BuildInfo::default();
+    if let Ok(response) = client.head(url).send()
+        && response.status().is_success()
+    {
+        let header = |name| response.headers().get(name)?.to_str().ok();
+        info.size = header(CONTENT_LENGTH).and_then(|length| length.parse().ok());
+        info.published =
+            header(LAST_MODIFIED).and_then(|date| DateTime::parse_from_rfc2822(date).ok());
+    }
+    info.version = client
+        .get(format!("{url}.version"))
+        .send()
+        .ok()
+        .filter(|response| response.status().is_success())
+        .and_then(|response| response.text().ok())
+        .and_then(|text| parse_version(&text));
+    info
+}
+
+// A version is a short single line, anything else is likely an error page
+fn parse_version(text: &str) -> Option<String> {
+    let version = text.trim();
+    (!version.is_empty() && version.len() <= MAX_VERSION_LEN && !version.contains(['\n', '<']))
+        .then(|| version.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version_takes_a_short_line() {
+        assert_eq!(parse_version("0.9.3\n"), Some("0.9.3".into()));
+        assert_eq!(parse_version("   "), None);
+        assert_eq!(parse_version("<html>\n<body>Not found</body>"), None);
+    }
+}
@@ -0,0 +1,57 @@
+use color_eyre::Result;
+use eyre::{WrapErr, eyre};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::hash;
+use crate::launcher;
+use crate::profile;
+
+/// Name of the launcher in a bundle
+const LAUNCHER_NAME: &str = "grav-launcher";
+
+// A bundle is a plain directory (a USB stick works) with the game build, its
+// hash in `sha256sum` format and the launcher, for machines without internet
+
+/// Copy the installed build, its hash and this launcher into `dir`. Returns
+/// the hash of the exported build
+pub fn export(dir: &Path) -> Result<String> {
+    let artifact = profile::current().artifact();
+    let (hash, game_path) =
+        hash::get_local_hash()?.ok_or_else(|| eyre!("{artifact} isn't installed yet"))?;
+    fs::create_dir_all(dir).wrap_err_with(|| format!("Failed to create {dir:?}"))?;
+
+    fs::copy(&game_path, dir.join(artifact))
+        .wrap_err_with(|| format!("Failed to copy {artifact} to {dir:?}"))?;
+    // Hashed again so a build that changed on disk while copying isn't passed on
+    let copied_hash = hash::compute_file_hash(&dir.join(artifact))?;
+    if copied_hash != hash {
+        return Err(eyre!(
+            "{artifact} changed while it was copied, export again"
+        ));
+    }
+    fs::write(hash_path(dir), format!("{hash}  {artifact}\n"))
+        .wrap_err("Failed to write the build hash")?;
+
+    let launcher = env::current_exe().wrap_err("Can't find the launcher binary")?;
+    fs::copy(&launcher, dir.join(LAUNCHER_NAME))
+        .wrap_err_with(|| format!("Failed to copy the launcher to {dir:?}"))?;
+    Ok(hash)
+}
+
+/// Install the build from a bundle in `dir` once it matches the bundled hash.
+/// Returns its hash
+pub fn import(dir: &Path) -> Result<String> {
+    let artifact = profile::current().artifact();
+    let hash_path = hash_path(dir);
+    let body = fs::read_to_string(&hash_path)
+        .wrap_err_with(|| format!("Failed to read {hash_path:?}, is {dir:?} a bundle?"))?;
+    let expected = hash::parse_hash(&body, artifact)?;
+    launcher::import_verified(&dir.join(artifact), &expected)?;
+    Ok(expected)
+}
+
+fn hash_path(dir: &Path) -> PathBuf {
+    dir.join(format!("{}.sha256", profile::current().artifact()))
+}
@@ -0,0 +1,245 @@
+use std::collections::VecDeque;
+use std::sync::mpsc::{RecvError, SendError, TryRecvError, TrySendError};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+use crate::event::{Event, InputEvent};
+
+/// How urgently an event has to reach the UI. Events are received highest
+/// priority first, so input never waits behind a flood of progress or output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    /// Keyboard, controller and terminal events
+    Input,
+    /// Everything that changes what the launcher is doing
+    State,
+    /// Download progress reports
+    Progress,
+    /// Game output lines
+    Output,
+}
+
+impl Priority {
+    /// Events waiting at this priority before senders are blocked or
+    /// `try_send` fails
+    const fn capacity(self) -> usize {
+        match self {
+            Self::Input => 256,
+            Self::State => 1024,
+            Self::Progress => 64,
+            Self::Output => 256,
+        }
+    }
+}
+
+struct Queues {
+    events: [VecDeque<Event>; 4],
+    senders: usize,
+    receiver_alive: bool,
+    // The thread receiving events may send to itself, it's never blocked
+    // since nothing else would make room
+    receiver_thread: Option<ThreadId>,
+    // When anything but a tick was last sent
+    last_activity: Instant,
+}
+
+impl Queues {
+    fn pop(&mut self) -> Option<Event> {
+        self.events.iter_mut().find_map(VecDeque::pop_front)
+    }
+}
+
+struct Shared {
+    queues: Mutex<Queues>,
+    received: Condvar,
+    sent: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Queues> {
+        self.queues.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A bounded channel for events with a separate queue per priority,
+/// a drop-in replacement for `mpsc::channel::<Event>()`
+pub fn channel() -> (Sender, Receiver) {
+    let shared = Arc::new(Shared {
+        queues: Mutex::new(Queues {
+            events: Default::default(),
+            senders: 1,
+            receiver_alive: true,
+            receiver_thread: None,
+            last_activity: Instant::now(),
+        }),
+        received: Condvar::new(),
+        sent: Condvar::new(),
+    });
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver { shared },
+    )
+}
+
+pub struct Sender {
+    shared: Arc<Shared>,
+}
+
+impl Sender {
+    /// Queue the event, waiting while its priority's queue is full.
+    /// Fails once the receiver is gone
+    pub fn send(&self, event: impl Into<Event>) -> Result<(), SendError<Event>> {
+        let event = event.into();
+        let priority = event.priority() as usize;
+        let capacity = event.priority().capacity();
+        let mut queues = self.shared.lock();
+        let current = thread::current().id();
+        while queues.receiver_alive
+            && queues.events[priority].len() >= capacity
+            && queues.receiver_thread != Some(current)
+        {
+            queues = self
+                .shared
+                .received
+                .wait(queues)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        if !queues.receiver_alive {
+            return Err(SendError(event));
+        }
+        self.push(&mut queues, event);
+        Ok(())
+    }
+
+    /// Queue the event if its priority's queue has room, for senders that
+    /// must not wait for the UI
+    pub fn try_send(&self, event: impl Into<Event>) -> Result<(), TrySendError<Event>> {
+        let event = event.into();
+        let priority = event.priority() as usize;
+        let mut queues = self.shared.lock();
+        if !queues.receiver_alive {
+            return Err(TrySendError::Disconnected(event));
+        }
+        if queues.events[priority].len() >= event.priority().capacity()
+            && queues.receiver_thread != Some(thread::current().id())
+        {
+            return Err(TrySendError::Full(event));
+        }
+        self.push(&mut queues, event);
+        Ok(())
+    }
+
+    fn push(&self, queues: &mut Queues, event: Event) {
+        if !matches!(event, Event::Input(InputEvent::Tick)) {
+            queues.last_activity = Instant::now();
+        }
+        queues.events[event.priority() as usize].push_back(event);
+        self.shared.sent.notify_one();
+    }
+}
+
+impl Sender {
+    /// Time since an event other than a tick was sent: no input,
+    /// download progress or game output
+    pub fn idle_for(&self) -> Duration {
+        self.shared.lock().last_activity.elapsed()
+    }
+}
+
+impl Clone for Sender {
+    fn clone(&self) -> Self {
+        self.shared.lock().senders += 1;
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for Sender {
+    fn drop(&mut self) {
+        let mut queues = self.shared.lock();
+        queues.senders -= 1;
+        if queues.senders == 0 {
+            self.shared.sent.notify_all();
+        }
+    }
+}
+
+pub struct Receiver {
+    shared: Arc<Shared>,
+}
+
+impl Receiver {
+    /// Wait for the most urgent event. Fails once all senders are gone
+    pub fn recv(&self) -> Result<Event, RecvError> {
+        let mut queues = self.shared.lock();
+        queues.receiver_thread = Some(thread::current().id());
+        loop {
+            if let Some(event) = queues.pop() {
+                self.shared.received.notify_all();
+                return Ok(event);
+            }
+            if queues.senders == 0 {
+                return Err(RecvError);
+            }
+            queues = self
+                .shared
+                .sent
+                .wait(queues)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// Events waiting to be received, by priority
+    pub fn depth(&self) -> [usize; 4] {
+        self.shared.lock().events.each_ref().map(VecDeque::len)
+    }
+
+    pub fn try_recv(&self) -> Result<Event, TryRecvError> {
+        let mut queues = self.shared.lock();
+        match queues.pop() {
+            Some(event) => {
+                self.shared.received.notify_all();
+                Ok(event)
+            }
+            None if queues.senders == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+}
+
+impl Drop for Receiver {
+    fn drop(&mut self) {
+        let mut queues = self.shared.lock();
+        queues.receiver_alive = false;
+        // Nothing will be received, free the events and wake blocked senders
+        for queue in &mut queues.events {
+            queue.clear();
+        }
+        self.shared.received.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{DownloadEvent, GameEvent};
+
+    #[test]
+    fn test_input_is_received_before_queued_output() {
+        let (tx, rx) = channel();
+        tx.send(GameEvent::Output(vec!["line".into()])).unwrap();
+        tx.send(DownloadEvent::DownloadProgress(10)).unwrap();
+        tx.send(InputEvent::Tick).unwrap();
+        assert!(matches!(rx.recv(), Ok(Event::Input(InputEvent::Tick))));
+        assert!(matches!(
+            rx.recv(),
+            Ok(Event::Download(DownloadEvent::DownloadProgress(10)))
+        ));
+        assert!(matches!(rx.recv(), Ok(Event::Game(GameEvent::Output(_)))));
+        assert!(matches!(rx.try_recv(), Err(TryRecvError::Empty)));
+    }
+}
@@ -0,0 +1,140 @@
+use color_eyre::Result;
+use eyre::WrapErr;
+use std::fs::{self, File};
+use std::io;
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+
+use crate::dirs;
+
+// Downloaded builds are stored under their SHA-256, so identical bytes are
+// never downloaded twice no matter which profile or channel asks for them
+
+/// Path of the cached build with the given hash, if present
+pub fn lookup(hash: &str) -> Result<Option<PathBuf>> {
+    Ok(dirs::base().find_cache_file(format!("builds/{hash}")))
+}
+
+/// Where a build is written while it is being downloaded
+pub fn partial_path(hash: &str) -> Result<PathBuf> {
+    dirs::base()
+        .place_cache_file(format!("builds/{hash}.part"))
+        .wrap_err("Can't create cache file path")
+}
+
+/// Delete a build from the cache, if it's there
+pub fn remove(hash: &str) -> Result<()> {
+    match lookup(hash)? {
+        Some(cached) => {
+            fs::remove_file(&cached).wrap_err_with(|| format!("Failed to delete {cached:?}"))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Move a finished download into the cache
+pub fn store(hash: &str, partial: &Path) -> Result<PathBuf> {
+    let cached = dirs::base()
+        .place_cache_file(format!("builds/{hash}"))
+        .wrap_err("Can't create cache file path")?;
+    fs::rename(partial, &cached)
+        .wrap_err_with(|| format!("Failed to move {partial:?} into the cache"))?;
+    Ok(cached)
+}
+
+/// Install a cached build at `destination` without duplicating its bytes where
+/// the filesystem allows it: a reflink (copy-on-write clone) first, falling
+/// back to a plain copy. Never a hard link, writing to the install would change
+/// the cached original with it. The destination is replaced by rename so a
+/// running game is never overwritten in place
+pub fn install(cached: &Path, destination: &Path) -> Result<()> {
+    let staging = staging_path(destination);
+    let _ = fs::remove_file(&staging);
+    share_or_copy(cached, &staging)?;
+    fs::rename(&staging, destination).wrap_err_with(|| format!("Failed to install {destination:?}"))
+}
+
+/// Where `install` puts the build before it replaces `destination`
+pub fn staging_path(destination: &Path) -> PathBuf {
+    destination.with_extension("installing")
+}
+
+/// Add a file that was downloaded elsewhere to the cache, sharing its bytes
+/// the same way as `install`
+pub fn import(hash: &str, file: &Path) -> Result<PathBuf> {
+    let partial = partial_path(hash)?;
+    let _ = fs::remove_file(&partial);
+    share_or_copy(file, &partial)?;
+    store(hash, &partial)
+}
+
+fn share_or_copy(source: &Path, destination: &Path) -> Result<()> {
+    if reflink(source, destination).is_err() {
+        fs::copy(source, destination)
+            .wrap_err_with(|| format!("Failed to copy {source:?} to {destination:?}"))?;
+    }
+    Ok(())
+}
+
+/// Clone `source` into a new file sharing its extents (btrfs, XFS, bcachefs)
+fn reflink(source: &Path, destination: &Path) -> io::Result<()> {
+    let source_file = File::open(source)?;
+    let destination_file = File::create(destination)?;
+    // SAFETY: both file descriptors stay open for the duration of the call
+    let result = unsafe {
+        libc::ioctl(
+            destination_file.as_raw_fd(),
+            libc::FICLONE,
+            source_file.as_raw_fd(),
+        )
+    };
+    if result != 0 {
+        let error = io::Error::last_os_error();
+        drop(destination_file);
+        let _ = fs::remove_file(destination);
+        return Err(error);
+    }
+    destination_file.set_permissions(source_file.metadata()?.permissions())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_install_replaces_destination_without_touching_cache() {
+        let dir = std::env::temp_dir().join(format!("grav-cache-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let old_build = dir.join("old");
+        let new_build = dir.join("new");
+        let destination = dir.join("GRAV.x86_64");
+        fs::write(&old_build, b"old build").unwrap();
+        fs::write(&new_build, b"new build").unwrap();
+
+        install(&old_build, &destination).unwrap();
+        install(&new_build, &destination).unwrap();
+
+        assert_eq!(fs::read(&destination).unwrap(), b"new build");
+        assert_eq!(
+            fs::read(&old_build).unwrap(),
+            b"old build",
+            "Cached build must survive a reinstall over a linked destination"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_writing_to_the_install_leaves_the_cache_alone() {
+        let dir = std::env::temp_dir().join(format!("grav-cache-write-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let cached = dir.join("cached");
+        let destination = dir.join("GRAV.x86_64");
+        fs::write(&cached, b"original build").unwrap();
+
+        install(&cached, &destination).unwrap();
+        fs::write(&destination, b"modded build").unwrap();
+
+        assert_eq!(fs::read(&cached).unwrap(), b"original build");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
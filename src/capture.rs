@@ -0,0 +1,259 @@
+use chrono::{DateTime, Local};
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, ErrorKind, Read};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::TrySendError;
+use std::sync::{Mutex, PoisonError};
+
+use crate::bus;
+use crate::event::GameEvent;
+
+/// Lines sent to the UI but not yet shown, beyond this new output is dropped
+/// so a game flooding its output can't bury the launcher's own events
+const MAX_PENDING_LINES: usize = 20_000;
+/// Lines already buffered are sent together instead of one event per line
+const MAX_BATCH_LINES: usize = 1_000;
+/// Error lines kept for a crash snippet
+const RECENT_LINES: usize = 50;
+
+static PENDING_LINES: AtomicUsize = AtomicUsize::new(0);
+/// Lines the game printed since the launcher started, dropped ones included
+static CAPTURED_LINES: AtomicUsize = AtomicUsize::new(0);
+/// The last error lines with the time they were printed, kept even when
+/// the UI drops them or the pane is cleared
+static RECENT: Mutex<VecDeque<(DateTime<Local>, String)>> = Mutex::new(VecDeque::new());
+/// The player let the game go, its output is no longer read
+static RELEASED: AtomicBool = AtomicBool::new(false);
+
+/// Lines the game printed so far, to tell whether it's doing anything
+pub fn captured() -> usize {
+    CAPTURED_LINES.load(Ordering::Relaxed)
+}
+
+/// Stop reading the game's output, each stream's pipe is closed once it
+/// delivers its next line
+pub fn release() {
+    RELEASED.store(true, Ordering::Relaxed);
+}
+
+/// Capture the output of a newly started game
+pub fn resume() {
+    RELEASED.store(false, Ordering::Relaxed);
+    RECENT
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clear();
+}
+
+/// The last error lines of the game, oldest first, as `HH:MM:SS line`
+pub fn recent_errors() -> Vec<String> {
+    RECENT
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .map(|(time, line)| format!("{} {line}", time.format("%H:%M:%S")))
+        .collect()
+}
+
+fn remember(lines: &[String]) {
+    let now = Local::now();
+    let mut recent = RECENT.lock().unwrap_or_else(PoisonError::into_inner);
+    for line in lines {
+        if recent.len() == RECENT_LINES {
+            recent.pop_front();
+        }
+        recent.push_back((now, line.clone()));
+    }
+}
+
+/// The UI has shown `count` lines of game output
+pub fn delivered(count: usize) {
+    let _ = PENDING_LINES.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |pending| {
+        Some(pending.saturating_sub(count))
+    });
+}
+
+/// Sends batches of game output lines, dropping them while the UI is behind
+pub struct OutputSender {
+    tx: bus::Sender,
+    to_event: fn(Vec<String>) -> GameEvent,
+    dropped: usize,
+    keep_recent: bool,
+}
+
+impl OutputSender {
+    pub fn new(tx: bus::Sender, to_event: fn(Vec<String>) -> GameEvent) -> Self {
+        Self {
+            tx,
+            to_event,
+            dropped: 0,
+            keep_recent: false,
+        }
+    }
+
+    /// Also keep the last lines for a crash snippet
+    pub const fn keep_recent(mut self) -> Self {
+        self.keep_recent = true;
+        self
+    }
+
+    /// Returns false once the UI is gone. Never waits for the UI: a reader
+    /// waiting for room would stop draining the game's pipe and block the
+    /// game on its next write, so lines that don't fit are dropped too
+    pub fn send(&mut self, lines: Vec<String>) -> bool {
+        if lines.is_empty() {
+            return true;
+        }
+        CAPTURED_LINES.fetch_add(lines.len(), Ordering::Relaxed);
+        if self.keep_recent {
+            remember(&lines);
+        }
+        if PENDING_LINES.load(Ordering::Relaxed) + lines.len() > MAX_PENDING_LINES {
+            self.dropped += lines.len();
+            return true;
+        }
+        if self.dropped > 0 {
+            match self.tx.try_send(GameEvent::OutputDropped(self.dropped)) {
+                Ok(()) => self.dropped = 0,
+                Err(TrySendError::Full(_)) => {
+                    self.dropped += lines.len();
+                    return true;
+                }
+                Err(TrySendError::Disconnected(_)) => return false,
+            }
+        }
+        let count = lines.len();
+        PENDING_LINES.fetch_add(count, Ordering::Relaxed);
+        match self.tx.try_send((self.to_event)(lines)) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                delivered(count);
+                self.dropped += count;
+                true
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        }
+    }
+}
+
+impl Drop for OutputSender {
+    // Lines dropped right before the game exited are still reported. The
+    // stream has ended by now, waiting for room no longer holds up the game
+    fn drop(&mut self) {
+        if self.dropped > 0 {
+            let _ = self.tx.send(GameEvent::OutputDropped(self.dropped));
+        }
+    }
+}
+
+/// Send the lines of a game output stream to the UI. Lines are read as raw
+/// bytes, so invalid UTF-8 or binary garbage shows up as replacement characters
+/// instead of breaking the capture
+pub fn forward_output(stream: impl Read, name: &str, mut sender: OutputSender, tx: &bus::Sender) {
+    let mut reader = BufReader::new(stream);
+    let mut line = Vec::new();
+    loop {
+        let mut batch = Vec::new();
+        // Block for one line, then take whatever complete lines are already buffered
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) => {
+                    sender.send(batch);
+                    return;
+                }
+                Ok(_) => batch.push(decode_line(&line)),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    sender.send(batch);
+                    let _ = tx.send(GameEvent::ExecutionError(format!("{name} read: {e}")));
+                    return;
+                }
+            }
+            if batch.len() >= MAX_BATCH_LINES || !reader.buffer().contains(&b'\n') {
+                break;
+            }
+        }
+        if RELEASED.load(Ordering::Relaxed) {
+            return;
+        }
+        if !sender.send(batch) {
+            eprintln!("Game output channel disconnected, shutting down {name} thread");
+            return;
+        }
+    }
+}
+
+/// Text of a raw output line without its line ending
+pub fn decode_line(raw: &[u8]) -> String {
+    let raw = raw.strip_suffix(b"\n").unwrap_or(raw);
+    let raw = raw.strip_suffix(b"\r").unwrap_or(raw);
+    String::from_utf8_lossy(raw).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Event;
+    use std::io::{self, Cursor};
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    // Tells when the reader got to the end of the output
+    struct Output {
+        data: Cursor<Vec<u8>>,
+        read_all: Option<mpsc::Sender<()>>,
+    }
+
+    impl Read for Output {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let read = self.data.read(buf)?;
+            if read == 0
+                && let Some(read_all) = self.read_all.take()
+            {
+                let _ = read_all.send(());
+            }
+            Ok(read)
+        }
+    }
+
+    #[test]
+    fn test_decode_line_survives_invalid_utf8() {
+        assert_eq!(decode_line(b"ok\r\n"), "ok");
+        assert_eq!(
+            decode_line(b"bad \xff\xfe bytes\n"),
+            "bad \u{fffd}\u{fffd} bytes"
+        );
+    }
+
+    #[test]
+    fn test_reader_keeps_reading_while_the_output_queue_is_full() {
+        let (tx, rx) = bus::channel();
+        while tx.try_send(GameEvent::Output(Vec::new())).is_ok() {}
+        let lines = 5_000;
+        let (read_all_tx, read_all_rx) = mpsc::channel();
+        let stream = Output {
+            data: Cursor::new("line\n".repeat(lines).into_bytes()),
+            read_all: Some(read_all_tx),
+        };
+        let sender = OutputSender::new(tx.clone(), GameEvent::Output);
+        let reader = thread::spawn(move || forward_output(stream, "stdout", sender, &tx));
+
+        // Nothing is received until the whole output was read
+        assert!(
+            read_all_rx.recv_timeout(Duration::from_secs(10)).is_ok(),
+            "the reader waited for the UI"
+        );
+        let mut dropped = 0;
+        while let Ok(event) = rx.recv() {
+            match event {
+                Event::Game(GameEvent::Output(batch)) => assert!(batch.is_empty()),
+                Event::Game(GameEvent::OutputDropped(count)) => dropped += count,
+                other => panic!("unexpected {} event", other.kind()),
+            }
+        }
+        reader.join().unwrap();
+        assert_eq!(dropped, lines);
+    }
+}
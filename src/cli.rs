@@ -0,0 +1,290 @@
+use clap::builder::PossibleValuesParser;
+use clap::{CommandFactory, Parser, Subcommand};
+use color_eyre::Result;
+use std::io;
+use std::path::PathBuf;
+
+use crate::game_env;
+use crate::region;
+use crate::ui::theme::GaugeStyle;
+use crate::update::provider::ProviderKind;
+
+/// Updates and launches the GRAV game
+#[derive(Parser, Debug)]
+#[command(name = "grav-launcher", version, about)]
+pub struct Cli {
+    /// Read settings from this file instead of $XDG_CONFIG_HOME/GRAV/config.toml
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Don't relaunch in a terminal emulator when started outside of one
+    #[arg(long)]
+    pub no_terminal: bool,
+
+    /// Check for a new build, download it and run the game without the
+    /// interface, printing progress as plain text. Exits with the game's exit code
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Keep the game, its cache, state and logs in this directory instead of
+    /// the XDG base directories
+    #[arg(long, value_name = "DIR")]
+    pub data_dir: Option<PathBuf>,
+
+    /// Install the game and keep the download cache in this directory shared by
+    /// all users, e.g. /opt/grav. Logs and settings stay per user. The users
+    /// need a common group that can write to the directory
+    #[arg(long, value_name = "DIR")]
+    pub shared_install: Option<PathBuf>,
+
+    /// Hash the installed game on every start to catch modified files, instead
+    /// of only when its size or modification time changed
+    #[arg(long)]
+    pub verify_install: bool,
+
+    /// Where launcher updates come from [default: github]
+    #[arg(long, value_name = "PROVIDER")]
+    pub update_provider: Option<ProviderKind>,
+
+    /// Repository, JSON document or directory URL for --update-provider,
+    /// GitHub defaults to this launcher's repository
+    #[arg(long, value_name = "URL")]
+    pub update_url: Option<String>,
+
+    /// Run even as root. Files created then belong to root and break updates
+    /// for the regular user
+    #[arg(long)]
+    pub allow_root: bool,
+
+    /// Set an environment variable for the game, e.g. --game-env MANGOHUD=1.
+    /// Can be repeated
+    #[arg(long, value_name = "NAME=VALUE", value_parser = game_env::parse_assignment)]
+    pub game_env: Vec<(String, String)>,
+
+    /// Directory to start the game in, by default the one it's installed in
+    #[arg(long, value_name = "DIR")]
+    pub game_workdir: Option<PathBuf>,
+
+    /// Shell command to run when a game build finished downloading
+    #[arg(long, value_name = "COMMAND")]
+    pub on_download_complete: Option<String>,
+
+    /// Shell command to run on a download, game or launcher error. The message
+    /// is in GRAV_MESSAGE and in the JSON on stdin
+    #[arg(long, value_name = "COMMAND")]
+    pub on_error: Option<String>,
+
+    /// Shell command to run when the game exits, its status is in GRAV_STATUS
+    #[arg(long, value_name = "COMMAND")]
+    pub on_game_exit: Option<String>,
+
+    /// Install and run the dedicated server build instead of the game.
+    /// It runs headless and is restarted when it crashes
+    #[arg(long)]
+    pub server: bool,
+
+    /// Run the game under a pseudo-terminal, for output that is only printed to a TTY.
+    /// Errors then show up in the game output pane
+    #[arg(long)]
+    pub pty: bool,
+
+    /// Keep the game running when the launcher or its terminal is closed, and
+    /// don't wait for the relaunched terminal. Has no effect on a game under --pty
+    #[arg(long)]
+    pub detach: bool,
+
+    /// Game server to measure the latency and packet loss to while the game runs
+    #[arg(long, value_name = "HOST:PORT")]
+    pub game_server: Option<String>,
+
+    /// Serve /status (JSON) and /metrics (Prometheus) over HTTP
+    #[arg(long, value_name = "HOST:PORT")]
+    pub status_addr: Option<String>,
+
+    /// Keep this many downloaded game builds to roll back to [default: 3]
+    #[arg(long, value_name = "COUNT", value_parser = clap::value_parser!(u64).range(1..))]
+    pub keep_versions: Option<u64>,
+
+    /// Write progress as JSON lines to this open file descriptor, for Steam
+    /// shortcuts and wrapper scripts drawing their own progress bar
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(i32).range(0..))]
+    pub progress_fd: Option<i32>,
+
+    /// Apply a downloaded launcher update and restart without asking
+    #[arg(long)]
+    pub auto_restart: bool,
+
+    /// Focus the game errors pane when the game writes to stderr
+    #[arg(long)]
+    pub focus_errors: bool,
+
+    /// Stream launcher and game log lines as text to a TCP collector
+    #[arg(long, value_name = "HOST:PORT")]
+    pub stream_logs: Option<String>,
+
+    /// Don't use colors, same as setting NO_COLOR
+    #[arg(long)]
+    pub no_color: bool,
+
+    /// Progress bar style, detected from the terminal by default
+    #[arg(long, value_enum, value_name = "STYLE")]
+    pub gauge: Option<GaugeStyle>,
+
+    /// Interval of UI refreshes, slowed down while nothing happens
+    #[arg(long, value_name = "MS", default_value_t = 200, value_parser = clap::value_parser!(u64).range(1..))]
+    pub tick_rate: u64,
+
+    /// Interval of controller polling, slowed down while nothing happens
+    #[arg(long, value_name = "MS", default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+    pub controller_poll: u64,
+
+    /// Ignore a key or button repeated within this many milliseconds, for
+    /// unsteady hands
+    #[arg(long, value_name = "MS", default_value_t = 0)]
+    pub input_debounce: u64,
+
+    /// Only count controller buttons held down at least this many milliseconds
+    #[arg(long, value_name = "MS", default_value_t = 0)]
+    pub min_hold: u64,
+
+    /// Keep the game updated but don't start it, wait for the player to press play
+    #[arg(long)]
+    pub manual_launch: bool,
+
+    /// For machines shared by the public: the player can't quit the launcher,
+    /// pick another build or run other programs from the install
+    #[arg(long)]
+    pub kiosk: bool,
+
+    /// Seconds to count down before starting the game, to cancel the launch.
+    /// 0 starts it right away [default: 5]
+    #[arg(long, value_name = "SECONDS")]
+    pub launch_countdown: Option<u64>,
+
+    /// Download builds from the build server of this region, remembered for
+    /// the game and the server separately once it answered. It doesn't pick
+    /// the game server to play on
+    #[arg(long, value_name = "REGION", value_parser = PossibleValuesParser::new(region::REGIONS))]
+    pub region: Option<String>,
+
+    /// Wait a random time up to this long before checking for a new build,
+    /// so launchers of a fleet started together don't all check at once
+    #[arg(long, value_name = "SECONDS")]
+    pub check_jitter: Option<u64>,
+
+    /// Warn when the game prints nothing this long after launch, it's likely
+    /// stuck before showing a window
+    #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub startup_timeout: Option<u64>,
+
+    /// Download and hash with idle CPU and I/O priority while the game runs
+    #[arg(long)]
+    pub background_priority: bool,
+
+    /// Run the game in its own systemd scope with this CPU and I/O weight,
+    /// the default weight of other programs is 100
+    #[arg(long, value_name = "WEIGHT", value_parser = clap::value_parser!(u64).range(1..=10000))]
+    pub game_weight: Option<u64>,
+
+    /// Pin the game to these CPUs, e.g. the performance cores of a hybrid CPU.
+    /// Same format as `taskset --cpu-list`: 0-7,16
+    #[arg(long, value_name = "LIST", value_parser = crate::priority::parse_cpu_list)]
+    pub game_cpus: Option<String>,
+
+    /// Niceness of the game process, negative values need privileges
+    #[arg(long, value_name = "NICE", allow_negative_numbers = true, value_parser = clap::value_parser!(i32).range(-20..=19))]
+    pub game_nice: Option<i32>,
+
+    /// Run the game in its own systemd scope, so the OOM killer or systemd-oomd
+    /// ends a leaking game and not the launcher with it
+    #[arg(long)]
+    pub game_scope: bool,
+
+    /// OOM score adjustment of the game, up to 1000 makes it the first to be
+    /// killed when memory runs out. Needs choom from util-linux
+    #[arg(long, value_name = "ADJ", allow_negative_numbers = true, value_parser = clap::value_parser!(i32).range(-1000..=1000))]
+    pub game_oom_score_adj: Option<i32>,
+
+    /// OOM score adjustment of the launcher, negative values protect it and
+    /// need privileges
+    #[arg(long, value_name = "ADJ", allow_negative_numbers = true, value_parser = clap::value_parser!(i32).range(-1000..=1000))]
+    pub launcher_oom_score_adj: Option<i32>,
+
+    /// Octal mode of the installed game and launcher binaries, e.g. 700 on a
+    /// shared machine. Defaults to 755 limited by the umask
+    #[arg(long, value_name = "MODE", value_parser = crate::file_mode::parse_mode)]
+    pub binary_mode: Option<u32>,
+
+    /// Shared bandwidth limit for all downloads
+    #[arg(long, value_name = "KiB/s")]
+    pub limit_rate: Option<u64>,
+
+    /// Ask before downloading a game update once this much was downloaded
+    /// in the current month
+    #[arg(long, value_name = "MiB")]
+    pub monthly_cap: Option<u64>,
+
+    /// Download game updates without asking when NetworkManager reports the
+    /// connection as metered
+    #[arg(long)]
+    pub allow_metered: bool,
+
+    /// Record tracing spans of the launcher threads to a file in the Chrome
+    /// trace format, viewable in Perfetto or chrome://tracing
+    #[arg(long, value_name = "FILE")]
+    pub trace_output: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+
+    /// Arguments passed on to the game, after `--`
+    #[arg(last = true, value_name = "GAME_ARGS")]
+    pub game_args: Vec<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    /// Print a man page generated from the command line definition
+    Manpage,
+    /// Give launcher files created by running it with sudo back to the user,
+    /// run as `sudo grav-launcher repair-ownership`
+    RepairOwnership,
+    /// Install a game build downloaded by hand instead of downloading it again.
+    /// The file is checked against the published hash first
+    Import {
+        /// The downloaded GRAV.x86_64, or the server build with --server
+        file: PathBuf,
+    },
+    /// Copy the installed game build, its hash and the launcher into a
+    /// directory, e.g. a USB stick, for machines without internet
+    ExportBundle { dir: PathBuf },
+    /// Install the game build from a directory made by export-bundle
+    ImportBundle { dir: PathBuf },
+    /// Time the build server in every region and recommend the fastest one
+    /// to download from
+    Regions,
+}
+
+/// Render the man page in roff format to stdout
+pub fn print_manpage() -> Result<()> {
+    clap_mangen::Man::new(Cli::command()).render(&mut io::stdout())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_definition_is_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_arguments_after_separator_go_to_the_game() {
+        let cli =
+            Cli::try_parse_from(["grav-launcher", "--pty", "--", "-windowed", "--pty"]).unwrap();
+        assert!(cli.pty);
+        assert_eq!(cli.game_args, ["-windowed", "--pty"]);
+    }
+}
@@ -0,0 +1,377 @@
+use clap::ValueEnum;
+use color_eyre::Result;
+use eyre::{WrapErr, eyre};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use toml_edit::DocumentMut;
+
+use crate::region;
+use crate::ui::theme::GaugeStyle;
+use crate::update::provider::ProviderKind;
+
+const CONFIG_FILE: &str = "config.toml";
+/// Settings enforced by the administrator, e.g. on the machines of a club
+pub const POLICY_FILE: &str = "/etc/grav-launcher/policy.toml";
+
+/// Settings taken from the policy file, whatever the player set
+static ENFORCED: OnceLock<Vec<String>> = OnceLock::new();
+/// Settings read from each file, the policy file last
+static SOURCES: OnceLock<Vec<(PathBuf, Vec<String>)>> = OnceLock::new();
+/// Keys setting the same thing, one in the policy file replaces the other
+/// in the player's config
+const SAME_SETTING: &[(&str, &str)] = &[("builds_url", "channel")];
+
+/// Settings from `$XDG_CONFIG_HOME/GRAV/config.toml`. Command line options
+/// take precedence over them, the policy file over both
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Config {
+    /// Where the game builds and their hashes are published
+    pub builds_url: Option<String>,
+    /// Release channel to follow instead of a `builds_url`, e.g. `nightly`
+    pub channel: Option<String>,
+    /// Servers with the same builds, tried in order when it fails
+    pub mirrors: Vec<String>,
+    /// Build servers of other channels, their latest builds are cached while
+    /// the game runs
+    pub prefetch: Vec<String>,
+    /// Same as `--data-dir`
+    pub data_dir: Option<PathBuf>,
+    /// Same as `--shared-install`
+    pub shared_install: Option<PathBuf>,
+    /// Same as `--region`
+    pub region: Option<String>,
+    /// Same as `--allow-metered`
+    pub allow_metered: Option<bool>,
+    /// Same as `--kiosk`
+    pub kiosk: Option<bool>,
+    /// Terminal emulator to relaunch in, before the known ones
+    pub terminal: Option<String>,
+    pub manual_launch: Option<bool>,
+    pub launch_countdown: Option<u64>,
+    /// Same as `--gauge`
+    pub gauge: Option<GaugeStyle>,
+    /// Same as `--check-jitter`
+    pub check_jitter: Option<u64>,
+    pub keep_versions: Option<u64>,
+    pub detach: Option<bool>,
+    /// `false` turns off launcher updates
+    pub self_update: Option<bool>,
+    /// Same as `--update-provider`
+    pub update_provider: Option<ProviderKind>,
+    /// Same as `--update-url`
+    pub update_url: Option<String>,
+    /// File name the game build is published and installed as
+    pub artifact: Option<String>,
+    /// Other programs in the install that can be launched instead of the game
+    pub executables: Vec<String>,
+}
+
+/// Read the config file, `path` or the default one, with the policy file
+/// on top. A missing file is an empty config
+pub fn load(path: Option<&Path>) -> Result<Config> {
+    let path = path.map(Path::to_path_buf).or_else(default_path);
+    let user = path.as_deref().map(read).transpose()?.flatten();
+    if let (Some(path), Some(text)) = (&path, &user) {
+        parse(text).wrap_err_with(|| format!("Invalid config file {path:?}"))?;
+    }
+    let policy = read(Path::new(POLICY_FILE))?;
+    if let Some(text) = &policy {
+        parse(text).wrap_err_with(|| format!("Invalid policy file {POLICY_FILE}"))?;
+    }
+    let (config, enforced) = layer(
+        user.as_deref().unwrap_or_default(),
+        policy.as_deref().unwrap_or_default(),
+    )?;
+    let mut sources = Vec::new();
+    if let (Some(path), Some(text)) = (path, &user) {
+        let keys: Vec<String> = text
+            .parse::<DocumentMut>()?
+            .iter()
+            .map(|(key, _)| key.to_string())
+            .filter(|key| !replaced(key, &enforced))
+            .collect();
+        sources.push((path, keys));
+    }
+    sources.push((PathBuf::from(POLICY_FILE), enforced.clone()));
+    let _ = SOURCES.set(sources);
+    let _ = ENFORCED.set(enforced);
+    Ok(config)
+}
+
+/// The files settings were read from with the settings each one set. The
+/// policy file is last, its settings replaced the player's
+pub fn sources() -> &'static [(PathBuf, Vec<String>)] {
+    SOURCES.get().map_or(&[], Vec::as_slice)
+}
+
+/// Names of the settings the policy file enforces
+pub fn enforced() -> &'static [String] {
+    ENFORCED.get().map_or(&[], Vec::as_slice)
+}
+
+/// The policy file sets `key`, the command line and saved choices don't count
+pub fn is_enforced(key: &str) -> bool {
+    enforced().iter().any(|enforced| enforced == key)
+}
+
+// A setting of the player's the policy file replaced, by the same key or
+// by one setting the same thing
+fn replaced(key: &str, enforced: &[String]) -> bool {
+    enforced.iter().any(|enforced| {
+        enforced == key
+            || SAME_SETTING
+                .iter()
+                .any(|&(a, b)| (a, b) == (key, enforced) || (b, a) == (key, enforced))
+    })
+}
+
+fn read(path: &Path) -> Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(Some(text)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).wrap_err_with(|| format!("Failed to read {path:?}")),
+    }
+}
+
+// Settings of the policy replace the player's, the rest are kept
+fn layer(user: &str, policy: &str) -> Result<(Config, Vec<String>)> {
+    let mut document: DocumentMut = user.parse()?;
+    let policy: DocumentMut = policy.parse()?;
+    let enforced: Vec<String> = policy.iter().map(|(key, _)| key.to_string()).collect();
+    document.retain(|key, _| !replaced(key, &enforced));
+    for (key, item) in policy.iter() {
+        document.insert(key, item.clone());
+    }
+    Ok((parse_document(&document)?, enforced))
+}
+
+// Read before the launcher directories are set up, since it can move them
+fn default_path() -> Option<PathBuf> {
+    let xdg_dirs = xdg::BaseDirectories::with_prefix("GRAV").ok()?;
+    Some(xdg_dirs.get_config_home().join(CONFIG_FILE))
+}
+
+fn parse(text: &str) -> Result<Config> {
+    parse_document(&text.parse()?)
+}
+
+fn parse_document(document: &DocumentMut) -> Result<Config> {
+    let mut config = Config::default();
+    for (key, item) in document.iter() {
+        let wrong_type = |expected: &str| eyre!("{key} has to be {expected}");
+        match key {
+            "builds_url" => {
+                let url = item.as_str().ok_or_else(|| wrong_type("a string"))?;
+                config.builds_url = Some(url.trim_end_matches('/').to_string());
+            }
+            "channel" => {
+                let channel = item
+                    .as_str()
+                    .filter(|channel| {
+                        !channel.is_empty()
+                            && channel
+                                .chars()
+                                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                    })
+                    .ok_or_else(|| wrong_type("a channel name like \"nightly\""))?;
+                config.channel = Some(channel.to_string());
+            }
+            "mirrors" => {
+                config.mirrors = url_list(item).ok_or_else(|| wrong_type("a list of URLs"))?;
+            }
+            "prefetch" => {
+                config.prefetch = url_list(item).ok_or_else(|| wrong_type("a list of URLs"))?;
+            }
+            "data_dir" => {
+                let dir = item.as_str().ok_or_else(|| wrong_type("a path"))?;
+                config.data_dir = Some(PathBuf::from(dir));
+            }
+            "shared_install" => {
+                let dir = item.as_str().ok_or_else(|| wrong_type("a path"))?;
+                config.shared_install = Some(PathBuf::from(dir));
+            }
+            "region" => {
+                let name = item
+                    .as_str()
+                    .filter(|name| region::REGIONS.contains(name))
+                    .ok_or_else(|| wrong_type(&format!("one of {}", region::REGIONS.join(", "))))?;
+                config.region = Some(name.to_string());
+            }
+            "allow_metered" => {
+                config.allow_metered =
+                    Some(item.as_bool().ok_or_else(|| wrong_type("true or false"))?);
+            }
+            "kiosk" => {
+                config.kiosk = Some(item.as_bool().ok_or_else(|| wrong_type("true or false"))?);
+            }
+            "terminal" => {
+                let terminal = item.as_str().ok_or_else(|| wrong_type("a string"))?;
+                config.terminal = Some(terminal.to_string());
+            }
+            "manual_launch" => {
+                config.manual_launch =
+                    Some(item.as_bool().ok_or_else(|| wrong_type("true or false"))?);
+            }
+            "artifact" => {
+                let name = item.as_str().ok_or_else(|| wrong_type("a file name"))?;
+                config.artifact = Some(name.to_string());
+            }
+            "executables" => {
+                config.executables = item
+                    .as_array()
+                    .and_then(|names| {
+                        names
+                            .iter()
+                            .map(|name| Some(name.as_str()?.to_string()))
+                            .collect()
+                    })
+                    .ok_or_else(|| wrong_type("a list of file names"))?;
+            }
+            "detach" => {
+                config.detach = Some(item.as_bool().ok_or_else(|| wrong_type("true or false"))?);
+            }
+            "self_update" => {
+                config.self_update =
+                    Some(item.as_bool().ok_or_else(|| wrong_type("true or false"))?);
+            }
+            "update_provider" => {
+                let kind = item
+                    .as_str()
+                    .and_then(|kind| ProviderKind::from_str(kind, false).ok())
+                    .ok_or_else(|| {
+                        wrong_type("\"github\", \"gitea\", \"json\" or \"directory\"")
+                    })?;
+                config.update_provider = Some(kind);
+            }
+            "update_url" => {
+                let url = item.as_str().ok_or_else(|| wrong_type("a URL"))?;
+                config.update_url = Some(url.to_string());
+            }
+            "launch_countdown" => {
+                let seconds = item
+                    .as_integer()
+                    .and_then(|seconds| u64::try_from(seconds).ok())
+                    .ok_or_else(|| wrong_type("a number of seconds"))?;
+                config.launch_countdown = Some(seconds);
+            }
+            "gauge" => {
+                let style = item
+                    .as_str()
+                    .and_then(|style| GaugeStyle::from_str(style, false).ok())
+                    .ok_or_else(|| wrong_type("\"line\", \"ascii\", \"blocks\" or \"braille\""))?;
+                config.gauge = Some(style);
+            }
+            "check_jitter" => {
+                let seconds = item
+                    .as_integer()
+                    .and_then(|seconds| u64::try_from(seconds).ok())
+                    .ok_or_else(|| wrong_type("a number of seconds"))?;
+                config.check_jitter = Some(seconds);
+            }
+            "keep_versions" => {
+                let count = item
+                    .as_integer()
+                    .and_then(|count| u64::try_from(count).ok())
+                    .filter(|count| *count > 0)
+                    .ok_or_else(|| wrong_type("a positive number"))?;
+                config.keep_versions = Some(count);
+            }
+            _ => return Err(eyre!("Unknown setting {key}")),
+        }
+    }
+    if config.builds_url.is_some() && config.channel.is_some() {
+        return Err(eyre!("builds_url and channel can't be set together"));
+    }
+    Ok(config)
+}
+
+fn url_list(item: &toml_edit::Item) -> Option<Vec<String>> {
+    item.as_array()?
+        .iter()
+        .map(|url| Some(url.as_str()?.trim_end_matches('/').to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = parse(
+            r#"
+            builds_url = "https://example.com/builds/"
+            terminal = "foot"
+            launch_countdown = 0
+            gauge = "braille"
+            update_provider = "gitea"
+            update_url = "https://codeberg.org/grav/grav-launcher"
+            executables = ["GRAV-editor.x86_64"]
+            mirrors = ["https://mirror.example.com/grav/"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            Config {
+                builds_url: Some("https://example.com/builds".into()),
+                terminal: Some("foot".into()),
+                launch_countdown: Some(0),
+                gauge: Some(GaugeStyle::Braille),
+                update_provider: Some(ProviderKind::Gitea),
+                update_url: Some("https://codeberg.org/grav/grav-launcher".into()),
+                executables: vec!["GRAV-editor.x86_64".into()],
+                mirrors: vec!["https://mirror.example.com/grav".into()],
+                ..Config::default()
+            }
+        );
+        assert!(parse("launch_countdown = -1").is_err());
+        assert!(parse("executables = [1]").is_err());
+        assert!(parse("gauge = \"dots\"").is_err());
+        assert!(parse("update_provider = \"gitlab\"").is_err());
+        assert!(parse("region = \"mars\"").is_err());
+        assert!(parse("channel = \"../nightly\"").is_err());
+        assert!(parse("builds_url = \"https://example.com\"\nchannel = \"nightly\"").is_err());
+        assert!(parse("bilds_url = \"typo\"").is_err());
+        assert!(parse("not toml").is_err());
+    }
+
+    #[test]
+    fn test_policy_overrides_user_settings() {
+        let (config, enforced) = layer(
+            "builds_url = \"https://example.com/nightly\"\nterminal = \"foot\"",
+            "builds_url = \"https://example.com/stable\"\nself_update = false",
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            Config {
+                builds_url: Some("https://example.com/stable".into()),
+                terminal: Some("foot".into()),
+                self_update: Some(false),
+                ..Config::default()
+            }
+        );
+        assert_eq!(enforced, ["builds_url", "self_update"]);
+
+        // A channel in the policy replaces the player's build server
+        let (config, enforced) = layer(
+            "builds_url = \"https://example.com/nightly\"\nregion = \"us\"",
+            "channel = \"stable\"\nkiosk = true\nregion = \"eu\"",
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            Config {
+                channel: Some("stable".into()),
+                kiosk: Some(true),
+                region: Some("eu".into()),
+                ..Config::default()
+            }
+        );
+        assert!(replaced("builds_url", &enforced));
+        assert!(!replaced("terminal", &enforced));
+    }
+}
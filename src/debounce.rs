@@ -0,0 +1,182 @@
+use std::time::{Duration, Instant};
+
+/// Drops an input repeated sooner than the minimum interval after it was last
+/// accepted, so a trembling hand doesn't move twice. Other inputs pass
+pub struct Debounce<T> {
+    min_interval: Duration,
+    last: Option<(T, Instant)>,
+}
+
+impl<T: PartialEq> Debounce<T> {
+    pub const fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            last: None,
+        }
+    }
+
+    pub fn accept(&mut self, input: T, now: Instant) -> bool {
+        if let Some((last, at)) = &self.last
+            && *last == input
+            && now.duration_since(*at) < self.min_interval
+        {
+            return false;
+        }
+        self.last = Some((input, now));
+        true
+    }
+}
+
+/// Controller buttons count as pressed only once held down this long, a
+/// brief accidental tap is ignored
+pub struct MinHold<T> {
+    min_hold: Duration,
+    held: Vec<(T, Instant)>,
+}
+
+impl<T: PartialEq + Copy> MinHold<T> {
+    pub const fn new(min_hold: Duration) -> Self {
+        Self {
+            min_hold,
+            held: Vec::new(),
+        }
+    }
+
+    /// Returns true if the press counts right away
+    pub fn pressed(&mut self, input: T, now: Instant) -> bool {
+        if self.min_hold.is_zero() {
+            return true;
+        }
+        self.held.retain(|(held, _)| *held != input);
+        self.held.push((input, now));
+        false
+    }
+
+    pub fn released(&mut self, input: T) {
+        self.held.retain(|(held, _)| *held != input);
+    }
+
+    /// Inputs held long enough since the last call, each reported once
+    pub fn long_enough(&mut self, now: Instant) -> Vec<T> {
+        let min_hold = self.min_hold;
+        let (ready, waiting) = self
+            .held
+            .drain(..)
+            .partition(|(_, since)| now.duration_since(*since) >= min_hold);
+        self.held = waiting;
+        ready.into_iter().map(|(input, _)| input).collect()
+    }
+}
+
+/// Tells a tap on a button from holding it down: the tap is reported on
+/// release, the hold once while the button stays down
+pub struct LongPress<T> {
+    input: T,
+    min_hold: Duration,
+    long: Duration,
+    /// Since when the button is down and if the hold was reported
+    down: Option<(Instant, bool)>,
+}
+
+impl<T: PartialEq + Copy> LongPress<T> {
+    /// Watch `input`, taps shorter than `min_hold` are ignored like with `MinHold`
+    pub const fn new(input: T, min_hold: Duration, long: Duration) -> Self {
+        Self {
+            input,
+            min_hold,
+            long,
+            down: None,
+        }
+    }
+
+    /// Returns true if the input is the watched one, it's reported later
+    pub fn pressed(&mut self, input: T, now: Instant) -> bool {
+        if input != self.input {
+            return false;
+        }
+        self.down = Some((now, false));
+        true
+    }
+
+    /// The input as a tap if it was released before it counted as held
+    pub fn released(&mut self, input: T, now: Instant) -> Option<T> {
+        if input != self.input {
+            return None;
+        }
+        let (since, held) = self.down.take()?;
+        (!held && now.duration_since(since) >= self.min_hold).then_some(input)
+    }
+
+    /// Returns true if the input is down, it's then used up by a chord with
+    /// another button and isn't reported itself
+    pub fn chord(&mut self) -> bool {
+        match &mut self.down {
+            Some((_, held)) => {
+                *held = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The input if it's held long enough since the last call, once per press
+    pub fn held(&mut self, now: Instant) -> Option<T> {
+        let (since, held) = self.down.as_mut()?;
+        if *held || now.duration_since(*since) < self.long {
+            return None;
+        }
+        *held = true;
+        Some(self.input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeats_and_short_presses_are_dropped() {
+        let start = Instant::now();
+        let ms = |ms| start + Duration::from_millis(ms);
+
+        let mut debounce = Debounce::new(Duration::from_millis(150));
+        assert!(debounce.accept('j', ms(0)));
+        assert!(!debounce.accept('j', ms(100)));
+        assert!(debounce.accept('k', ms(110)));
+        assert!(debounce.accept('k', ms(300)));
+
+        let mut hold = MinHold::new(Duration::from_millis(100));
+        assert!(!hold.pressed('a', ms(0)));
+        hold.pressed('b', ms(0));
+        hold.released('b');
+        assert!(hold.long_enough(ms(50)).is_empty());
+        assert_eq!(hold.long_enough(ms(120)), ['a']);
+        assert!(hold.long_enough(ms(200)).is_empty());
+    }
+
+    #[test]
+    fn test_long_press_tells_taps_from_holds() {
+        let start = Instant::now();
+        let ms = |ms| start + Duration::from_millis(ms);
+        let mut press = LongPress::new('s', Duration::from_millis(50), Duration::from_millis(500));
+
+        assert!(!press.pressed('a', ms(0)));
+        assert!(press.pressed('s', ms(0)));
+        assert_eq!(press.held(ms(100)), None);
+        assert_eq!(press.released('s', ms(200)), Some('s'));
+
+        press.pressed('s', ms(1000));
+        assert_eq!(press.released('s', ms(1010)), None);
+
+        press.pressed('s', ms(2000));
+        assert_eq!(press.held(ms(2600)), Some('s'));
+        assert_eq!(press.held(ms(2700)), None);
+        assert_eq!(press.released('s', ms(2800)), None);
+
+        assert!(!press.chord());
+        press.pressed('s', ms(3000));
+        assert!(press.chord());
+        assert_eq!(press.held(ms(3600)), None);
+        assert_eq!(press.released('s', ms(3700)), None);
+    }
+}
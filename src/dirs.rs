@@ -0,0 +1,208 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Mode of directories created in a shared install: group writable, and
+/// setgid so everything in them keeps the install's group
+const SHARED_DIR_MODE: u32 = 0o2775;
+
+/// Where the launcher keeps its files: the XDG base directories with a `GRAV`
+/// prefix, or a single directory given with `--data-dir`. The game and the
+/// download cache may instead live in a shared install used by all users
+#[derive(Debug)]
+pub struct Dirs {
+    data_home: PathBuf,
+    state_home: PathBuf,
+    cache_home: PathBuf,
+    config_home: PathBuf,
+    /// Where the game build is installed
+    install_home: PathBuf,
+    shared: bool,
+}
+
+static DIRS: OnceLock<Dirs> = OnceLock::new();
+/// Why the usual directories couldn't be used, shown once the UI is up
+static PROBLEM: OnceLock<String> = OnceLock::new();
+
+/// Pick the directories once at startup, everything goes under `data_dir` if
+/// given. With a `shared_install` the game and its cache are kept there, the
+/// rest stays per user
+pub fn init(data_dir: Option<PathBuf>, shared_install: Option<PathBuf>) {
+    let mut dirs = resolve(data_dir);
+    if let Some(shared) = shared_install {
+        dirs.cache_home = shared.join("cache");
+        dirs.install_home = shared;
+        dirs.shared = true;
+    }
+    let _ = DIRS.set(dirs);
+}
+
+pub fn base() -> &'static Dirs {
+    DIRS.get_or_init(|| resolve(None))
+}
+
+/// How to fix the environment, when the launcher had to fall back to other directories
+pub fn problem() -> Option<&'static str> {
+    PROBLEM.get().map(String::as_str)
+}
+
+fn resolve(data_dir: Option<PathBuf>) -> Dirs {
+    if let Some(root) = data_dir {
+        return Dirs::under(root);
+    }
+    match xdg::BaseDirectories::with_prefix("GRAV") {
+        Ok(xdg_dirs) => Dirs {
+            data_home: xdg_dirs.get_data_home(),
+            state_home: xdg_dirs.get_state_home(),
+            cache_home: xdg_dirs.get_cache_home(),
+            config_home: xdg_dirs.get_config_home(),
+            install_home: xdg_dirs.get_data_home(),
+            shared: false,
+        },
+        Err(e) => {
+            let root = fallback_root();
+            let _ = PROBLEM.set(format!(
+                "The launcher couldn't find its directories: {e}. \
+                 For now the game, its cache and the logs are kept in {}.\n\n\
+                 This usually means HOME isn't set in the session starting the launcher, \
+                 e.g. under a display manager or a custom Steam shortcut. \
+                 Set HOME there, or pass --data-dir with a directory to keep everything in.",
+                root.display()
+            ));
+            Dirs::under(root)
+        }
+    }
+}
+
+// Next to the launcher binary (a USB stick keeps working), otherwise a
+// temporary directory that at least lasts until a reboot
+fn fallback_root() -> PathBuf {
+    env::current_exe()
+        .ok()
+        .and_then(|exe| Some(exe.parent()?.join("grav-launcher-data")))
+        .filter(|dir| fs::create_dir_all(dir).is_ok())
+        .unwrap_or_else(|| {
+            // SAFETY: getuid has no preconditions and can't fail
+            let uid = unsafe { libc::getuid() };
+            env::temp_dir().join(format!("grav-launcher-{uid}"))
+        })
+}
+
+impl Dirs {
+    fn under(root: PathBuf) -> Self {
+        Self {
+            data_home: root.clone(),
+            state_home: root.clone(),
+            cache_home: root.clone(),
+            config_home: root.clone(),
+            install_home: root,
+            shared: false,
+        }
+    }
+
+    /// The game is installed for all users of the machine
+    pub const fn is_shared(&self) -> bool {
+        self.shared
+    }
+
+    pub fn data_home(&self) -> &Path {
+        &self.data_home
+    }
+
+    pub fn state_home(&self) -> &Path {
+        &self.state_home
+    }
+
+    pub fn cache_home(&self) -> &Path {
+        &self.cache_home
+    }
+
+    pub fn config_home(&self) -> &Path {
+        &self.config_home
+    }
+
+    pub fn install_home(&self) -> &Path {
+        &self.install_home
+    }
+
+    pub fn find_state_file(&self, path: impl AsRef<Path>) -> Option<PathBuf> {
+        find(&self.state_home, path)
+    }
+
+    pub fn find_cache_file(&self, path: impl AsRef<Path>) -> Option<PathBuf> {
+        find(&self.cache_home, path)
+    }
+
+    /// The installed game build
+    pub fn find_install_file(&self, path: impl AsRef<Path>) -> Option<PathBuf> {
+        find(&self.install_home, path)
+    }
+
+    /// Path of a data file, creating the directories leading to it
+    pub fn place_data_file(&self, path: impl AsRef<Path>) -> io::Result<PathBuf> {
+        place(&self.data_home, path)
+    }
+
+    pub fn place_state_file(&self, path: impl AsRef<Path>) -> io::Result<PathBuf> {
+        place(&self.state_home, path)
+    }
+
+    pub fn place_cache_file(&self, path: impl AsRef<Path>) -> io::Result<PathBuf> {
+        let path = place(&self.cache_home, path)?;
+        if self.shared {
+            share_dirs(&self.cache_home, &path);
+        }
+        Ok(path)
+    }
+
+    pub fn place_install_file(&self, path: impl AsRef<Path>) -> io::Result<PathBuf> {
+        let path = place(&self.install_home, path)?;
+        if self.shared {
+            share_dirs(&self.install_home, &path);
+        }
+        Ok(path)
+    }
+}
+
+// Let the other users of a shared install write to the directories leading to
+// `path`. Best effort, directories created by someone else already are shared
+fn share_dirs(root: &Path, path: &Path) {
+    for dir in path.ancestors().skip(1) {
+        if !dir.starts_with(root) {
+            break;
+        }
+        let _ = fs::set_permissions(dir, fs::Permissions::from_mode(SHARED_DIR_MODE));
+    }
+}
+
+fn find(dir: &Path, path: impl AsRef<Path>) -> Option<PathBuf> {
+    let path = dir.join(path);
+    path.exists().then_some(path)
+}
+
+fn place(dir: &Path, path: impl AsRef<Path>) -> io::Result<PathBuf> {
+    let path = dir.join(path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_dir_keeps_everything_in_one_place() {
+        let root = env::temp_dir().join(format!("grav-dirs-test-{}", std::process::id()));
+        let dirs = Dirs::under(root.clone());
+        assert_eq!(dirs.find_state_file("session.log"), None);
+        let path = dirs.place_cache_file("builds/abc").unwrap();
+        assert_eq!(path, root.join("builds/abc"));
+        assert!(root.join("builds").is_dir());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,269 @@
+use color_eyre::{Result, eyre::eyre};
+use eyre::WrapErr;
+use reqwest::StatusCode;
+use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE, RANGE};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::info_span;
+
+use crate::bandwidth;
+use crate::http;
+use crate::net;
+
+/// How many transfers may run at the same time
+const MAX_CONCURRENT: usize = 2;
+/// Minimum interval between two progress reports of the same transfer
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What is being downloaded. Higher priority kinds always go first:
+/// a lower priority job doesn't start while a higher priority one runs or waits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobKind {
+    Game,
+    Launcher,
+    #[allow(dead_code)] // reserved for mod downloads
+    Mod,
+    #[allow(dead_code)] // reserved for asset pack downloads
+    AssetPack,
+    /// Builds of other channels cached ahead of a switch
+    Prefetch,
+}
+
+impl JobKind {
+    const fn priority(self) -> u8 {
+        match self {
+            Self::Game => 4,
+            Self::Launcher => 3,
+            Self::Mod => 2,
+            Self::AssetPack => 1,
+            Self::Prefetch => 0,
+        }
+    }
+}
+
+/// A single transfer into a file
+pub struct Job {
+    pub kind: JobKind,
+    pub url: String,
+    pub destination: PathBuf,
+    /// Continue a partial file an interrupted transfer left at `destination`
+    pub resume: bool,
+}
+
+/// Progress reports passed to the caller of `run`
+pub enum Progress {
+    /// The job waits for other transfers to finish
+    Queued,
+    /// The transfer started, with the total size if the server reported it
+    Started(Option<u64>),
+    /// The transfer continues a partial file from this many bytes
+    Resumed(u64),
+    /// Bytes transferred so far
+    Transferred(u64),
+}
+
+struct Queue {
+    running: Vec<u8>,
+    waiting: Vec<(u8, u64)>,
+    next_ticket: u64,
+}
+
+impl Queue {
+    fn can_start(&self, priority: u8, ticket: u64) -> bool {
+        self.running.len() < MAX_CONCURRENT
+            && !self.running.iter().any(|p| *p > priority)
+            && !self
+                .waiting
+                .iter()
+                .any(|(p, t)| *p > priority || (*p == priority && *t < ticket))
+    }
+}
+
+static QUEUE: Mutex<Queue> = Mutex::new(Queue {
+    running: Vec::new(),
+    waiting: Vec::new(),
+    next_ticket: 0,
+});
+static QUEUE_CHANGED: Condvar = Condvar::new();
+
+/// Bandwidth limit shared by all transfers in bytes per second, 0 means unlimited
+static RATE_LIMIT: AtomicU64 = AtomicU64::new(0);
+/// Point in time until which the shared bandwidth is already spent
+static RATE_BUDGET: Mutex<Option<Instant>> = Mutex::new(None);
+
+fn lock_queue() -> MutexGuard<'static, Queue> {
+    QUEUE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// A running transfer's place in the queue, released on drop
+struct Slot {
+    priority: u8,
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        let mut queue = lock_queue();
+        if let Some(index) = queue.running.iter().position(|p| *p == self.priority) {
+            queue.running.remove(index);
+        }
+        QUEUE_CHANGED.notify_all();
+    }
+}
+
+fn acquire(priority: u8, on_progress: &mut impl FnMut(Progress) -> Result<()>) -> Result<Slot> {
+    let _span = info_span!("download_queue", priority).entered();
+    let mut queue = lock_queue();
+    let ticket = queue.next_ticket;
+    queue.next_ticket += 1;
+    queue.waiting.push((priority, ticket));
+
+    if !queue.can_start(priority, ticket) {
+        drop(queue);
+        if let Err(e) = on_progress(Progress::Queued) {
+            lock_queue().waiting.retain(|(_, t)| *t != ticket);
+            QUEUE_CHANGED.notify_all();
+            return Err(e);
+        }
+        queue = lock_queue();
+        while !queue.can_start(priority, ticket) {
+            queue = QUEUE_CHANGED
+                .wait(queue)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    queue.waiting.retain(|(_, t)| *t != ticket);
+    queue.running.push(priority);
+    QUEUE_CHANGED.notify_all();
+    Ok(Slot { priority })
+}
+
+/// Limit the combined speed of all transfers, 0 disables the limit
+pub fn set_rate_limit(bytes_per_second: u64) {
+    RATE_LIMIT.store(bytes_per_second, Ordering::Relaxed);
+}
+
+// Sleep long enough to keep all transfers together under the rate limit
+fn spend_bandwidth(bytes: usize) {
+    let limit = RATE_LIMIT.load(Ordering::Relaxed);
+    if limit == 0 {
+        return;
+    }
+    let cost = Duration::from_secs_f64(bytes as f64 / limit as f64);
+    let now = Instant::now();
+    let start = {
+        let mut budget = RATE_BUDGET.lock().unwrap_or_else(PoisonError::into_inner);
+        let start = budget.map_or(now, |spent_until| spent_until.max(now));
+        *budget = Some(start + cost);
+        start
+    };
+    thread::sleep(start.saturating_duration_since(now));
+}
+
+/// Counts the bytes of a transfer toward the monthly usage when it ends,
+/// whether it finished or failed
+struct Metered(u64);
+
+impl Drop for Metered {
+    fn drop(&mut self) {
+        bandwidth::add(self.0);
+    }
+}
+
+/// Wait for a free slot in the queue and download `job`, reporting progress
+/// through `on_progress`. Returns the size of the downloaded file
+pub fn run(job: &Job, mut on_progress: impl FnMut(Progress) -> Result<()>) -> Result<u64> {
+    let _span = info_span!("download", url = job.url).entered();
+    let _slot = acquire(job.kind.priority(), &mut on_progress)?;
+
+    let partial = if job.resume {
+        fs::metadata(&job.destination).map_or(0, |metadata| metadata.len())
+    } else {
+        0
+    };
+    let response = net::send(|client| {
+        let request = client.get(&job.url);
+        if partial > 0 {
+            request.header(RANGE, format!("bytes={partial}-"))
+        } else {
+            request
+        }
+    })
+    .wrap_err_with(|| format!("Failed to download {} (network error)", job.url))?;
+    // Nothing is left past the partial file, it's checked like a full download
+    if partial > 0 && response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        return Ok(partial);
+    }
+    let mut response = http::check(response)?;
+    // Every download is a binary, a web page is an error or a login page
+    if let Some(content_type) = response.headers().get(CONTENT_TYPE)
+        && content_type
+            .to_str()
+            .is_ok_and(|t| t.starts_with("text/html"))
+    {
+        return Err(eyre!("{} served a web page instead of the file", job.url));
+    }
+    // A server without range support sends the whole file again
+    let offset = if response.status() == StatusCode::PARTIAL_CONTENT {
+        partial
+    } else {
+        0
+    };
+    let total_size = response
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|ct_len| ct_len.to_str().ok()?.parse::<u64>().ok())
+        .map(|remaining| offset + remaining);
+
+    let mut file = if offset > 0 {
+        OpenOptions::new().append(true).open(&job.destination)
+    } else {
+        File::create(&job.destination)
+    }
+    .wrap_err_with(|| format!("Failed to create file {:?}", job.destination))?;
+
+    on_progress(Progress::Started(total_size))?;
+    if offset > 0 {
+        on_progress(Progress::Resumed(offset))?;
+    }
+
+    let mut downloaded = offset;
+    let mut metered = Metered(0);
+    let mut buffer = [0u8; 8 * 1024];
+    let mut last_report = Instant::now();
+
+    loop {
+        let bytes_read = response
+            .read(&mut buffer)
+            .wrap_err("Failed to read from HTTP stream")?;
+        if bytes_read == 0 {
+            break;
+        }
+        file.write_all(&buffer[..bytes_read])
+            .wrap_err_with(|| format!("Failed to write {:?}", job.destination))?;
+        downloaded += bytes_read as u64;
+        metered.0 = downloaded - offset;
+        spend_bandwidth(bytes_read);
+
+        if last_report.elapsed() >= PROGRESS_INTERVAL {
+            last_report = Instant::now();
+            on_progress(Progress::Transferred(downloaded))?;
+        }
+    }
+    on_progress(Progress::Transferred(downloaded))?;
+
+    if let Some(total) = total_size
+        && downloaded != total
+    {
+        return Err(eyre!(
+            "Incomplete download: got {downloaded} of {total} bytes"
+        ));
+    }
+
+    Ok(downloaded)
+}
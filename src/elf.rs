@@ -0,0 +1,57 @@
+use color_eyre::Result;
+use eyre::{WrapErr, eyre};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+const ELF_MAGIC: &[u8; 4] = b"\x7fELF";
+const ELF_CLASS_64: u8 = 2;
+const ELF_MACHINE_X86_64: u16 = 0x3e;
+/// Smaller files are error pages or truncated downloads, not a Godot build
+/// or the launcher
+const MIN_SIZE: u64 = 64 * 1024;
+
+/// Check that a downloaded file looks like a 64-bit x86 Linux executable
+/// before it's made executable, CDNs sometimes serve an error page instead
+pub fn check_executable(path: &Path) -> Result<()> {
+    let mut file = File::open(path).wrap_err_with(|| format!("Failed to open {path:?}"))?;
+    let size = file.metadata()?.len();
+    let mut header = [0; 20];
+    let read = file.read(&mut header)?;
+    check_header(&header[..read], size)
+}
+
+fn check_header(header: &[u8], size: u64) -> Result<()> {
+    if !header.starts_with(ELF_MAGIC) {
+        let text = String::from_utf8_lossy(&header[..header.len().min(16)]);
+        return Err(eyre!("Not an executable, the file starts with {text:?}"));
+    }
+    if header.get(4) != Some(&ELF_CLASS_64)
+        || header.get(18..20) != Some(&ELF_MACHINE_X86_64.to_le_bytes())
+    {
+        return Err(eyre!("Not a 64-bit x86 executable"));
+    }
+    if size < MIN_SIZE {
+        return Err(eyre!(
+            "Executable is only {size} bytes, the download is cut short"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_header_rejects_error_pages() {
+        let mut elf = [0; 20];
+        elf[..4].copy_from_slice(ELF_MAGIC);
+        elf[4] = ELF_CLASS_64;
+        elf[18] = 0x3e;
+        assert!(check_header(&elf, MIN_SIZE).is_ok());
+        assert!(check_header(&elf, 512).is_err());
+        assert!(check_header(b"<html><body>503", MIN_SIZE).is_err());
+        assert!(check_header(&elf[..4], MIN_SIZE).is_err());
+    }
+}
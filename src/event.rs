@@ -1,40 +1,271 @@
+use chrono::{DateTime, Local};
 use crossterm::event as terminal_event;
 use gilrs::{Axis, Button};
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use crate::build_info::BuildInfo;
+use crate::bus::Priority;
+use crate::game_env;
+use crate::install_journal::Recovery;
+use crate::latency;
+use crate::troubleshoot::{Check, Outcome};
+use crate::usage;
 
 type FileSize = u64;
 // type Percentage = f64;
 pub enum Event {
-    Input(terminal_event::KeyEvent),
+    Input(InputEvent),
+    Download(DownloadEvent),
+    Update(UpdateEvent),
+    Game(GameEvent),
+    LauncherError(String),
+    // Start the installed build again, without restarting the launcher
+    RequestGameLaunch,
+    // The build server failed, builds come from this mirror now
+    MirrorSwitched(String),
+    // A troubleshooting check finished
+    CheckFinished(Check, Outcome),
+}
+
+/// User input and terminal changes
+pub enum InputEvent {
+    Key(terminal_event::KeyEvent),
     ControllerInput(Button),
+    // A button held down instead of tapped, only for buttons that do both
+    ControllerHold(Button),
+    // A button pressed while Select is held down
+    ControllerChord(Button),
     ControllerAxisMoved(Axis, f32),
     TerminalFocusChanged(bool),
     Tick,
     Resize,
+    // Tray icon menu actions
+    #[cfg_attr(not(feature = "tray"), allow(dead_code))]
+    TrayLaunchRequested,
+    #[cfg_attr(not(feature = "tray"), allow(dead_code))]
+    TrayQuitRequested,
+}
+
+/// Checking for and fetching the game build
+pub enum DownloadEvent {
     AccessingOnlineHash,
+    // The build check waits this long, so a fleet doesn't check all at once
+    CheckDelayed(Duration),
     OfflineError(String),
+    // The build server answered with an error, it's not a connection problem
+    ServerError(String),
+    // The build server is overloaded or failing, the request is repeated
+    ServerBusy {
+        reason: String,
+        retry_in: Duration,
+    },
+    // A network login page answered instead of the server, with its address
+    // if the request was redirected there
+    CaptivePortal(Option<String>),
     RemoteHash(String),
     LocalHash(String),
     ComputingLocalHash,
-    HashAreEqual(bool),
+    HashAreEqual,
+    // The published build differs from the installed one, installed at the
+    // given time if it's known
+    NewerBuild {
+        info: BuildInfo,
+        installed: Option<DateTime<Local>>,
+    },
+    BuildFoundInCache(String),
     StartDownloadingBinary(Option<FileSize>),
+    // The download continues a partial file left by an interrupted one
+    DownloadResumed(FileSize),
     DownloadProgress(FileSize),
     BinaryDownloadError(String),
+    // The downloaded build's hash isn't the published one, it's downloaded
+    // again after the delay unless this was the last attempt
+    HashMismatch {
+        expected: String,
+        actual: String,
+        retry_in: Option<Duration>,
+    },
+    // A build about to be installed doesn't match its hash, it was dropped
+    // and the installed build is kept
+    DownloadVerificationFailed {
+        expected: String,
+        actual: String,
+    },
+    // The download isn't an executable at all, e.g. a CDN error page
+    NotABuild {
+        reason: String,
+        retry_in: Option<Duration>,
+    },
+    // The update failed, the installed build with this hash is run instead
+    KeepingInstalledBuild(String),
+    // The player picked this build from the version list, updates are skipped
+    PinnedBuildKept(String),
+    // A build from the version list was installed and pinned, or `None` when
+    // updates are followed again
+    VersionPicked(Option<String>),
+    // The published build crashed on start before, the installed one is kept
+    CrashedBuildSkipped(String),
+    // The installed build changed since it was installed, e.g. by mods or
+    // corruption. Restorable if the original is in the download cache
+    InstallModified {
+        installed: String,
+        restorable: bool,
+    },
+    InstallRestored(String),
+    // The launcher was killed while installing this build, the install was
+    // finished or rolled back on this start
+    InstallRecovered {
+        hash: String,
+        recovery: Recovery,
+    },
+    // Downloading needs a confirmation for this reason, the answer is sent back
+    ConfirmDownload {
+        reason: String,
+        answer: mpsc::Sender<bool>,
+    },
+    // Whether the network connection is metered, if known
+    ConnectionMetered(Option<bool>),
+    // Bytes downloaded this month so far
+    MonthlyUsage(u64),
     RemoteBinaryDownloaded,
     NoLocalBinaryFound,
-    GameExecutionError(String),
     GameBinaryUpdated,
+    // A newer build was downloaded while the game was running
+    GameUpdateStaged(String),
+    // The staged build was installed after the game exited
+    GameUpdateInstalled(String),
+    // The latest build of another channel was put into the download cache
+    Prefetched {
+        channel: String,
+        hash: String,
+    },
+    PrefetchFailed {
+        channel: String,
+        error: String,
+    },
+}
+
+/// Updating the launcher itself
+pub enum UpdateEvent {
+    // A package manager updates the launcher, it doesn't check itself
+    ManagedExternally,
+    // Launcher updates are turned off in the config or policy
+    Disabled,
+    // The launcher is on a read-only filesystem, a copy at this path could
+    // update itself
+    ReadOnlyInstall(PathBuf),
+    Checking,
+    Available(String),
+    NoUpdateAvailable,
+    Queued,
+    StartDownloading,
+    DownloadProgress(FileSize, Option<FileSize>),
+    Verifying,
+    Downloaded,
+    Applying,
+    Applied,
+    Requested,
+    // User's choice once the launcher update is downloaded
+    ApplyNow,
+    ApplyOnNextStart,
+    Discard,
+}
+
+/// The running game process
+pub enum GameEvent {
+    // The game starts at `until` unless the player answers: true starts it
+    // right away, false waits for them to press play
+    Countdown {
+        until: Instant,
+        answer: mpsc::Sender<bool>,
+    },
+    // The game is up to date and starts once something is sent here
+    ReadyToPlay(mpsc::Sender<()>),
     Launching,
-    GameOutput(String),
-    GameErrorOutput(String),
-    LauncherError(String),
-    // Launcher update events
-    CheckingForLauncherUpdate,
-    LauncherUpdateAvailable(String),
-    LauncherNoUpdateAvailable,
-    StartDownloadingLauncherUpdate,
-    LauncherDownloadProgress(FileSize, Option<FileSize>),
-    LauncherUpdateDownloaded,
-    LauncherApplyingUpdate,
-    LauncherUpdateApplied,
-    RequestLauncherUpdate,
+    // Working directory and environment variables the game is started with
+    Environment(game_env::Report),
+    ExecutionError(String),
+    // The game process ended, the launcher is done with it unless a server restarts
+    Exited(ExitStatus),
+    // The server crashed with the given status and is started again
+    ServerRestarting(String),
+    // The game printed nothing this long after launch
+    StartupStalled(Duration),
+    // The build crashed right after launch, the last known good one is run instead
+    FallingBack {
+        crashed: String,
+        good: String,
+    },
+    // Lines sent here are written to the game's stdin
+    StdinReady(mpsc::Sender<String>),
+    // Lines are batched when the game prints faster than they're read
+    Output(Vec<String>),
+    ErrorOutput(Vec<String>),
+    // Lines dropped because the UI fell too far behind the game's output
+    OutputDropped(usize),
+    // Connection to the game server, `None` once it's no longer measured
+    ServerLatency(Option<latency::Report>),
+    // Memory and CPU use of the game, `None` once it's no longer sampled
+    Usage(Option<usage::Report>),
+}
+
+impl Event {
+    pub const fn priority(&self) -> Priority {
+        match self {
+            Self::Input(_) => Priority::Input,
+            Self::Download(DownloadEvent::DownloadProgress(_))
+            | Self::Update(UpdateEvent::DownloadProgress(..)) => Priority::Progress,
+            Self::Game(
+                GameEvent::Output(_) | GameEvent::ErrorOutput(_) | GameEvent::OutputDropped(_),
+            ) => Priority::Output,
+            _ => Priority::State,
+        }
+    }
+}
+
+impl Event {
+    /// Short name of the kind of event for the performance overlay
+    pub const fn kind(&self) -> &'static str {
+        match self {
+            Self::Input(InputEvent::Tick) => "tick",
+            Self::Input(_) => "input",
+            Self::Download(DownloadEvent::DownloadProgress(_)) => "download progress",
+            Self::Download(_) => "download",
+            Self::Update(UpdateEvent::DownloadProgress(..)) => "update progress",
+            Self::Update(_) => "update",
+            Self::Game(GameEvent::Output(_) | GameEvent::ErrorOutput(_)) => "game output",
+            Self::Game(_) => "game",
+            Self::LauncherError(_) => "error",
+            Self::RequestGameLaunch => "launch",
+            Self::MirrorSwitched(_) => "mirror",
+            Self::CheckFinished(..) => "troubleshoot",
+        }
+    }
+}
+
+impl From<InputEvent> for Event {
+    fn from(event: InputEvent) -> Self {
+        Self::Input(event)
+    }
+}
+
+impl From<DownloadEvent> for Event {
+    fn from(event: DownloadEvent) -> Self {
+        Self::Download(event)
+    }
+}
+
+impl From<UpdateEvent> for Event {
+    fn from(event: UpdateEvent) -> Self {
+        Self::Update(event)
+    }
+}
+
+impl From<GameEvent> for Event {
+    fn from(event: GameEvent) -> Self {
+        Self::Game(event)
+    }
 }
@@ -0,0 +1,68 @@
+use color_eyre::Result;
+use eyre::WrapErr;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Mode of installed binaries before the umask is applied
+const DEFAULT_BINARY_MODE: u32 = 0o755;
+/// Mode of binaries in a shared install, whatever the umask
+const SHARED_BINARY_MODE: u32 = 0o775;
+
+static BINARY_MODE: OnceLock<u32> = OnceLock::new();
+
+/// Pick the mode of the installed game and launcher binaries: the configured
+/// one as is, otherwise the default restricted by the umask, so a `umask 077`
+/// on a shared machine keeps the game private. Call before spawning threads,
+/// reading the umask briefly changes it for the whole process
+pub fn init(configured: Option<u32>, shared: bool) {
+    let mode = configured.unwrap_or_else(|| {
+        // Every user of a shared install replaces the binary
+        if shared {
+            return SHARED_BINARY_MODE;
+        }
+        // SAFETY: umask only swaps the process file mode mask, it's restored right away
+        let umask = unsafe {
+            let umask = libc::umask(0o022);
+            libc::umask(umask);
+            umask
+        };
+        DEFAULT_BINARY_MODE & !umask
+    });
+    let _ = BINARY_MODE.set(mode);
+}
+
+pub fn binary_mode() -> u32 {
+    BINARY_MODE.get().copied().unwrap_or(DEFAULT_BINARY_MODE)
+}
+
+/// Give an installed binary the configured mode
+pub fn make_executable(path: &Path) -> Result<()> {
+    let mode = binary_mode();
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+        .wrap_err_with(|| format!("Failed to set mode {mode:o} on {path:?}"))
+}
+
+/// Check an octal mode for `--binary-mode`, the owner has to be able to run it
+pub fn parse_mode(mode: &str) -> Result<u32, String> {
+    match u32::from_str_radix(mode, 8) {
+        Ok(mode) if mode <= 0o777 && mode & 0o700 == 0o700 => Ok(mode),
+        Ok(_) => Err(format!("{mode} has to be at most 777 and include 700")),
+        Err(_) => Err(format!("{mode:?} isn't an octal mode like 750")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mode_needs_an_executable_owner() {
+        assert_eq!(parse_mode("700"), Ok(0o700));
+        assert_eq!(parse_mode("0750"), Ok(0o750));
+        assert!(parse_mode("644").is_err());
+        assert!(parse_mode("1777").is_err());
+        assert!(parse_mode("rwx").is_err());
+    }
+}
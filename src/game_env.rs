@@ -0,0 +1,190 @@
+use std::env;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+use crate::dirs;
+use crate::profile;
+
+/// Parts of variable names whose values are left out of reports
+const SECRET_MARKERS: [&str; 7] = [
+    "TOKEN",
+    "SECRET",
+    "PASSWORD",
+    "PASSWD",
+    "KEY",
+    "AUTH",
+    "CREDENTIAL",
+];
+
+/// A variable the game gets on top of the launcher's own environment
+#[derive(Debug, Clone)]
+pub struct Override {
+    pub name: String,
+    pub value: String,
+    /// Where it was set, e.g. "command line"
+    pub source: &'static str,
+}
+
+static OVERRIDES: Mutex<Vec<Override>> = Mutex::new(Vec::new());
+static WORKING_DIR: OnceLock<PathBuf> = OnceLock::new();
+static EXTRA_ARGS: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Start the game with `name` set to `value`, a later override of the same
+/// variable wins
+pub fn set(name: &str, value: &str, source: &'static str) {
+    let mut overrides = OVERRIDES.lock().unwrap_or_else(PoisonError::into_inner);
+    overrides.retain(|o| o.name != name);
+    overrides.push(Override {
+        name: name.to_string(),
+        value: value.to_string(),
+        source,
+    });
+}
+
+pub fn overrides() -> Vec<Override> {
+    OVERRIDES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// Check a `NAME=VALUE` pair for `--game-env`
+pub fn parse_assignment(assignment: &str) -> Result<(String, String), String> {
+    match assignment.split_once('=') {
+        Some((name, value)) if !name.is_empty() && !name.contains(char::is_whitespace) => {
+            Ok((name.to_string(), value.to_string()))
+        }
+        _ => Err(format!("{assignment:?} isn't a NAME=VALUE pair")),
+    }
+}
+
+/// Pass `args` to the game after its own, e.g. from `grav-launcher -- -windowed`
+pub fn set_extra_args(args: Vec<String>) {
+    let _ = EXTRA_ARGS.set(args);
+}
+
+/// Arguments the game is started with: the ones of the profile, then the
+/// ones given on the command line
+pub fn args() -> Vec<String> {
+    let mut args: Vec<String> = profile::current()
+        .args()
+        .iter()
+        .map(|arg| (*arg).to_string())
+        .collect();
+    args.extend(EXTRA_ARGS.get().into_iter().flatten().cloned());
+    args
+}
+
+/// Start the game in `dir` instead of the directory it's installed in
+pub fn set_working_dir(dir: PathBuf) {
+    let _ = WORKING_DIR.set(dir);
+}
+
+/// Directory the game is started in. GRAV looks for its data next to the
+/// binary, so it's the install directory unless configured otherwise
+pub fn working_dir() -> PathBuf {
+    WORKING_DIR
+        .get()
+        .cloned()
+        .unwrap_or_else(|| dirs::base().install_home().to_path_buf())
+}
+
+/// The environment and working directory the game was started with, with
+/// secrets hidden, for "works from a shell" reports
+pub struct Report {
+    pub working_dir: PathBuf,
+    pub args: Vec<String>,
+    /// `NAME=value` inherited from the launcher
+    pub inherited: Vec<String>,
+    /// `NAME=value (set by source)`
+    pub overrides: Vec<String>,
+}
+
+impl Report {
+    /// One line per setting, for the session log
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("Working directory: {}", self.working_dir.display()),
+            format!("Arguments: {}", self.command_line()),
+        ];
+        lines.extend(self.inherited.iter().cloned());
+        lines.extend(self.overrides.iter().cloned());
+        lines
+    }
+
+    /// The arguments as typed in a shell, quoted where they have spaces
+    pub fn command_line(&self) -> String {
+        self.args
+            .iter()
+            .map(|arg| {
+                if arg.is_empty() || arg.contains(char::is_whitespace) {
+                    format!("{arg:?}")
+                } else {
+                    arg.clone()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+pub fn report() -> Report {
+    let overrides = overrides();
+    let mut variables: Vec<(String, String)> = env::vars_os()
+        .map(|(name, value)| {
+            (
+                name.to_string_lossy().into_owned(),
+                value.to_string_lossy().into_owned(),
+            )
+        })
+        .filter(|(name, _)| !overrides.iter().any(|o| &o.name == name))
+        .collect();
+    variables.sort();
+
+    Report {
+        working_dir: working_dir(),
+        args: args(),
+        inherited: variables
+            .iter()
+            .map(|(name, value)| format!("{name}={}", sanitize(name, value)))
+            .collect(),
+        overrides: overrides
+            .iter()
+            .map(|o| {
+                format!(
+                    "{}={} (set by {})",
+                    o.name,
+                    sanitize(&o.name, &o.value),
+                    o.source
+                )
+            })
+            .collect(),
+    }
+}
+
+fn sanitize<'a>(name: &str, value: &'a str) -> &'a str {
+    let name = name.to_ascii_uppercase();
+    if SECRET_MARKERS.iter().any(|marker| name.contains(marker)) {
+        "<hidden>"
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secrets_are_hidden() {
+        assert_eq!(sanitize("GITHUB_TOKEN", "ghp_123"), "<hidden>");
+        assert_eq!(sanitize("ssh_auth_sock", "/run/agent"), "<hidden>");
+        assert_eq!(sanitize("DISPLAY", ":0"), ":0");
+        assert_eq!(
+            parse_assignment("MANGOHUD=1"),
+            Ok(("MANGOHUD".into(), "1".into()))
+        );
+        assert!(parse_assignment("=1").is_err());
+        assert!(parse_assignment("MANGOHUD").is_err());
+    }
+}
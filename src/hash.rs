@@ -1,52 +1,232 @@
 use color_eyre::Result;
 use color_eyre::eyre::eyre;
+use reqwest::Url;
 use sha2::{Digest, Sha256};
+use std::ffi::OsStr;
+use std::fmt;
 use std::fs::File;
 use std::io::{BufReader, Read};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use tracing::info_span;
+
+use crate::dirs;
+use crate::http;
+use crate::install_record;
+use crate::net;
+use crate::profile;
 
 pub fn get_remote_hash(base_url: &str) -> Result<String> {
-    let sha_url = format!("{base_url}.sha256");
-    let current_version_hash_body = reqwest::blocking::get(sha_url)?.text()?;
-    Ok(current_version_hash_body.trim().to_string())
+    let file_name = base_url.rsplit('/').next().unwrap_or(base_url);
+    fetch_hash(&format!("{base_url}.sha256"), file_name)
 }
 
-pub fn get_local_hash() -> Result<Option<(String, PathBuf)>> {
-    // Specify the file path
-    let xdg_dirs = xdg::BaseDirectories::with_prefix("GRAV")
-        .map_err(|e| eyre!("Failed to get xdg directories: {}", e))?;
-
-    if let Some(game_binary_path) = xdg_dirs.find_data_file("GRAV.x86_64") {
-        // Open the file in read-only mode
-        let file = File::open(&game_binary_path).map_err(|e| {
-            eyre!(
-                "Failed to open game binary at {:?}: {}",
-                game_binary_path,
-                e
-            )
-        })?;
-        let mut reader = BufReader::new(file);
-
-        // Create a Sha256 object
-        let mut hasher = Sha256::new();
-
-        // Read the file in chunks
-        let mut buffer = [0; 1024];
-        loop {
-            let bytes_read = reader
-                .read(&mut buffer)
-                .map_err(|e| eyre!("Failed to read from file: {}", e))?;
-            if bytes_read == 0 {
-                break;
+/// A network login page (hotel or airport Wi-Fi) was served instead of the
+/// hash file, the player has to log in through a browser first
+#[derive(Debug)]
+pub struct CaptivePortal {
+    /// The login page the request was redirected to, if it was
+    pub login_url: Option<String>,
+}
+
+impl fmt::Display for CaptivePortal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.login_url {
+            Some(url) => write!(f, "The network redirects to a login page at {url}"),
+            None => write!(
+                f,
+                "The network serves a login page instead of the update server"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CaptivePortal {}
+
+/// Fetch a hash file and pick the hash of `file_name` from it
+pub fn fetch_hash(sha_url: &str, file_name: &str) -> Result<String> {
+    let _span = info_span!("fetch_hash", url = sha_url).entered();
+    let response = net::get(sha_url)?;
+    let redirected = response.url().host_str() != Url::parse(sha_url)?.host_str();
+    let html = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("text/html"));
+    let final_url = response.url().to_string();
+    let current_version_hash_body = http::check(response)?.text()?;
+    // A redirect alone may be a CDN, it's a login page if no hash came back
+    parse_hash(&current_version_hash_body, file_name).map_err(|e| {
+        if redirected || html || looks_like_html(&current_version_hash_body) {
+            CaptivePortal {
+                login_url: redirected.then_some(final_url),
             }
-            // Feed the contents of the buffer into the hasher
-            hasher.update(&buffer[..bytes_read]);
+            .into()
+        } else {
+            e
         }
+    })
+}
+
+fn looks_like_html(body: &str) -> bool {
+    let start: String = body.trim_start().chars().take(14).collect();
+    let start = start.to_ascii_lowercase();
+    start.starts_with("<!doctype html") || start.starts_with("<html")
+}
 
-        // Retrieve the final hash
-        let result = hasher.finalize();
-        Ok(Some((format!("{result:x}"), game_binary_path)))
+/// The SHA-256 of `file_name` from a hash file body, in lowercase. Accepts a
+/// bare hash, `sha256sum` output (`hash  name` per line) and BSD style
+/// `SHA256 (name) = hash` lines. A file listing a single hash is trusted
+/// whatever name it gives, with several the name has to match
+pub fn parse_hash(body: &str, file_name: &str) -> Result<String> {
+    let entries: Vec<(&str, Option<&str>)> = body
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(parse_hash_line)
+        .collect();
+    let hash = match entries.as_slice() {
+        [] => return Err(eyre!("Hash file is empty")),
+        [(hash, _)] => *hash,
+        _ => entries
+            .iter()
+            .find(|(_, name)| name.is_some_and(|name| same_file(name, file_name)))
+            .map(|(hash, _)| *hash)
+            .ok_or_else(|| eyre!("Hash file has no entry for {file_name}"))?,
+    };
+    if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(eyre!("Not a SHA-256 hash: {hash:?}"));
+    }
+    Ok(hash.to_ascii_lowercase())
+}
+
+/// Hash and file name, if any, of a trimmed hash file line
+fn parse_hash_line(line: &str) -> (&str, Option<&str>) {
+    if let Some(tagged) = line.strip_prefix("SHA256 (")
+        && let Some((name, hash)) = tagged.rsplit_once(") = ")
+    {
+        return (hash.trim(), Some(name));
+    }
+    match line.split_once(char::is_whitespace) {
+        // A '*' marks a file hashed in binary mode
+        Some((hash, name)) => (hash, Some(name.trim_start().trim_start_matches('*'))),
+        None => (line, None),
+    }
+}
+
+/// Hash files may list paths like `./GRAV.x86_64` or `build/GRAV.x86_64`
+fn same_file(listed: &str, file_name: &str) -> bool {
+    Path::new(listed).file_name() == Some(OsStr::new(file_name))
+}
+
+pub fn get_local_hash() -> Result<Option<(String, PathBuf)>> {
+    if let Some(game_binary_path) = dirs::base().find_install_file(profile::current().artifact()) {
+        let hash = match install_record::unchanged_hash(&game_binary_path) {
+            Some(hash) => hash,
+            None => compute_file_hash(&game_binary_path)?,
+        };
+        Ok(Some((hash, game_binary_path)))
     } else {
         Ok(None)
     }
 }
+
+pub fn compute_file_hash(path: &Path) -> Result<String> {
+    let _span = info_span!("compute_file_hash", path = %path.display()).entered();
+    // Open the file in read-only mode
+    let file = File::open(path).map_err(|e| eyre!("Failed to open {:?}: {}", path, e))?;
+    let mut reader = BufReader::new(file);
+
+    // Create a Sha256 object
+    let mut hasher = Sha256::new();
+
+    // Read the file in chunks
+    let mut buffer = [0; 1024];
+    loop {
+        let bytes_read = reader
+            .read(&mut buffer)
+            .map_err(|e| eyre!("Failed to read from file: {}", e))?;
+        if bytes_read == 0 {
+            break;
+        }
+        // Feed the contents of the buffer into the hasher
+        hasher.update(&buffer[..bytes_read]);
+    }
+
+    // Retrieve the final hash
+    let result = hasher.finalize();
+    Ok(format!("{result:x}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn test_parse_hash_accepts_checksum_formats(
+            hash in "[0-9a-fA-F]{64}",
+            name in "[A-Za-z0-9._-]{1,20}",
+            padding in "[ \t]{0,3}",
+            line_end in prop_oneof!["", "\n", "\r\n"],
+        ) {
+            let expected = hash.to_ascii_lowercase();
+            let bodies = [
+                format!("{padding}{hash}{padding}{line_end}"),
+                format!("{hash}  {name}{line_end}"),
+                format!("{line_end}{hash} *{name}\r\n"),
+            ];
+            for body in bodies {
+                prop_assert_eq!(parse_hash(&body, &name).ok(), Some(expected.clone()), "{:?}", body);
+            }
+        }
+
+        #[test]
+        fn test_parse_hash_never_panics(body in "\\PC*") {
+            if let Ok(hash) = parse_hash(&body, "GRAV.x86_64") {
+                prop_assert_eq!(hash.len(), 64);
+            }
+        }
+    }
+
+    #[test]
+    fn test_login_pages_look_like_html() {
+        assert!(looks_like_html("\n<!DOCTYPE html><html><title>Hotel Wi-Fi"));
+        assert!(looks_like_html("<HTML>"));
+        assert!(!looks_like_html(&"a".repeat(64)));
+        assert!(!looks_like_html(""));
+    }
+
+    #[test]
+    fn test_parse_hash_rejects_garbage() {
+        assert!(parse_hash("", "GRAV.x86_64").is_err());
+        assert!(parse_hash("<html>Not Found</html>", "GRAV.x86_64").is_err());
+        assert!(parse_hash(&"g".repeat(64), "GRAV.x86_64").is_err());
+    }
+
+    #[test]
+    fn test_parse_hash_picks_the_named_file() {
+        let game = "a".repeat(64);
+        let server = "b".repeat(64);
+        let body = format!("{server}  GRAV-server.x86_64\n{game} *./GRAV.x86_64\n");
+        assert_eq!(parse_hash(&body, "GRAV.x86_64").unwrap(), game);
+        assert_eq!(parse_hash(&body, "GRAV-server.x86_64").unwrap(), server);
+        assert!(parse_hash(&body, "other").is_err());
+
+        let bsd = format!("SHA256 (GRAV.x86_64) = {game}\nSHA256 (GRAV-server.x86_64) = {server}");
+        assert_eq!(parse_hash(&bsd, "GRAV-server.x86_64").unwrap(), server);
+    }
+
+    #[test]
+    fn test_file_hash_spans_chunks() {
+        // A million "a", the FIPS 180-2 test vector, is many chunks long
+        let path = std::env::temp_dir().join(format!("grav-hash-test-{}", std::process::id()));
+        std::fs::write(&path, "a".repeat(1_000_000)).unwrap();
+        let hash = compute_file_hash(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            hash.unwrap(),
+            "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+        );
+    }
+}
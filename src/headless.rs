@@ -0,0 +1,174 @@
+use std::process::ExitStatus;
+use std::thread;
+
+use crate::bus;
+use crate::event::{DownloadEvent, Event, GameEvent};
+use crate::hooks;
+use crate::install_journal::Recovery;
+use crate::launcher;
+use crate::progress_fd;
+use crate::status;
+use crate::ui::format_file_size;
+
+/// Download progress is printed in steps of this many percent
+const PROGRESS_STEP: u64 = 10;
+
+/// A line printed for an event, errors go to stderr
+#[derive(Debug, PartialEq, Eq)]
+enum Line {
+    Progress(String),
+    Error(String),
+}
+
+/// Size of the running download and the last progress step printed
+#[derive(Default)]
+struct Download {
+    total: Option<u64>,
+    printed: u64,
+}
+
+/// Update and run the game without the TUI, printing what happens as plain
+/// text. Returns the exit code of the game, or 1 if it didn't run
+pub fn run(tx: bus::Sender, rx: &bus::Receiver) -> i32 {
+    let launcher = thread::spawn(move || launcher::launcher_logic(tx));
+    let mut download = Download::default();
+    // Ends once the launcher and the game's output threads are done
+    while let Ok(event) = rx.recv() {
+        status::record(&event);
+        hooks::on_event(&event);
+        progress_fd::on_event(&event);
+        answer(&event);
+        match describe(&event, &mut download) {
+            Some(Line::Progress(line)) => println!("{line}"),
+            Some(Line::Error(line)) => eprintln!("{line}"),
+            None => {}
+        }
+    }
+    launcher
+        .join()
+        .ok()
+        .flatten()
+        .and_then(|status: ExitStatus| status.code())
+        .unwrap_or(1)
+}
+
+// Nobody is there to cancel the launch or confirm a download
+fn answer(event: &Event) {
+    match event {
+        Event::Game(GameEvent::Countdown { answer, .. }) => {
+            let _ = answer.send(true);
+        }
+        Event::Game(GameEvent::ReadyToPlay(play)) => {
+            let _ = play.send(());
+        }
+        Event::Download(DownloadEvent::ConfirmDownload { answer, .. }) => {
+            let _ = answer.send(false);
+        }
+        _ => {}
+    }
+}
+
+fn describe(event: &Event, download: &mut Download) -> Option<Line> {
+    let progress = |text: &str| Some(Line::Progress(text.to_string()));
+    match event {
+        Event::Download(event) => match event {
+            DownloadEvent::AccessingOnlineHash => progress("Checking for a new build"),
+            DownloadEvent::CheckDelayed(delay) => Some(Line::Progress(format!(
+                "Waiting {}s before checking, to spread the load on the build server",
+                delay.as_secs()
+            ))),
+            DownloadEvent::HashAreEqual => progress("The game is up to date"),
+            DownloadEvent::OfflineError(e) | DownloadEvent::ServerError(e) => {
+                Some(Line::Error(format!("Can't check for a new build: {e}")))
+            }
+            DownloadEvent::CaptivePortal(_) => Some(Line::Error(
+                "The network needs a login in the browser before the build server can be reached"
+                    .into(),
+            )),
+            DownloadEvent::ConfirmDownload { reason, .. } => Some(Line::Error(format!(
+                "Not downloading: {reason}, run with --allow-metered to download anyway"
+            ))),
+            DownloadEvent::StartDownloadingBinary(size) => {
+                *download = Download {
+                    total: *size,
+                    printed: 0,
+                };
+                Some(Line::Progress(match size {
+                    Some(size) => format!("Downloading the game, {}", format_file_size(*size)),
+                    None => "Downloading the game".into(),
+                }))
+            }
+            DownloadEvent::DownloadResumed(downloaded) => Some(Line::Progress(format!(
+                "Resuming the download at {}",
+                format_file_size(*downloaded)
+            ))),
+            DownloadEvent::DownloadProgress(downloaded) => {
+                let total = download.total.filter(|total| *total > 0)?;
+                let step = downloaded.min(&total) * 100 / total / PROGRESS_STEP * PROGRESS_STEP;
+                if step <= download.printed {
+                    return None;
+                }
+                download.printed = step;
+                Some(Line::Progress(format!("Downloaded {step}%")))
+            }
+            DownloadEvent::RemoteBinaryDownloaded => progress("Download finished"),
+            DownloadEvent::GameBinaryUpdated => progress("Installed the new build"),
+            DownloadEvent::BinaryDownloadError(e) => {
+                Some(Line::Error(format!("Download failed: {e}")))
+            }
+            DownloadEvent::InstallRecovered { recovery, .. } => progress(match recovery {
+                Recovery::Completed => "Finished an install that was interrupted",
+                Recovery::RolledBack => "Rolled back an install that was interrupted",
+            }),
+            DownloadEvent::KeepingInstalledBuild(_) => {
+                progress("Running the installed build instead")
+            }
+            DownloadEvent::NoLocalBinaryFound => Some(Line::Error(
+                "The game isn't installed and can't be downloaded".into(),
+            )),
+            _ => None,
+        },
+        Event::Game(event) => match event {
+            GameEvent::Launching => progress("Starting the game"),
+            GameEvent::ExecutionError(e) => {
+                Some(Line::Error(format!("Failed to run the game: {e}")))
+            }
+            GameEvent::Exited(status) => Some(Line::Progress(format!("The game exited, {status}"))),
+            GameEvent::ServerRestarting(status) => Some(Line::Progress(format!(
+                "The server exited with {status}, restarting"
+            ))),
+            GameEvent::FallingBack { .. } => {
+                progress("The new build crashed, starting the last one that worked")
+            }
+            // The game's own output is passed through as it is
+            GameEvent::Output(lines) => Some(Line::Progress(lines.join("\n"))),
+            GameEvent::ErrorOutput(lines) => Some(Line::Error(lines.join("\n"))),
+            _ => None,
+        },
+        Event::LauncherError(e) => Some(Line::Error(e.clone())),
+        Event::MirrorSwitched(url) => Some(Line::Progress(format!("Switched to the mirror {url}"))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_download_progress_is_printed_in_steps() {
+        let mut download = Download::default();
+        let mut printed = |event| describe(&Event::Download(event), &mut download);
+        printed(DownloadEvent::StartDownloadingBinary(Some(1000)));
+        assert_eq!(printed(DownloadEvent::DownloadProgress(50)), None);
+        assert_eq!(
+            printed(DownloadEvent::DownloadProgress(120)),
+            Some(Line::Progress("Downloaded 10%".into()))
+        );
+        assert_eq!(printed(DownloadEvent::DownloadProgress(150)), None);
+        assert_eq!(
+            printed(DownloadEvent::DownloadProgress(1000)),
+            Some(Line::Progress("Downloaded 100%".into()))
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+
+use crate::event::{DownloadEvent, Event, GameEvent};
+
+/// Launcher events a command can be run on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    DownloadComplete,
+    Error,
+    GameExit,
+}
+
+impl Hook {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::DownloadComplete => "download_complete",
+            Self::Error => "error",
+            Self::GameExit => "game_exit",
+        }
+    }
+}
+
+static COMMANDS: Mutex<Vec<(Hook, String)>> = Mutex::new(Vec::new());
+
+/// Run `command` through `sh -c` whenever `hook` happens
+pub fn set(hook: Hook, command: String) {
+    COMMANDS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push((hook, command));
+}
+
+/// Run the hooks of a launcher event, if it's one they can be set on
+pub fn on_event(event: &Event) {
+    match event {
+        Event::Download(DownloadEvent::RemoteBinaryDownloaded) => {
+            run(Hook::DownloadComplete, &[]);
+        }
+        Event::Download(
+            DownloadEvent::OfflineError(message)
+            | DownloadEvent::ServerError(message)
+            | DownloadEvent::BinaryDownloadError(message),
+        ) => run(Hook::Error, &[("source", "download"), ("message", message)]),
+        Event::Game(GameEvent::ExecutionError(message)) => {
+            run(Hook::Error, &[("source", "game"), ("message", message)]);
+        }
+        Event::LauncherError(message) => {
+            run(Hook::Error, &[("source", "launcher"), ("message", message)]);
+        }
+        _ => {}
+    }
+}
+
+/// Run the commands set for `hook` in the background. The data is passed as
+/// `GRAV_<NAME>` environment variables and as a JSON object on stdin.
+/// Best effort, a failing command doesn't affect the launcher
+pub fn run(hook: Hook, data: &[(&str, &str)]) {
+    let commands: Vec<String> = COMMANDS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .filter(|(set_for, _)| *set_for == hook)
+        .map(|(_, command)| command.clone())
+        .collect();
+    if commands.is_empty() {
+        return;
+    }
+    let mut fields: BTreeMap<&str, &str> = data.iter().copied().collect();
+    fields.insert("event", hook.name());
+    let json = serde_json::to_string(&fields).unwrap_or_default();
+    let envs: Vec<(String, String)> = fields
+        .iter()
+        .map(|(name, value)| (format!("GRAV_{}", name.to_uppercase()), value.to_string()))
+        .collect();
+
+    for command in commands {
+        let (json, envs) = (json.clone(), envs.clone());
+        thread::spawn(move || {
+            let Ok(mut child) = Command::new("sh")
+                .arg("-c")
+                .arg(&command)
+                .envs(envs)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+            else {
+                return;
+            };
+            // Commands that don't read their stdin close it early
+            if let Some(mut stdin) = child.stdin.take() {
+                let _ = writeln!(stdin, "{json}");
+            }
+            let _ = child.wait();
+        });
+    }
+}
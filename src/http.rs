@@ -0,0 +1,117 @@
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use reqwest::blocking::Response;
+use reqwest::header::RETRY_AFTER;
+use std::fmt;
+use std::time::Duration;
+
+/// An HTTP error status, explained in words a player understands
+#[derive(Debug)]
+pub struct HttpError {
+    pub url: String,
+    pub status: StatusCode,
+    /// How long the server asked to wait before trying again
+    pub retry_after: Option<Duration>,
+}
+
+impl HttpError {
+    /// The server may answer later, the request is worth repeating
+    pub fn is_temporary(&self) -> bool {
+        self.status == StatusCode::TOO_MANY_REQUESTS || self.status.is_server_error()
+    }
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let file = self.url.rsplit('/').next().unwrap_or(&self.url);
+        let code = self.status.as_u16();
+        match self.status {
+            StatusCode::NOT_FOUND => write!(f, "{file} isn't published yet ({code})"),
+            StatusCode::FORBIDDEN | StatusCode::UNAUTHORIZED => {
+                write!(f, "The server refused access to {file} ({code})")
+            }
+            StatusCode::TOO_MANY_REQUESTS => write!(f, "The server is overloaded ({code})"),
+            status if status.is_server_error() => {
+                write!(
+                    f,
+                    "The server has a problem ({code}), it's usually temporary"
+                )
+            }
+            status => write!(f, "The server answered {status} for {file}"),
+        }
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+/// Turn an error status into an `HttpError`
+pub fn check(response: Response) -> Result<Response, HttpError> {
+    let status = response.status();
+    if status.is_client_error() || status.is_server_error() {
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| parse_retry_after(value, Utc::now()));
+        return Err(HttpError {
+            url: response.url().to_string(),
+            status,
+            retry_after,
+        });
+    }
+    Ok(response)
+}
+
+// Either a number of seconds or an HTTP date
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    if let Ok(seconds) = value.trim().parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - now)
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_overload_and_server_errors_are_retried() {
+        let error = |status| HttpError {
+            url: "https://example.com/builds/GRAV.x86_64".into(),
+            status,
+            retry_after: None,
+        };
+        assert!(error(StatusCode::SERVICE_UNAVAILABLE).is_temporary());
+        assert!(error(StatusCode::TOO_MANY_REQUESTS).is_temporary());
+        assert!(!error(StatusCode::NOT_FOUND).is_temporary());
+        assert_eq!(
+            error(StatusCode::NOT_FOUND).to_string(),
+            "GRAV.x86_64 isn't published yet (404)"
+        );
+    }
+
+    #[test]
+    fn test_retry_after_is_seconds_or_a_date() {
+        let now = DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:29:30 GMT", now),
+            Some(Duration::from_secs(90))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+}
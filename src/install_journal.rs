@@ -0,0 +1,87 @@
+use color_eyre::Result;
+use eyre::WrapErr;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info_span;
+
+use crate::cache;
+use crate::dirs;
+use crate::hash;
+use crate::install_record;
+use crate::profile;
+
+/// An install in progress, kept per profile in `$XDG_STATE_HOME/GRAV` from
+/// before the installed build is touched until its record is written. Left
+/// behind, it means the launcher was killed halfway
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Journal {
+    hash: String,
+    destination: PathBuf,
+}
+
+/// How an install the launcher didn't finish was cleaned up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recovery {
+    /// The new build was in place or complete in staging, it's installed now
+    Completed,
+    /// The new build wasn't complete, the build from before stays installed
+    RolledBack,
+}
+
+fn journal_path() -> Result<PathBuf> {
+    dirs::base()
+        .place_state_file(format!("{}.install.json", profile::current().artifact()))
+        .wrap_err("Can't create install journal path")
+}
+
+/// Install a cached build at `destination`, journaled so an interrupted
+/// install can be finished or undone on the next start
+pub fn install(hash: &str, cached: &Path, destination: &Path) -> Result<()> {
+    let path = journal_path()?;
+    let journal = Journal {
+        hash: hash.to_string(),
+        destination: destination.to_path_buf(),
+    };
+    fs::write(&path, serde_json::to_string_pretty(&journal)?)
+        .wrap_err_with(|| format!("Failed to write {path:?}"))?;
+    cache::install(cached, destination)?;
+    install_record::record(destination, hash)?;
+    fs::remove_file(&path).wrap_err_with(|| format!("Failed to delete {path:?}"))
+}
+
+/// Finish or undo an install that was interrupted, if there was one. Returns
+/// the hash of the build it was installing and what was done about it
+pub fn recover() -> Result<Option<(String, Recovery)>> {
+    let path = journal_path()?;
+    let Ok(saved) = fs::read_to_string(&path) else {
+        return Ok(None);
+    };
+    let _span = info_span!("recover_install").entered();
+    let recovery = match serde_json::from_str::<Journal>(&saved) {
+        Ok(journal) => Some((journal.hash.clone(), finish(&journal)?)),
+        // Cut off while it was written, nothing was touched yet
+        Err(_) => None,
+    };
+    fs::remove_file(&path).wrap_err_with(|| format!("Failed to delete {path:?}"))?;
+    Ok(recovery)
+}
+
+// Each step of `install` leaves the files in a state that tells how far it got
+fn finish(journal: &Journal) -> Result<Recovery> {
+    let staging = cache::staging_path(&journal.destination);
+    let is_new_build = |path: &Path| {
+        path.exists() && hash::compute_file_hash(path).is_ok_and(|hash| hash == journal.hash)
+    };
+    if is_new_build(&journal.destination) {
+        let _ = fs::remove_file(&staging);
+    } else if is_new_build(&staging) {
+        fs::rename(&staging, &journal.destination)
+            .wrap_err_with(|| format!("Failed to install {:?}", journal.destination))?;
+    } else {
+        let _ = fs::remove_file(&staging);
+        return Ok(Recovery::RolledBack);
+    }
+    install_record::record(&journal.destination, &journal.hash)?;
+    Ok(Recovery::Completed)
+}
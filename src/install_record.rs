@@ -0,0 +1,191 @@
+use color_eyre::Result;
+use eyre::WrapErr;
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::UNIX_EPOCH;
+
+use crate::dirs;
+use crate::profile;
+
+/// Key signing the records, readable by the user only
+const KEY_FILE: &str = "install-record.key";
+
+static ALWAYS_VERIFY: AtomicBool = AtomicBool::new(false);
+
+/// Hash, size and modification time of the installed build, kept per profile
+/// in `$XDG_STATE_HOME/GRAV`. Signed with HMAC-SHA256, so a record edited to
+/// match a modified build is noticed and ignored
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Record {
+    hash: String,
+    size: u64,
+    modified_secs: u64,
+    modified_nanos: u32,
+    #[serde(default)]
+    signature: String,
+}
+
+impl Record {
+    fn of(path: &Path, hash: &str) -> Result<Self> {
+        let metadata = fs::metadata(path).wrap_err_with(|| format!("Failed to stat {path:?}"))?;
+        let modified = metadata.modified()?.duration_since(UNIX_EPOCH)?;
+        Ok(Self {
+            hash: hash.to_string(),
+            size: metadata.len(),
+            modified_secs: modified.as_secs(),
+            modified_nanos: modified.subsec_nanos(),
+            signature: String::new(),
+        })
+    }
+
+    fn signed_fields(&self) -> String {
+        format!(
+            "{} {} {} {}",
+            self.hash, self.size, self.modified_secs, self.modified_nanos
+        )
+    }
+
+    fn sign(&self, key: &[u8]) -> String {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, key);
+        hmac::sign(&key, self.signed_fields().as_bytes())
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    fn has_valid_signature(&self, key: &[u8]) -> bool {
+        let signature: Option<Vec<u8>> = self
+            .signature
+            .as_bytes()
+            .chunks(2)
+            .map(|pair| u8::from_str_radix(str::from_utf8(pair).ok()?, 16).ok())
+            .collect();
+        let key = hmac::Key::new(hmac::HMAC_SHA256, key);
+        signature.is_some_and(|signature| {
+            hmac::verify(&key, self.signed_fields().as_bytes(), &signature).is_ok()
+        })
+    }
+}
+
+/// Hash every build on start instead of trusting an unchanged size and
+/// modification time
+pub fn always_verify(enabled: bool) {
+    ALWAYS_VERIFY.store(enabled, Ordering::Relaxed);
+}
+
+fn record_path() -> Result<PathBuf> {
+    dirs::base()
+        .place_state_file(format!("{}.installed.json", profile::current().artifact()))
+        .wrap_err("Can't create install record path")
+}
+
+fn key() -> Result<Vec<u8>> {
+    let path = dirs::base()
+        .place_state_file(KEY_FILE)
+        .wrap_err("Can't create install record key path")?;
+    if let Ok(key) = fs::read(&path) {
+        return Ok(key);
+    }
+    let mut key = vec![0; 32];
+    fs::File::open("/dev/urandom")
+        .and_then(|mut random| random.read_exact(&mut key))
+        .wrap_err("Failed to generate the install record key")?;
+    // Created private, it's never readable by others even for a moment
+    let created = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path);
+    match created {
+        Ok(mut file) => file
+            .write_all(&key)
+            .wrap_err_with(|| format!("Failed to write {path:?}"))?,
+        // Another launcher got there first
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+            return fs::read(&path).wrap_err_with(|| format!("Failed to read {path:?}"));
+        }
+        Err(e) => return Err(e).wrap_err_with(|| format!("Failed to create {path:?}")),
+    }
+    Ok(key)
+}
+
+/// The record of the installed build, if there is one with a valid signature
+fn load() -> Option<Record> {
+    let saved = fs::read_to_string(record_path().ok()?).ok()?;
+    let record: Record = serde_json::from_str(&saved).ok()?;
+    record.has_valid_signature(&key().ok()?).then_some(record)
+}
+
+/// Remember the build at `path` as installed with `hash`
+pub fn record(path: &Path, hash: &str) -> Result<()> {
+    let mut record = Record::of(path, hash)?;
+    record.signature = record.sign(&key()?);
+    let path = record_path()?;
+    fs::write(&path, serde_json::to_string_pretty(&record)?)
+        .wrap_err_with(|| format!("Failed to write {path:?}"))
+}
+
+/// Hash of the build as it was installed
+pub fn installed_hash() -> Option<String> {
+    load().map(|record| record.hash)
+}
+
+/// The recorded hash of the build at `path` if its size and modification time
+/// are still the recorded ones, saving a full hash on every start
+pub fn unchanged_hash(path: &Path) -> Option<String> {
+    if ALWAYS_VERIFY.load(Ordering::Relaxed) {
+        return None;
+    }
+    let record = load()?;
+    let current = Record::of(path, &record.hash).ok()?;
+    (Record {
+        signature: record.signature.clone(),
+        ..current
+    } == record)
+        .then_some(record.hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_covers_every_field() {
+        let record = Record {
+            hash: "ab".repeat(32),
+            size: 42,
+            modified_secs: 1_700_000_000,
+            modified_nanos: 5,
+            signature: String::new(),
+        };
+        let signature = record.sign(b"key");
+        assert_ne!(signature, record.sign(b"other key"));
+        let resized = Record { size: 43, ..record };
+        assert_ne!(signature, resized.sign(b"key"));
+    }
+
+    #[test]
+    fn test_edited_records_fail_verification() {
+        let record = Record {
+            hash: String::new(),
+            size: 0,
+            modified_secs: 0,
+            modified_nanos: 0,
+            signature: String::new(),
+        };
+        let signed = Record {
+            signature: record.sign(b"key"),
+            ..record
+        };
+        assert!(signed.has_valid_signature(b"key"));
+        assert!(!signed.has_valid_signature(b"other key"));
+        let tampered = Record { size: 1, ..signed };
+        assert!(!tampered.has_valid_signature(b"key"));
+    }
+}
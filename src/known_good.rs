@@ -0,0 +1,77 @@
+use color_eyre::Result;
+use eyre::WrapErr;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::cache;
+use crate::dirs;
+use crate::profile;
+
+/// Builds that are known to start, or known not to, kept per profile in
+/// `$XDG_STATE_HOME/GRAV` so a broken update can be rolled back
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct KnownBuilds {
+    /// Hash of the last build that ran past its startup
+    last_good: Option<String>,
+    /// Hash of a build that crashed right after launch, it isn't installed again
+    crashed: Option<String>,
+}
+
+fn state_path() -> Result<PathBuf> {
+    dirs::base()
+        .place_state_file(format!("{}.known-good.json", profile::current().artifact()))
+        .wrap_err("Can't create known good build state path")
+}
+
+fn load() -> KnownBuilds {
+    state_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|saved| serde_json::from_str(&saved).ok())
+        .unwrap_or_default()
+}
+
+fn save(known: &KnownBuilds) -> Result<()> {
+    let path = state_path()?;
+    let saved = serde_json::to_string_pretty(known)?;
+    fs::write(&path, saved).wrap_err_with(|| format!("Failed to write {path:?}"))
+}
+
+/// The build started fine, it's the one to fall back to from now on
+pub fn record_good(hash: &str) -> Result<()> {
+    let mut known = load();
+    if known.last_good.as_deref() == Some(hash) {
+        return Ok(());
+    }
+    known.last_good = Some(hash.to_string());
+    if known.crashed.as_deref() == Some(hash) {
+        known.crashed = None;
+    }
+    save(&known)
+}
+
+/// Remember the build crashed on start, so it's skipped until a newer one is published
+pub fn record_crashed(hash: &str) -> Result<()> {
+    let mut known = load();
+    known.crashed = Some(hash.to_string());
+    save(&known)
+}
+
+/// Hash of the build to fall back to
+pub fn last_good() -> Option<String> {
+    load().last_good
+}
+
+pub fn is_crashed(hash: &str) -> bool {
+    load().crashed.as_deref() == Some(hash)
+}
+
+/// Hash and cached path of the last good build to run instead of `crashed`,
+/// if it differs and is still in the download cache
+pub fn fallback_for(crashed: &str) -> Option<(String, PathBuf)> {
+    let good = load().last_good.filter(|good| good != crashed)?;
+    let path = cache::lookup(&good).ok().flatten()?;
+    Some((good, path))
+}
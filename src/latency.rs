@@ -0,0 +1,105 @@
+use std::collections::VecDeque;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::bus;
+use crate::event::GameEvent;
+
+const PING_INTERVAL: Duration = Duration::from_secs(5);
+/// A connection taking longer counts as lost
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+/// Pings the packet loss is counted over
+const LOSS_WINDOW: usize = 20;
+
+static SERVER: OnceLock<String> = OnceLock::new();
+
+/// Measure the latency to this `host:port` while the game runs
+pub fn set_server(address: String) {
+    let _ = SERVER.set(address);
+}
+
+/// Latest measurement of the connection to the game server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Report {
+    /// Time to connect, `None` when the last attempt failed
+    pub latency: Option<Duration>,
+    pub loss_percent: u32,
+}
+
+/// Stops measuring when dropped
+pub struct Monitor {
+    stop: Arc<AtomicBool>,
+}
+
+impl Drop for Monitor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Start timing TCP connections to the game server, if one is configured.
+/// Connect time stands in for ping, which needs privileges
+pub fn monitor(tx: &bus::Sender) -> Option<Monitor> {
+    let address = SERVER.get()?.clone();
+    let stop = Arc::new(AtomicBool::new(false));
+    let tx = tx.clone();
+    thread::spawn({
+        let stop = stop.clone();
+        move || {
+            let mut window = VecDeque::with_capacity(LOSS_WINDOW);
+            while !stop.load(Ordering::Relaxed) {
+                if window.len() == LOSS_WINDOW {
+                    window.pop_front();
+                }
+                window.push_back(ping(&address));
+                if tx
+                    .send(GameEvent::ServerLatency(Some(report(&window))))
+                    .is_err()
+                {
+                    return;
+                }
+                thread::sleep(PING_INTERVAL);
+            }
+            let _ = tx.send(GameEvent::ServerLatency(None));
+        }
+    });
+    Some(Monitor { stop })
+}
+
+fn ping(address: &str) -> Option<Duration> {
+    // Resolved every time, a failing lookup is as bad as a lost packet
+    let address = address.to_socket_addrs().ok()?.next()?;
+    let started = Instant::now();
+    TcpStream::connect_timeout(&address, CONNECT_TIMEOUT).ok()?;
+    Some(started.elapsed())
+}
+
+fn report(window: &VecDeque<Option<Duration>>) -> Report {
+    let lost = window.iter().filter(|ping| ping.is_none()).count();
+    Report {
+        latency: window.back().copied().flatten(),
+        loss_percent: (lost * 100 / window.len().max(1)) as u32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_counts_lost_pings() {
+        let ms = |ms| Some(Duration::from_millis(ms));
+        let window = VecDeque::from([ms(40), None, ms(42), ms(41)]);
+        assert_eq!(
+            report(&window),
+            Report {
+                latency: ms(41),
+                loss_percent: 25
+            }
+        );
+    }
+}
@@ -1,73 +1,204 @@
+use chrono::{DateTime, Local};
 use color_eyre::{Result, eyre::eyre};
 use eyre::WrapErr;
-use std::fs::{self, File};
-use std::io::{BufRead, BufReader, Read, Write};
-use std::os::unix::fs::PermissionsExt;
-use std::path::PathBuf;
-use std::process::{Command, Stdio};
-use std::sync::mpsc;
-use std::thread;
-
-use crate::BASE_URL;
-use crate::event::Event;
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError, mpsc};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tracing::info_span;
+
+use crate::bandwidth;
+use crate::build_info;
+use crate::bus;
+use crate::cache;
+use crate::capture::{self, OutputSender};
+use crate::dirs;
+use crate::downloads::{self, Job, JobKind, Progress};
+use crate::elf;
+use crate::event::{DownloadEvent, Event, GameEvent};
+use crate::file_mode;
+use crate::game_env;
 use crate::hash;
+use crate::hooks::{self, Hook};
+use crate::http::HttpError;
+use crate::install_journal;
+use crate::install_record;
+use crate::known_good;
+use crate::latency;
+use crate::metered;
+use crate::platform;
+use crate::prefetch;
+use crate::priority;
+use crate::profile;
+use crate::pty;
+use crate::region;
+use crate::usage;
+use crate::versions;
+
+const MIB: u64 = 1024 * 1024;
+/// How often to look for a newer build while the game is running
+const UPDATE_CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+/// How often to check whether the game process has exited
+const GAME_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Time a game asked to quit gets before it's killed
+const TERMINATE_GRACE: Duration = Duration::from_secs(5);
+/// Pause before a crashed server is started again
+const SERVER_RESTART_DELAY: Duration = Duration::from_secs(5);
+/// Downloads of a build that doesn't match its published hash before giving up.
+/// The hash may be published before the build reaches every CDN node
+const VERIFY_ATTEMPTS: u32 = 4;
+/// Pause before the first download retry, doubled after every mismatch
+const VERIFY_RETRY_DELAY: Duration = Duration::from_secs(15);
+/// Requests to an overloaded or failing build server before giving up
+const SERVER_ATTEMPTS: u32 = 3;
+const SERVER_RETRY_DELAY: Duration = Duration::from_secs(30);
+/// Longest wait a server's Retry-After is followed for, a later time is
+/// likely a misconfigured server
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(10 * 60);
+/// A build failing sooner than this after launch is considered broken,
+/// one running longer becomes the last known good build
+const STARTUP_CRASH_WINDOW: Duration = Duration::from_secs(10);
+
+/// Update and run the build until it's done. Returns how the game last
+/// exited, if it was started
+pub fn launcher_logic(tx: bus::Sender) -> Option<ExitStatus> {
+    run_until_done(&tx, launcher_logic_impl)
+}
+
+/// Start the installed build again without checking for a new one, e.g.
+/// after the game exited. Returns how it last exited, if it was started
+pub fn launch_installed(tx: bus::Sender) -> Option<ExitStatus> {
+    run_until_done(&tx, launch_installed_impl)
+}
+
+// Run `step` and report how the game exited, again for a crashed server
+fn run_until_done(
+    tx: &bus::Sender,
+    step: fn(&bus::Sender) -> Result<Option<ExitStatus>>,
+) -> Option<ExitStatus> {
+    loop {
+        let result = step(tx);
+        if let Ok(Some(status)) = &result {
+            let code = status
+                .code()
+                .map(|code| code.to_string())
+                .unwrap_or_default();
+            hooks::run(
+                Hook::GameExit,
+                &[("status", &status.to_string()), ("code", &code)],
+            );
+            if tx.send(GameEvent::Exited(*status)).is_err() {
+                return Some(*status);
+            }
+        }
+        match result {
+            // Start over from the update check, so a restart picks up a new build
+            Ok(Some(status)) if !status.success() && profile::current().restarts_on_crash() => {
+                if tx
+                    .send(GameEvent::ServerRestarting(status.to_string()))
+                    .is_err()
+                {
+                    return Some(status);
+                }
+                thread::sleep(SERVER_RESTART_DELAY);
+            }
+            Ok(status) => return status,
+            Err(e) => {
+                let _ = tx.send(Event::LauncherError(format!("Launcher error: {e}")));
+                return None;
+            }
+        }
+    }
+}
+
+fn launch_installed_impl(tx: &bus::Sender) -> Result<Option<ExitStatus>> {
+    let _span = info_span!("launch_installed").entered();
+    recover_install(tx)?;
+    match hash::get_local_hash()? {
+        Some((hash, game_path)) => launch_with_fallback(game_path, Some(&hash), tx),
+        None => {
+            if tx.send(DownloadEvent::NoLocalBinaryFound).is_err() {
+                return Err(eyre!("Channel disconnected when reporting no local binary"));
+            }
+            Ok(None)
+        }
+    }
+}
 
-pub fn launcher_logic(tx: mpsc::Sender<Event>) {
-    if let Err(e) = launcher_logic_impl(&tx) {
-        let _ = tx.send(Event::LauncherError(format!("Launcher error: {e}")));
+/// Finish or undo an install the launcher was killed in the middle of
+fn recover_install(tx: &bus::Sender) -> Result<()> {
+    let event = match install_journal::recover() {
+        Ok(Some((hash, recovery))) => DownloadEvent::InstallRecovered { hash, recovery }.into(),
+        Ok(None) => return Ok(()),
+        Err(e) => Event::LauncherError(format!("Failed to recover an interrupted install: {e}")),
+    };
+    if tx.send(event).is_err() {
+        return Err(eyre!(
+            "Channel disconnected when reporting an interrupted install"
+        ));
     }
+    Ok(())
 }
 
-fn launcher_logic_impl(tx: &mpsc::Sender<Event>) -> Result<()> {
-    if tx.send(Event::AccessingOnlineHash).is_err() {
+/// Update and run the build. Returns how it exited, if it was started
+fn launcher_logic_impl(tx: &bus::Sender) -> Result<Option<ExitStatus>> {
+    let _span = info_span!("launcher_logic").entered();
+    if tx.send(DownloadEvent::AccessingOnlineHash).is_err() {
         return Err(eyre!("Channel disconnected at start of launcher logic"));
     }
+    let _ = tx.send(DownloadEvent::MonthlyUsage(bandwidth::this_month()));
+    let _ = tx.send(DownloadEvent::ConnectionMetered(metered::is_metered()));
+    recover_install(tx)?;
+    wait_check_jitter(tx)?;
 
-    let remote_version_hash = match hash::get_remote_hash(BASE_URL) {
+    let remote_version_hash = match with_mirrors(tx, hash::get_remote_hash) {
         Ok(hash) => hash,
         Err(e) => {
-            if tx.send(Event::OfflineError(format!("{e}"))).is_err() {
+            let event = if let Some(portal) = e.downcast_ref::<hash::CaptivePortal>() {
+                DownloadEvent::CaptivePortal(portal.login_url.clone())
+            } else if e.downcast_ref::<HttpError>().is_some() {
+                DownloadEvent::ServerError(format!("{e}"))
+            } else {
+                DownloadEvent::OfflineError(format!("{e}"))
+            };
+            if tx.send(event).is_err() {
                 return Err(eyre!("Channel disconnected when reporting offline error"));
             }
 
-            let xdg_dirs = match xdg::BaseDirectories::with_prefix("GRAV") {
-                Ok(d) => d,
-                Err(e) => {
-                    if tx
-                        .send(Event::LauncherError(format!(
-                            "Failed to find XDG directories: {e}"
-                        )))
-                        .is_err()
-                    {
-                        return Err(eyre!("Channel disconnected when reporting XDG error"));
-                    }
-                    return Ok(());
-                }
-            };
-
-            if let Some(game_binary_path) = xdg_dirs.find_data_file("GRAV.x86_64") {
-                if let Err(e) = run_the_game(game_binary_path, tx) {
-                    if tx.send(Event::GameExecutionError(format!("{e}"))).is_err() {
-                        return Err(eyre!(
-                            "Channel disconnected when reporting game execution error"
-                        ));
-                    }
-                }
-            } else if tx.send(Event::NoLocalBinaryFound).is_err() {
+            if let Some(game_binary_path) =
+                dirs::base().find_install_file(profile::current().artifact())
+            {
+                return launch(game_binary_path, None, tx);
+            } else if tx.send(DownloadEvent::NoLocalBinaryFound).is_err() {
                 return Err(eyre!("Channel disconnected when reporting no local binary"));
             }
-            return Ok(());
+            return Ok(None);
         }
     };
 
     if tx
-        .send(Event::RemoteHash(remote_version_hash.clone()))
+        .send(DownloadEvent::RemoteHash(remote_version_hash.clone()))
         .is_err()
     {
         return Err(eyre!("Channel disconnected when reporting remote hash"));
     }
+    if let Err(e) = region::confirm()
+        && tx
+            .send(Event::LauncherError(format!(
+                "Failed to remember the region: {e}"
+            )))
+            .is_err()
+    {
+        return Err(eyre!("Channel disconnected when reporting a region error"));
+    }
 
-    if tx.send(Event::ComputingLocalHash).is_err() {
+    if tx.send(DownloadEvent::ComputingLocalHash).is_err() {
         return Err(eyre!(
             "Channel disconnected when reporting computing local hash"
         ));
@@ -76,79 +207,135 @@ fn launcher_logic_impl(tx: &mpsc::Sender<Event>) -> Result<()> {
     match hash::get_local_hash() {
         Ok(Some((local_version_hash, game_path))) => {
             if tx
-                .send(Event::LocalHash(local_version_hash.clone()))
+                .send(DownloadEvent::LocalHash(local_version_hash.clone()))
                 .is_err()
             {
                 return Err(eyre!("Channel disconnected when reporting local hash"));
             }
 
+            // A modified build is kept until there is an update, the player
+            // may restore the original from the UI, from the cache or by
+            // downloading it again while it's still the published build
+            if let Some(installed) = install_record::installed_hash()
+                && installed != local_version_hash
+            {
+                let restorable = installed == remote_version_hash
+                    || intact_cache_entry(&installed).ok().flatten().is_some();
+                if tx
+                    .send(DownloadEvent::InstallModified {
+                        installed: installed.clone(),
+                        restorable,
+                    })
+                    .is_err()
+                {
+                    return Err(eyre!(
+                        "Channel disconnected when reporting a modified build"
+                    ));
+                }
+                if installed == remote_version_hash {
+                    // Not run under a hash: how the modified build does says
+                    // nothing about the published one, it mustn't become the
+                    // last good build or be marked crashed
+                    return launch(game_path, None, tx);
+                }
+            }
+
             if local_version_hash == remote_version_hash {
-                if tx.send(Event::HashAreEqual(true)).is_err() {
+                if tx.send(DownloadEvent::HashAreEqual).is_err() {
                     return Err(eyre!("Channel disconnected when reporting hash equality"));
                 }
 
-                if let Err(e) = check_exec_permissions(&game_path) {
-                    if tx
+                if let Err(e) = file_mode::make_executable(&game_path)
+                    && tx
                         .send(Event::LauncherError(format!(
                             "Failed to set exec permissions: {e}"
                         )))
                         .is_err()
+                {
+                    return Err(eyre!(
+                        "Channel disconnected when reporting permission error"
+                    ));
+                }
+                // Optionally: still attempt to run anyway.
+                let _ = install_record::record(&game_path, &local_version_hash);
+
+                return launch(game_path, Some(&remote_version_hash), tx);
+            } else {
+                let info = build_info::fetch(&profile::current().url());
+                let installed = fs::metadata(&game_path)
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+                    .map(DateTime::<Local>::from);
+                if tx
+                    .send(DownloadEvent::NewerBuild { info, installed })
+                    .is_err()
+                {
+                    return Err(eyre!("Channel disconnected when reporting hash inequality"));
+                }
+
+                if versions::pinned().as_deref() == Some(local_version_hash.as_str()) {
+                    if tx
+                        .send(DownloadEvent::PinnedBuildKept(local_version_hash.clone()))
+                        .is_err()
                     {
-                        return Err(eyre!(
-                            "Channel disconnected when reporting permission error"
-                        ));
+                        return Err(eyre!("Channel disconnected when reporting a pinned build"));
                     }
-                    // Optionally: still attempt to run anyway.
+                    return launch(game_path, Some(&local_version_hash), tx);
                 }
 
-                if let Err(e) = run_the_game(game_path, tx) {
-                    if tx.send(Event::GameExecutionError(format!("{e}"))).is_err() {
-                        return Err(eyre!(
-                            "Channel disconnected when reporting game execution error"
-                        ));
+                if known_good::is_crashed(&remote_version_hash) {
+                    if tx
+                        .send(DownloadEvent::CrashedBuildSkipped(
+                            remote_version_hash.clone(),
+                        ))
+                        .is_err()
+                    {
+                        return Err(eyre!("Channel disconnected when reporting a skipped build"));
                     }
-                }
-            } else {
-                if tx.send(Event::HashAreEqual(false)).is_err() {
-                    return Err(eyre!("Channel disconnected when reporting hash inequality"));
+                    return launch(game_path, Some(&local_version_hash), tx);
                 }
 
-                match download_game_binary(remote_version_hash, tx) {
+                match download_game_binary(&remote_version_hash, tx) {
                     Ok(game_path) => {
-                        if tx.send(Event::RemoteBinaryDownloaded).is_err() {
+                        if tx.send(DownloadEvent::RemoteBinaryDownloaded).is_err() {
                             return Err(eyre!("Channel disconnected after binary download"));
                         }
 
-                        if let Err(e) = run_the_game(game_path, tx) {
-                            if tx.send(Event::GameExecutionError(format!("{e}"))).is_err() {
-                                return Err(eyre!(
-                                    "Channel disconnected when reporting game execution error"
-                                ));
-                            }
-                        }
+                        return launch(game_path, Some(&remote_version_hash), tx);
                     }
                     Err(e) => {
-                        if tx.send(Event::BinaryDownloadError(format!("{e}"))).is_err() {
+                        if tx
+                            .send(DownloadEvent::BinaryDownloadError(format!("{e}")))
+                            .is_err()
+                        {
                             return Err(eyre!(
                                 "Channel disconnected when reporting binary download error"
                             ));
                         }
+                        // The installed build is untouched until a verified one
+                        // replaces it, so it's still good to play
+                        if tx
+                            .send(DownloadEvent::KeepingInstalledBuild(
+                                local_version_hash.clone(),
+                            ))
+                            .is_err()
+                        {
+                            return Err(eyre!(
+                                "Channel disconnected when reporting installed build fallback"
+                            ));
+                        }
+                        return launch(game_path, Some(&local_version_hash), tx);
                     }
                 }
             }
         }
-        Ok(None) => match download_game_binary(remote_version_hash, tx) {
-            Ok(game_path) => {
-                if let Err(e) = run_the_game(game_path, tx) {
-                    if tx.send(Event::GameExecutionError(format!("{e}"))).is_err() {
-                        return Err(eyre!(
-                            "Channel disconnected when reporting game execution error"
-                        ));
-                    }
-                }
-            }
+        Ok(None) => match download_game_binary(&remote_version_hash, tx) {
+            Ok(game_path) => return launch(game_path, Some(&remote_version_hash), tx),
             Err(e) => {
-                if tx.send(Event::BinaryDownloadError(format!("{e}"))).is_err() {
+                if tx
+                    .send(DownloadEvent::BinaryDownloadError(format!("{e}")))
+                    .is_err()
+                {
                     return Err(eyre!(
                         "Channel disconnected when reporting binary download error"
                     ));
@@ -168,106 +355,641 @@ fn launcher_logic_impl(tx: &mpsc::Sender<Event>) -> Result<()> {
             }
         }
     }
-    Ok(())
+    Ok(None)
 }
 
-fn download_game_binary(current_hash: String, tx: &mpsc::Sender<Event>) -> Result<PathBuf> {
-    let response = reqwest::blocking::get(BASE_URL)
-        .wrap_err("Failed to download game binary (network/HTTP error)")?;
-    let total_size = response
-        .headers()
-        .get(reqwest::header::CONTENT_LENGTH)
-        .and_then(|ct_len| ct_len.to_str().ok()?.parse::<u64>().ok());
+// Give the player the countdown to cancel the launch, then run the game
+fn launch(
+    game_path: PathBuf,
+    running_hash: Option<&str>,
+    tx: &bus::Sender,
+) -> Result<Option<ExitStatus>> {
+    let launch_now = !MANUAL_LAUNCH.load(Ordering::Relaxed) && count_down(tx)?;
+    if !launch_now && !wait_for_play(tx)? {
+        return Ok(None);
+    }
+    launch_with_fallback(game_path, running_hash, tx)
+}
+
+/// Wait for the player to press play. Returns false if the launcher is closing
+fn wait_for_play(tx: &bus::Sender) -> Result<bool> {
+    let (play, played) = mpsc::channel();
+    if tx.send(GameEvent::ReadyToPlay(play)).is_err() {
+        return Err(eyre!("Channel disconnected when waiting to play"));
+    }
+    Ok(played.recv().is_ok())
+}
+
+/// Keep the game updated, but only start it when the player presses play
+pub fn use_manual_launch(enabled: bool) {
+    MANUAL_LAUNCH.store(enabled, Ordering::Relaxed);
+}
+
+static MANUAL_LAUNCH: AtomicBool = AtomicBool::new(false);
+
+/// Wait out the launch countdown. Returns false if the player cancelled it
+fn count_down(tx: &bus::Sender) -> Result<bool> {
+    let countdown = Duration::from_secs(LAUNCH_COUNTDOWN_SECS.load(Ordering::Relaxed));
+    // A server is started and restarted unattended
+    if countdown.is_zero() || profile::current().restarts_on_crash() {
+        return Ok(true);
+    }
+    let (answer, answered) = mpsc::channel();
+    if tx
+        .send(GameEvent::Countdown {
+            until: Instant::now() + countdown,
+            answer,
+        })
+        .is_err()
+    {
+        return Err(eyre!("Channel disconnected when counting down to launch"));
+    }
+    match answered.recv_timeout(countdown) {
+        Ok(launch_now) => Ok(launch_now),
+        Err(_) => Ok(true),
+    }
+}
+
+/// Count down this long before starting the game, so the launch can be cancelled
+pub fn set_launch_countdown(countdown: Duration) {
+    LAUNCH_COUNTDOWN_SECS.store(countdown.as_secs(), Ordering::Relaxed);
+}
+
+// 0 starts the game right away
+static LAUNCH_COUNTDOWN_SECS: AtomicU64 = AtomicU64::new(5);
+
+// Run the game, reporting a failure to start or supervise it in the log.
+// A build crashing right away is replaced by the last known good one
+fn launch_with_fallback(
+    game_path: PathBuf,
+    running_hash: Option<&str>,
+    tx: &bus::Sender,
+) -> Result<Option<ExitStatus>> {
+    // Another program from the install isn't the build, it has no fallback
+    let (game_path, running_hash) = match launch_target() {
+        Some(target) => (game_path.with_file_name(target), None),
+        None => (game_path, running_hash),
+    };
+    let started = Instant::now();
+    match run_the_game(game_path, running_hash, tx) {
+        Ok(status)
+            if !status.success()
+                && started.elapsed() < STARTUP_CRASH_WINDOW
+                && let Some(crashed) = running_hash
+                && let Some((good, cached_path)) = known_good::fallback_for(crashed) =>
+        {
+            let _ = known_good::record_crashed(crashed);
+            if tx
+                .send(GameEvent::FallingBack {
+                    crashed: crashed.to_string(),
+                    good: good.clone(),
+                })
+                .is_err()
+            {
+                return Err(eyre!("Channel disconnected when reporting fallback"));
+            }
+            let game_path = install_game_binary(&good, &cached_path, tx)?;
+            launch_with_fallback(game_path, Some(&good), tx)
+        }
+        Ok(status) => Ok(Some(status)),
+        Err(e) => {
+            if tx.send(GameEvent::ExecutionError(format!("{e}"))).is_err() {
+                return Err(eyre!(
+                    "Channel disconnected when reporting game execution error"
+                ));
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// Install the game binary for `current_hash`, downloading it unless it is cached.
+/// Returns the installed path
+fn download_game_binary(current_hash: &str, tx: &bus::Sender) -> Result<PathBuf> {
+    let cached_path = cached_or_fetch(current_hash, tx)?;
+    install_game_binary(current_hash, &cached_path, tx)
+}
+
+/// Path of the build in the download cache, downloading it first if needed
+fn cached_or_fetch(current_hash: &str, tx: &bus::Sender) -> Result<PathBuf> {
+    if let Some(cached_path) = cache::lookup(current_hash)? {
+        if tx
+            .send(DownloadEvent::BuildFoundInCache(current_hash.to_string()))
+            .is_err()
+        {
+            return Err(eyre!(
+                "Launcher channel disconnected when reporting cache hit"
+            ));
+        }
+        return Ok(cached_path);
+    }
+    fetch_game_binary(current_hash, tx)
+}
+
+/// Download the game binary into the cache, without touching the installed one.
+/// A download that doesn't match `current_hash` is retried with a growing delay
+fn fetch_game_binary(current_hash: &str, tx: &bus::Sender) -> Result<PathBuf> {
+    confirm_download(tx)?;
+    let tmp_path = cache::partial_path(current_hash)?;
 
-    let xdg_dirs =
-        xdg::BaseDirectories::with_prefix("GRAV").wrap_err("Failed to get XDG data dir")?;
-    let tmp_path = xdg_dirs
-        .place_data_file(current_hash)
-        .wrap_err("Can't create temporary file path")?;
-    let mut file =
-        File::create(&tmp_path).wrap_err_with(|| format!("Failed to create file {tmp_path:?}"))?;
+    let mut retry_delay = VERIFY_RETRY_DELAY;
+    for attempt in 1..=VERIFY_ATTEMPTS {
+        with_mirrors(tx, |url| {
+            let job = Job {
+                kind: JobKind::Game,
+                url: url.to_string(),
+                destination: tmp_path.clone(),
+                resume: true,
+            };
+            downloads::run(&job, |progress| {
+                let event = match progress {
+                    Progress::Queued => return Ok(()),
+                    Progress::Started(total_size) => {
+                        DownloadEvent::StartDownloadingBinary(total_size)
+                    }
+                    Progress::Resumed(offset) => DownloadEvent::DownloadResumed(offset),
+                    Progress::Transferred(downloaded) => {
+                        DownloadEvent::DownloadProgress(downloaded)
+                    }
+                };
+                tx.send(event)
+                    .map_err(|_| eyre!("Launcher channel disconnected during download"))
+            })
+        })?;
 
-    if tx.send(Event::StartDownloadingBinary(total_size)).is_err() {
+        let retry_in = (attempt < VERIFY_ATTEMPTS).then_some(retry_delay);
+        let problem = match elf::check_executable(&tmp_path) {
+            Err(e) => DownloadEvent::NotABuild {
+                reason: format!("{e}"),
+                retry_in,
+            },
+            Ok(()) => {
+                let actual = hash::compute_file_hash(&tmp_path)?;
+                if actual == current_hash {
+                    break;
+                }
+                DownloadEvent::HashMismatch {
+                    expected: current_hash.to_string(),
+                    actual,
+                    retry_in,
+                }
+            }
+        };
+        let _ = fs::remove_file(&tmp_path);
+        if tx.send(problem).is_err() {
+            return Err(eyre!("Launcher channel disconnected after a bad download"));
+        }
+        match retry_in {
+            Some(delay) => thread::sleep(delay),
+            None => {
+                return Err(eyre!(
+                    "Downloaded build isn't the published one after {VERIFY_ATTEMPTS} attempts"
+                ));
+            }
+        }
+        retry_delay *= 2;
+    }
+
+    if tx.send(DownloadEvent::RemoteBinaryDownloaded).is_err() {
         return Err(eyre!(
-            "Launcher channel disconnected during download initialization"
+            "Launcher channel disconnected after download completed"
         ));
     }
+    let _ = tx.send(DownloadEvent::MonthlyUsage(bandwidth::this_month()));
 
-    let mut downloaded: u64 = 0;
-    let mut resp = response;
-    let mut buffer = [0u8; 8 * 1024];
+    file_mode::make_executable(&tmp_path)?;
+    let cached = cache::store(current_hash, &tmp_path)?;
+    versions::record(current_hash)?;
+    Ok(cached)
+}
 
+/// Run `request` with the build URL of the active server, moving on to the
+/// next mirror while the server fails. The mirror that worked stays active
+fn with_mirrors<T>(tx: &bus::Sender, mut request: impl FnMut(&str) -> Result<T>) -> Result<T> {
+    let servers = profile::build_servers();
+    let first = profile::active_server();
+    let mut result = retry_busy_server(tx, || request(&profile::current().url()));
+    for offset in 1..servers.len() {
+        match &result {
+            Err(e) if server_failed(e) => {}
+            _ => break,
+        }
+        let next = (first + offset) % servers.len();
+        profile::use_server(next);
+        if tx
+            .send(Event::MirrorSwitched(servers[next].clone()))
+            .is_err()
+        {
+            return Err(eyre!("Channel disconnected when switching mirrors"));
+        }
+        result = retry_busy_server(tx, || request(&profile::current().url()));
+    }
+    result
+}
+
+// Another server may work where this one didn't answer or answered with an
+// error. A login page of the local network is in the way of every mirror
+fn server_failed(e: &eyre::Report) -> bool {
+    e.downcast_ref::<hash::CaptivePortal>().is_none()
+        && e.chain()
+            .any(|cause| cause.is::<HttpError>() || cause.is::<reqwest::Error>())
+}
+
+/// Repeat `request` while the build server reports a temporary problem
+fn retry_busy_server<T>(tx: &bus::Sender, mut request: impl FnMut() -> Result<T>) -> Result<T> {
+    let mut attempt = 1;
     loop {
-        let bytes_read = resp
-            .read(&mut buffer)
-            .wrap_err("Failed to read from HTTP stream")?;
-        if bytes_read == 0 {
-            break;
+        match request() {
+            Err(e)
+                if attempt < SERVER_ATTEMPTS
+                    && e.downcast_ref::<HttpError>()
+                        .is_some_and(HttpError::is_temporary) =>
+            {
+                let retry_in = e
+                    .downcast_ref::<HttpError>()
+                    .and_then(|error| error.retry_after)
+                    .map_or(SERVER_RETRY_DELAY, |delay| delay.min(MAX_RETRY_AFTER));
+                if tx
+                    .send(DownloadEvent::ServerBusy {
+                        reason: format!("{e}"),
+                        retry_in,
+                    })
+                    .is_err()
+                {
+                    return Err(eyre!("Channel disconnected when reporting a busy server"));
+                }
+                thread::sleep(retry_in);
+                attempt += 1;
+            }
+            result => return result,
         }
-        file.write_all(&buffer[..bytes_read])
-            .wrap_err("Failed to write binary file to disk")?;
-        downloaded += bytes_read as u64;
+    }
+}
+
+/// Ask the player before downloading past the monthly cap or on a metered
+/// connection, waiting for the answer
+fn confirm_download(tx: &bus::Sender) -> Result<()> {
+    let reason = if let Some(cap) = bandwidth::monthly_cap().filter(|_| bandwidth::over_cap()) {
+        format!(
+            "{} MiB were downloaded this month, over the {} MiB cap",
+            bandwidth::this_month() / MIB,
+            cap / MIB
+        )
+    } else if metered::downloads_need_confirmation() {
+        "The network connection is metered".to_string()
+    } else {
+        return Ok(());
+    };
+    let (answer, answered) = mpsc::channel();
+    if tx
+        .send(DownloadEvent::ConfirmDownload { reason, answer })
+        .is_err()
+    {
+        return Err(eyre!("Channel disconnected when asking to download"));
+    }
+    match answered.recv() {
+        Ok(true) => Ok(()),
+        _ => Err(eyre!("Download declined")),
+    }
+}
 
-        if tx.send(Event::DownloadProgress(downloaded)).is_err() {
-            return Err(eyre!("Launcher channel disconnected during download"));
+/// Put a cached build into place as the installed `GRAV.x86_64` (or the server build)
+fn install_game_binary(hash: &str, cached_path: &Path, tx: &bus::Sender) -> Result<PathBuf> {
+    let _span = info_span!("install_game_binary").entered();
+    // Checked again right before replacing the installed build: a cache entry
+    // can rot on disk, and a resumed download is stitched from two transfers
+    let actual = hash::compute_file_hash(cached_path)?;
+    if actual != hash {
+        let _ = fs::remove_file(cached_path);
+        if tx
+            .send(DownloadEvent::DownloadVerificationFailed {
+                expected: hash.to_string(),
+                actual,
+            })
+            .is_err()
+        {
+            return Err(eyre!("Channel disconnected when reporting a corrupt build"));
         }
+        return Err(eyre!(
+            "Build {hash} failed verification, the installed game was left as it is"
+        ));
+    }
+    let destination_path = dirs::base()
+        .place_install_file(profile::current().artifact())
+        .wrap_err("Can't create data file path")?;
+    install_journal::install(hash, cached_path, &destination_path)?;
+
+    if tx.send(DownloadEvent::GameBinaryUpdated).is_err() {
+        return Err(eyre!("Launcher channel disconnected after binary update"));
     }
+    Ok(destination_path)
+}
+
+/// Install a build the player downloaded by hand, if it is the published one.
+/// Returns its hash
+pub fn import_game_binary(file: &Path) -> Result<String> {
+    let _span = info_span!("import_game_binary", path = %file.display()).entered();
+    let remote_hash = hash::get_remote_hash(&profile::current().url())
+        .wrap_err("Can't get the published hash to check the file against")?;
+    import_verified(file, &remote_hash)?;
+    Ok(remote_hash)
+}
 
-    if tx.send(Event::RemoteBinaryDownloaded).is_err() {
+/// Install `file` if its hash is `expected`
+pub fn import_verified(file: &Path, expected: &str) -> Result<()> {
+    let file_hash = hash::compute_file_hash(file)?;
+    if file_hash != expected {
         return Err(eyre!(
-            "Launcher channel disconnected after download completed"
+            "{file:?} isn't the expected build, its hash is {file_hash} instead of {expected}"
         ));
     }
+    let cached = cache::import(&file_hash, file)?;
+    file_mode::make_executable(&cached)?;
+    versions::record(&file_hash)?;
+    let destination_path = dirs::base()
+        .place_install_file(profile::current().artifact())
+        .wrap_err("Can't create data file path")?;
+    install_journal::install(&file_hash, &cached, &destination_path)
+}
+
+/// Install a build from the version list and stay on it instead of updating
+pub fn install_version(hash: &str, tx: &bus::Sender) -> Result<()> {
+    restore_install(hash, tx)?;
+    versions::pin(Some(hash))
+}
 
-    check_exec_permissions(&tmp_path)?;
-    let destination_path = xdg_dirs
-        .place_data_file("GRAV.x86_64")
+/// Put the original of a modified build back from the download cache, or
+/// download it again if the cached copy is gone or damaged and it's still
+/// the published build
+pub fn restore_install(hash: &str, tx: &bus::Sender) -> Result<()> {
+    let cached = match intact_cache_entry(hash)? {
+        Some(cached) => cached,
+        None if with_mirrors(tx, hash::get_remote_hash)? == hash => fetch_game_binary(hash, tx)?,
+        None => {
+            return Err(eyre!(
+                "Build {hash} isn't in the download cache anymore and isn't published either"
+            ));
+        }
+    };
+    let destination_path = dirs::base()
+        .place_install_file(profile::current().artifact())
         .wrap_err("Can't create data file path")?;
-    fs::copy(&tmp_path, &destination_path)?;
+    install_journal::install(hash, &cached, &destination_path)
+}
 
-    if tx.send(Event::GameBinaryUpdated).is_err() {
-        return Err(eyre!("Launcher channel disconnected after binary update"));
+/// The cached build with this hash if its bytes still match it. A damaged
+/// entry is evicted, so it's downloaded again instead of installed
+fn intact_cache_entry(hash: &str) -> Result<Option<PathBuf>> {
+    let Some(cached) = cache::lookup(hash)? else {
+        return Ok(None);
+    };
+    if hash::compute_file_hash(&cached)? == hash {
+        return Ok(Some(cached));
     }
+    cache::remove(hash)?;
+    Ok(None)
+}
+
+/// Launch the game and watch it until it exits, installing any update that was
+/// published in the meantime once the game is closed
+fn run_the_game(
+    game_path: PathBuf,
+    running_hash: Option<&str>,
+    tx: &bus::Sender,
+) -> Result<ExitStatus> {
+    let child = if USE_PTY.load(Ordering::Relaxed) {
+        spawn_game_pty(&game_path, tx)?
+    } else {
+        GameProcess::Piped(spawn_game(game_path, tx)?)
+    };
+    let _latency = latency::monitor(tx);
+    let _usage = child.id().map(|pid| usage::monitor(pid, tx));
+    prefetch::start(tx);
+    supervise_game(child, running_hash, tx)
+}
+
+/// Launch another program from the install instead of the game build, or
+/// the build again with `None`
+pub fn set_launch_target(name: Option<String>) {
+    *LAUNCH_TARGET.lock().unwrap_or_else(PoisonError::into_inner) = name;
+}
+
+static LAUNCH_TARGET: Mutex<Option<String>> = Mutex::new(None);
+
+// The picked program, if it's still one of the configured ones
+fn launch_target() -> Option<String> {
+    let target = LAUNCH_TARGET
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()?;
+    let profile = profile::current();
+    (target != profile.artifact() && profile.launch_targets().contains(&target.as_str()))
+        .then_some(target)
+}
 
-    Ok(tmp_path)
+/// Run the game under a pseudo-terminal instead of pipes
+pub fn use_pty(enabled: bool) {
+    USE_PTY.store(enabled, Ordering::Relaxed);
 }
 
-fn run_the_game(game_path: PathBuf, tx: &mpsc::Sender<Event>) -> Result<()> {
-    if tx.send(Event::Launching).is_err() {
+static USE_PTY: AtomicBool = AtomicBool::new(false);
+
+/// Start the game on its own, so it keeps running when the launcher or its
+/// terminal is closed. Stopping it from the launcher still works
+pub fn detach_game(enabled: bool) {
+    DETACH_GAME.store(enabled, Ordering::Relaxed);
+}
+
+static DETACH_GAME: AtomicBool = AtomicBool::new(false);
+
+/// Stop capturing the running game's output, so the launcher and its
+/// terminal can be closed while the game keeps running
+pub fn release_game() -> Result<()> {
+    if USE_PTY.load(Ordering::Relaxed) {
+        return Err(eyre!(
+            "The game runs under --pty, it ends with the launcher's pseudo-terminal"
+        ));
+    }
+    capture::release();
+    Ok(())
+}
+
+/// Wait a random time up to `max` before asking the build server for a new
+/// build, so a fleet of launchers started together doesn't ask all at once
+pub fn set_check_jitter(max: Duration) {
+    CHECK_JITTER_SECS.store(max.as_secs(), Ordering::Relaxed);
+}
+
+// 0 checks right away
+static CHECK_JITTER_SECS: AtomicU64 = AtomicU64::new(0);
+
+fn wait_check_jitter(tx: &bus::Sender) -> Result<()> {
+    let max_millis = CHECK_JITTER_SECS.load(Ordering::Relaxed) * 1000;
+    if max_millis == 0 {
+        return Ok(());
+    }
+    // Hashers are seeded randomly for every process
+    let random = RandomState::new().build_hasher().finish();
+    let delay = Duration::from_millis(random % (max_millis + 1));
+    if tx.send(DownloadEvent::CheckDelayed(delay)).is_err() {
+        return Err(eyre!("Channel disconnected when delaying the build check"));
+    }
+    thread::sleep(delay);
+    Ok(())
+}
+
+/// Report the game as stuck when it prints nothing for this long after launch
+pub fn set_startup_timeout(timeout: Duration) {
+    STARTUP_TIMEOUT_SECS.store(timeout.as_secs(), Ordering::Relaxed);
+}
+
+// 0 disables the check
+static STARTUP_TIMEOUT_SECS: AtomicU64 = AtomicU64::new(0);
+
+/// Ask the running game to quit and kill it if it's still running after a
+/// grace period, picked up by the thread supervising it
+pub fn stop_game() {
+    STOP_REQUESTED.store(true, Ordering::Relaxed);
+}
+
+static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// The running game, with its output piped or behind a pseudo-terminal
+enum GameProcess {
+    Piped(Child),
+    Pty(pty::PtyGame),
+}
+
+impl GameProcess {
+    fn try_wait(&mut self) -> std::io::Result<Option<ExitStatus>> {
+        match self {
+            Self::Piped(child) => child.try_wait(),
+            Self::Pty(game) => game.try_wait(),
+        }
+    }
+
+    fn kill(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::Piped(child) => child.kill(),
+            Self::Pty(game) => game.kill(),
+        }
+    }
+
+    // Falls back to killing where the game can't be asked to quit
+    fn terminate(&mut self) -> std::io::Result<()> {
+        match self.id() {
+            Some(pid) if platform::terminate(pid).is_ok() => Ok(()),
+            _ => self.kill(),
+        }
+    }
+
+    fn id(&self) -> Option<u32> {
+        match self {
+            Self::Piped(child) => Some(child.id()),
+            Self::Pty(game) => game.id(),
+        }
+    }
+}
+
+// Everything the game prints arrives on one stream, so it all goes to the
+// output pane. Lines are cleaned up to what a terminal would show
+fn spawn_game_pty(game_path: &Path, tx: &bus::Sender) -> Result<GameProcess> {
+    if tx.send(GameEvent::Launching).is_err() {
         return Err(eyre!("Launcher channel disconnected"));
     }
+    report_environment(tx)?;
+    let (program, args) = priority::game_command(game_path, &game_env::args());
+    capture::resume();
+    let (game, streams) = pty::spawn(
+        &program,
+        &args,
+        &game_env::working_dir(),
+        &game_env::overrides(),
+    )?;
+    let (mut reader, mut writer) = (streams.output, streams.input);
 
-    let mut child = Command::new(game_path)
+    // Errors can't be told apart from other output on a terminal
+    let mut sender = OutputSender::new(tx.clone(), GameEvent::Output).keep_recent();
+    thread::spawn(move || {
+        let mut buffer = [0; 4096];
+        let mut line = Vec::new();
+        // Reading fails with EIO once the game exits and its terminal is gone
+        while let Ok(read) = reader.read(&mut buffer) {
+            if read == 0 {
+                break;
+            }
+            let mut batch = Vec::new();
+            for &byte in &buffer[..read] {
+                if byte == b'\n' {
+                    batch.push(pty::clean_line(&line));
+                    line.clear();
+                } else {
+                    line.push(byte);
+                }
+            }
+            if !sender.send(batch) {
+                return;
+            }
+        }
+        if !line.is_empty() {
+            sender.send(vec![pty::clean_line(&line)]);
+        }
+    });
+
+    let (stdin_tx, stdin_rx) = mpsc::channel::<String>();
+    thread::spawn(move || {
+        for line in stdin_rx {
+            // A terminal sends a carriage return for Enter
+            if write!(writer, "{line}\r")
+                .and_then(|()| writer.flush())
+                .is_err()
+            {
+                return;
+            }
+        }
+    });
+    let _ = tx.send(GameEvent::StdinReady(stdin_tx));
+
+    Ok(GameProcess::Pty(game))
+}
+
+fn report_environment(tx: &bus::Sender) -> Result<()> {
+    tx.send(GameEvent::Environment(game_env::report()))
+        .map_err(|_| eyre!("Launcher channel disconnected"))
+}
+
+fn spawn_game(game_path: PathBuf, tx: &bus::Sender) -> Result<Child> {
+    if tx.send(GameEvent::Launching).is_err() {
+        return Err(eyre!("Launcher channel disconnected"));
+    }
+
+    report_environment(tx)?;
+    let (program, args) = priority::game_command(&game_path, &game_env::args());
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .current_dir(game_env::working_dir())
+        .envs(game_env::overrides().into_iter().map(|o| (o.name, o.value)))
+        .stdin(Stdio::piped())
         .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .wrap_err("Failed to launch game binary")?;
+        .stderr(Stdio::piped());
+    if DETACH_GAME.load(Ordering::Relaxed) {
+        platform::detach(&mut command);
+    } else {
+        platform::releasable(&mut command);
+    }
+    capture::resume();
+    let mut child = command.spawn().wrap_err("Failed to launch game binary")?;
 
     let stdout = child
         .stdout
         .take()
         .ok_or_else(|| eyre!("Failed to capture stdout"))?;
-    let tx_stdout = tx.clone();
-    thread::spawn(move || {
-        let reader = BufReader::new(stdout);
-        for line in reader.lines() {
-            match line {
-                Ok(l) => {
-                    if tx_stdout.send(Event::GameOutput(l)).is_err() {
-                        eprintln!("Game output channel disconnected, shutting down stdout thread");
-                        return;
-                    }
-                }
-                Err(e) => {
-                    if tx_stdout
-                        .send(Event::GameExecutionError(format!("stdout read: {e}")))
-                        .is_err()
-                    {
-                        eprintln!("Game output channel disconnected, shutting down stdout thread");
-                        return;
-                    }
-                }
-            }
+    thread::spawn({
+        let tx = tx.clone();
+        move || {
+            let sender = OutputSender::new(tx.clone(), GameEvent::Output);
+            capture::forward_output(stdout, "stdout", sender, &tx);
         }
     });
 
@@ -275,40 +997,168 @@ fn run_the_game(game_path: PathBuf, tx: &mpsc::Sender<Event>) -> Result<()> {
         .stderr
         .take()
         .ok_or_else(|| eyre!("Failed to capture stderr"))?;
-    let tx_stderr = tx.clone();
+    thread::spawn({
+        let tx = tx.clone();
+        move || {
+            let sender = OutputSender::new(tx.clone(), GameEvent::ErrorOutput).keep_recent();
+            capture::forward_output(stderr, "stderr", sender, &tx);
+        }
+    });
+
+    // Lines typed in the console prompt, e.g. admin commands for a server build
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| eyre!("Failed to capture stdin"))?;
+    let (stdin_tx, stdin_rx) = mpsc::channel::<String>();
     thread::spawn(move || {
-        let reader = BufReader::new(stderr);
-        for line in reader.lines() {
-            match line {
-                Ok(l) => {
-                    if tx_stderr.send(Event::GameErrorOutput(l)).is_err() {
-                        eprintln!(
-                            "Game error output channel disconnected, shutting down stderr thread"
-                        );
-                        return;
-                    }
-                }
-                Err(e) => {
-                    if tx_stderr
-                        .send(Event::GameExecutionError(format!("stderr read: {e}")))
-                        .is_err()
-                    {
-                        eprintln!(
-                            "Game error output channel disconnected, shutting down stderr thread"
-                        );
-                        return;
-                    }
-                }
+        for line in stdin_rx {
+            // The game closed its stdin or exited
+            if writeln!(stdin, "{line}")
+                .and_then(|()| stdin.flush())
+                .is_err()
+            {
+                return;
             }
         }
     });
+    let _ = tx.send(GameEvent::StdinReady(stdin_tx));
 
-    Ok(())
+    Ok(child)
 }
 
-fn check_exec_permissions(binary_path: &PathBuf) -> Result<()> {
-    let permissions = fs::Permissions::from_mode(0o744);
-    fs::set_permissions(binary_path, permissions)
-        .wrap_err_with(|| format!("Failed to set execute permissions for {binary_path:?}"))?;
-    Ok(())
+/// Poll the running game for exit while periodically checking for a newer build.
+/// A newer build is downloaded in the background and only installed after exit,
+/// since the running binary must not be replaced under the game
+fn supervise_game(
+    mut child: GameProcess,
+    running_hash: Option<&str>,
+    tx: &bus::Sender,
+) -> Result<ExitStatus> {
+    // A modified build runs without a hash, the record of what was installed
+    // keeps its original from being downloaded again
+    let mut known_hash = running_hash
+        .map(str::to_string)
+        .or_else(install_record::installed_hash);
+    let started = Instant::now();
+    let mut proven = false;
+    let startup_timeout = Duration::from_secs(STARTUP_TIMEOUT_SECS.load(Ordering::Relaxed));
+    let mut liveness_checked = startup_timeout.is_zero();
+    let lines_at_launch = capture::captured();
+    STOP_REQUESTED.store(false, Ordering::Relaxed);
+    let mut kill_at: Option<Instant> = None;
+    let mut last_check = Instant::now();
+    let mut background_download: Option<(String, JoinHandle<Result<PathBuf>>)> = None;
+    let mut staged: Option<(String, PathBuf)> = None;
+
+    let exit_status = loop {
+        if let Some(status) = child.try_wait().wrap_err("Failed to poll game process")? {
+            break status;
+        }
+
+        if STOP_REQUESTED.swap(false, Ordering::Relaxed) {
+            child.terminate().wrap_err("Failed to stop the game")?;
+            kill_at = Some(Instant::now() + TERMINATE_GRACE);
+        }
+        if kill_at.is_some_and(|at| Instant::now() >= at) {
+            kill_at = None;
+            child.kill().wrap_err("Failed to kill the game")?;
+        }
+
+        // Running but silent, likely hung before showing a window
+        if !liveness_checked && started.elapsed() >= startup_timeout {
+            liveness_checked = true;
+            if capture::captured() == lines_at_launch
+                && tx.send(GameEvent::StartupStalled(startup_timeout)).is_err()
+            {
+                return Err(eyre!("Launcher channel disconnected"));
+            }
+        }
+
+        if !proven && started.elapsed() >= STARTUP_CRASH_WINDOW {
+            proven = true;
+            if let Some(hash) = running_hash {
+                let _ = known_good::record_good(hash);
+            }
+        }
+
+        if background_download
+            .as_ref()
+            .is_some_and(|(_, handle)| handle.is_finished())
+            && let Some((hash, handle)) = background_download.take()
+        {
+            staged = finish_background_download(hash, handle, tx);
+        }
+
+        if staged.is_none()
+            && background_download.is_none()
+            && last_check.elapsed() >= UPDATE_CHECK_INTERVAL
+        {
+            last_check = Instant::now();
+            if known_hash.is_none() {
+                known_hash = priority::in_background(hash::get_local_hash)
+                    .ok()
+                    .flatten()
+                    .map(|(h, _)| h);
+            }
+            if let Ok(remote_hash) = hash::get_remote_hash(&profile::current().url())
+                && known_hash.as_deref() != Some(remote_hash.as_str())
+                && !known_good::is_crashed(&remote_hash)
+                && versions::pinned().is_none()
+            {
+                let download_tx = tx.clone();
+                let download_hash = remote_hash.clone();
+                let handle = thread::spawn(move || {
+                    priority::lower_current_thread();
+                    cached_or_fetch(&download_hash, &download_tx)
+                });
+                background_download = Some((remote_hash, handle));
+            }
+        }
+
+        thread::sleep(GAME_POLL_INTERVAL);
+    };
+
+    if !proven
+        && exit_status.success()
+        && let Some(hash) = running_hash
+    {
+        let _ = known_good::record_good(hash);
+    }
+
+    // Let a download that is still running finish before installing it
+    if let Some((hash, handle)) = background_download.take() {
+        staged = finish_background_download(hash, handle, tx);
+    }
+
+    if let Some((hash, path)) = staged {
+        install_game_binary(&hash, &path, tx)?;
+        if tx.send(DownloadEvent::GameUpdateInstalled(hash)).is_err() {
+            return Err(eyre!("Launcher channel disconnected after staged install"));
+        }
+    }
+    Ok(exit_status)
+}
+
+fn finish_background_download(
+    hash: String,
+    handle: JoinHandle<Result<PathBuf>>,
+    tx: &bus::Sender,
+) -> Option<(String, PathBuf)> {
+    match handle.join() {
+        Ok(Ok(path)) => {
+            let _ = tx.send(DownloadEvent::GameUpdateStaged(hash.clone()));
+            Some((hash, path))
+        }
+        Ok(Err(e)) => {
+            let _ = tx.send(DownloadEvent::BinaryDownloadError(format!("{e}")));
+            None
+        }
+        Err(_) => {
+            let _ = tx.send(DownloadEvent::BinaryDownloadError(
+                "Background download thread panicked".into(),
+            ));
+            None
+        }
+    }
 }
@@ -0,0 +1,79 @@
+use std::io::{BufWriter, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread;
+use std::time::Duration;
+
+/// Lines waiting for a slow or unreachable collector before new ones are dropped
+const QUEUE_SIZE: usize = 4096;
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Opt-in streaming of log lines to a remote collector over TCP, one line of
+/// text per log line. Sending never blocks: when the queue is full lines are
+/// dropped and the collector is told how many were lost
+pub struct LogStream {
+    tx: SyncSender<String>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl LogStream {
+    /// Start streaming to `address` (host:port) from a background thread,
+    /// reconnecting whenever the connection is lost
+    pub fn connect(address: String) -> Self {
+        let (tx, rx) = mpsc::sync_channel(QUEUE_SIZE);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let thread_dropped = Arc::clone(&dropped);
+        thread::spawn(move || stream_lines(&address, &rx, &thread_dropped));
+        Self { tx, dropped }
+    }
+
+    pub fn send(&self, line: String) {
+        if let Err(TrySendError::Full(_)) = self.tx.try_send(line) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+fn stream_lines(address: &str, rx: &Receiver<String>, dropped: &AtomicU64) {
+    // A line taken from the queue but not yet delivered
+    let mut pending: Option<String> = None;
+    loop {
+        let Ok(stream) = TcpStream::connect(address) else {
+            thread::sleep(RECONNECT_DELAY);
+            continue;
+        };
+        let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+        let mut writer = BufWriter::new(stream);
+        loop {
+            let line = match pending.take() {
+                Some(line) => line,
+                None => match rx.recv() {
+                    Ok(line) => line,
+                    // The launcher is shutting down
+                    Err(_) => return,
+                },
+            };
+            if write_line(&mut writer, &line, dropped).is_err() {
+                pending = Some(line);
+                break;
+            }
+        }
+        thread::sleep(RECONNECT_DELAY);
+    }
+}
+
+fn write_line(
+    writer: &mut BufWriter<TcpStream>,
+    line: &str,
+    dropped: &AtomicU64,
+) -> std::io::Result<()> {
+    let lost = dropped.swap(0, Ordering::Relaxed);
+    if lost > 0 {
+        writeln!(writer, "[stream] {lost} lines dropped")?;
+    }
+    writeln!(writer, "{line}")?;
+    writer.flush()
+}
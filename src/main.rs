@@ -1,34 +1,92 @@
 use std::env;
-use std::io::{self, IsTerminal};
-use std::path::PathBuf;
+use std::fs::File;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::process::{Command, exit};
-use std::sync::mpsc;
 use std::thread;
 use std::time::{Duration, Instant};
 
+use clap::Parser;
 use color_eyre::Result;
-use gilrs::{Axis, EventType, Gilrs};
+use eyre::WrapErr;
+use gilrs::{Axis, Button, EventType, Gilrs};
+use tracing_chrome::{ChromeLayerBuilder, FlushGuard};
+use tracing_subscriber::prelude::*;
 
 use crossterm::event as terminal_event;
 use crossterm::event::Event as CrosstermEvent;
 use crossterm::execute;
 
+mod bus;
 mod event;
-use crate::event::Event;
+use crate::event::{Event, InputEvent, UpdateEvent};
 
 mod app;
+mod cli;
+use crate::cli::{Cli, Commands};
+use crate::debounce::{Debounce, LongPress, MinHold};
+use crate::hooks::Hook;
+use crate::profile::Profile;
+use crate::ui::theme::Theme;
+use crate::update::NoSelfUpdate;
+use crate::update::provider::ProviderKind;
+mod bandwidth;
+mod build_info;
+mod bundle;
+mod cache;
+mod capture;
+mod config;
+mod debounce;
+mod dirs;
+mod downloads;
+mod elf;
+mod file_mode;
+mod game_env;
 mod hash;
+mod headless;
+mod hooks;
+mod http;
+mod install_journal;
+mod install_record;
+mod known_good;
+mod latency;
 mod launcher;
+mod log_stream;
+mod metered;
+mod net;
+mod notes;
+mod ownership;
+mod platform;
+mod prefetch;
+mod priority;
+mod profile;
+mod progress_fd;
+mod pty;
+mod region;
+mod session_log;
+mod status;
+#[cfg(feature = "tray")]
+mod tray;
+mod troubleshoot;
 mod ui;
 mod update;
+mod usage;
+mod versions;
 
-static BASE_URL: &str = "https://grav.arigven.games/builds/GRAV.x86_64";
+static BUILDS_URL: &str = "https://grav.arigven.games/builds";
 static VERSION: &str = env!("CARGO_PKG_VERSION");
 static REPOSITORY: &str = env!("CARGO_PKG_REPOSITORY");
 
-struct TerminalConfig {
-    name: &'static str,
-    exec_flag: &'static str,
+struct TerminalConfig<'a> {
+    name: &'a str,
+    exec_flag: &'a str,
+    /// Open the window from this process instead of handing it to a running
+    /// instance, whose windows Steam's game mode and focus stealing prevention
+    /// leave in the background
+    own_process_flags: &'a [&'a str],
+    /// Start the window fullscreen, window managers keep it above the others
+    /// and Steam's game mode shows it instead of its own UI
+    foreground_flags: &'a [&'a str],
 }
 
 fn enable_focus_reporting() -> Result<()> {
@@ -43,40 +101,79 @@ fn disable_focus_reporting() -> Result<()> {
     Ok(())
 }
 
+fn save_terminal_title() -> Result<()> {
+    // Push the current window title onto the terminal's title stack (XTWINOPS)
+    let mut stdout = io::stdout();
+    stdout.write_all(b"\x1b[22;0t")?;
+    stdout.flush()?;
+    Ok(())
+}
+
+fn restore_terminal_title() -> Result<()> {
+    // Pop the window title saved at startup
+    let mut stdout = io::stdout();
+    stdout.write_all(b"\x1b[23;0t")?;
+    stdout.flush()?;
+    Ok(())
+}
+
 fn get_executable_path() -> Option<PathBuf> {
     env::current_exe().ok()
 }
 
-fn find_terminal_emulator() -> Option<TerminalConfig> {
+fn find_terminal_emulator(preferred: Option<&str>) -> Option<TerminalConfig<'_>> {
     // Prioritize common terminal emulators with their exec flags
     // Different terminals use different flags to execute commands
     let terminal_configs = [
         TerminalConfig {
             name: "konsole",
             exec_flag: "-e",
+            own_process_flags: &["--separate"],
+            foreground_flags: &["--fullscreen"],
         },
         TerminalConfig {
             name: "gnome-terminal",
             exec_flag: "--",
+            own_process_flags: &["--wait"],
+            foreground_flags: &["--full-screen"],
         },
         TerminalConfig {
             name: "xfce4-terminal",
             exec_flag: "-e",
+            own_process_flags: &["--disable-server"],
+            foreground_flags: &["--fullscreen"],
         },
         TerminalConfig {
             name: "kitty",
             exec_flag: "-e",
+            own_process_flags: &[],
+            foreground_flags: &["--start-as=fullscreen"],
         },
         TerminalConfig {
             name: "alacritty",
             exec_flag: "-e",
+            own_process_flags: &[],
+            foreground_flags: &["--option", "window.startup_mode=\"Fullscreen\""],
         },
         TerminalConfig {
             name: "xterm",
             exec_flag: "-e",
+            own_process_flags: &[],
+            foreground_flags: &["-fullscreen"],
         },
     ];
 
+    // The configured terminal goes first, with its exec flag if it's a known one
+    if let Some(name) = preferred {
+        let known = terminal_configs.iter().find(|config| config.name == name);
+        return Some(TerminalConfig {
+            name,
+            exec_flag: known.map_or("-e", |config| config.exec_flag),
+            own_process_flags: known.map_or(&[], |config| config.own_process_flags),
+            foreground_flags: known.map_or(&[], |config| config.foreground_flags),
+        });
+    }
+
     for config in &terminal_configs {
         if Command::new("which")
             .arg(config.name)
@@ -87,6 +184,8 @@ fn find_terminal_emulator() -> Option<TerminalConfig> {
             return Some(TerminalConfig {
                 name: config.name,
                 exec_flag: config.exec_flag,
+                own_process_flags: config.own_process_flags,
+                foreground_flags: config.foreground_flags,
             });
         }
     }
@@ -94,7 +193,11 @@ fn find_terminal_emulator() -> Option<TerminalConfig> {
     None
 }
 
-fn relaunch_in_terminal() -> Result<()> {
+/// Where the desktop passes a started program the right to take focus:
+/// xdg-activation on Wayland and startup notification on X11
+const ACTIVATION_TOKEN_VARS: [&str; 2] = ["XDG_ACTIVATION_TOKEN", "DESKTOP_STARTUP_ID"];
+
+fn relaunch_in_terminal(preferred: Option<&str>, detach: bool) -> Result<()> {
     // Get the path to the current executable
     let executable_path = match get_executable_path() {
         Some(path) => path,
@@ -105,7 +208,7 @@ fn relaunch_in_terminal() -> Result<()> {
     };
 
     // Find a suitable terminal emulator
-    let terminal_config = match find_terminal_emulator() {
+    let terminal_config = match find_terminal_emulator(preferred) {
         Some(config) => config,
         None => {
             eprintln!("No suitable terminal emulator found");
@@ -114,53 +217,297 @@ fn relaunch_in_terminal() -> Result<()> {
     };
 
     // Launch the terminal with the application
-    Command::new(terminal_config.name)
+    let mut command = Command::new(terminal_config.name);
+    command
+        .args(terminal_config.own_process_flags)
+        .args(terminal_config.foreground_flags)
         .arg(terminal_config.exec_flag)
-        .arg(executable_path)
-        .spawn()
+        .arg(executable_path);
+    // Hand the activation token of whoever started us over to the terminal,
+    // which uses it to take focus. It's good for one window only, so nothing
+    // else started from here gets it
+    for var in ACTIVATION_TOKEN_VARS {
+        if let Some(token) = env::var_os(var) {
+            command.env(var, token);
+            // SAFETY: none of the threads started so far reads the environment
+            unsafe { env::remove_var(var) };
+        }
+    }
+    if detach {
+        platform::detach(&mut command);
+        command
+            .spawn()
+            .map_err(|e| eyre::eyre!("Failed to launch terminal: {}", e))?;
+        exit(0);
+    }
+    let status = command
+        .status()
         .map_err(|e| eyre::eyre!("Failed to launch terminal: {}", e))?;
 
-    // Exit the current process since we've spawned a new one
-    exit(0);
+    // Stay until the window closes: Steam counts a shortcut that exits as a
+    // closed game and brings its own UI back over the terminal
+    exit(status.code().unwrap_or(1));
+}
+
+/// Record spans from all threads to `path` in the Chrome trace format
+fn start_tracing(path: &Path) -> Result<FlushGuard> {
+    let file = File::create(path).wrap_err_with(|| format!("Failed to create {path:?}"))?;
+    let (layer, guard) = ChromeLayerBuilder::new()
+        .writer(file)
+        .include_args(true)
+        .build();
+    tracing_subscriber::registry().with(layer).init();
+    Ok(guard)
 }
 
 fn main() -> Result<()> {
     color_eyre::install()?;
 
-    // Check if --no-terminal flag is provided
-    let args: Vec<String> = env::args().collect();
-    let skip_terminal_check = args.iter().any(|arg| arg == "--no-terminal");
+    let mut cli = Cli::parse();
+    let config = config::load(cli.config.as_deref())?;
+    enforce_policy(&mut cli);
+    let shared_install = cli.shared_install.clone().or(config.shared_install);
+    file_mode::init(cli.binary_mode, shared_install.is_some());
+    dirs::init(cli.data_dir.clone().or(config.data_dir), shared_install);
+    match cli.command {
+        Some(Commands::Manpage) => return cli::print_manpage(),
+        Some(Commands::RepairOwnership) => {
+            let repaired = ownership::repair()?;
+            println!("Changed the owner of {repaired} files");
+            return Ok(());
+        }
+        // Installing builds is handled once the profile is known
+        _ => {}
+    }
+
+    if ownership::is_root() && !cli.allow_root {
+        eprintln!(
+            "grav-launcher is running as root, files it creates would be owned by root \
+             and break updates when you run it as yourself.\n\
+             Run it without sudo, or pass --allow-root if you really mean it.\n\
+             Files left by an earlier run as root are fixed by \
+             `sudo grav-launcher repair-ownership`."
+        );
+        exit(1);
+    }
+
+    if cli.server {
+        profile::set(Profile::Server);
+    }
+    if let Some(name) = config.artifact {
+        profile::set_artifact(name);
+    }
+    profile::set_extra_executables(config.executables);
+    // A region set by the policy wins over the one the player saved
+    let region = if config::is_enforced("region") {
+        config.region
+    } else if let Some(name) = cli.region.clone() {
+        region::try_out(name.clone());
+        Some(name)
+    } else {
+        region::saved().or(config.region)
+    };
+    if let Some(name) = region {
+        profile::set_region(name);
+    }
+    match &cli.command {
+        Some(Commands::Import { file }) => {
+            let hash = launcher::import_game_binary(file)?;
+            println!(
+                "Installed {} with hash {hash}",
+                profile::current().artifact()
+            );
+            return Ok(());
+        }
+        Some(Commands::ExportBundle { dir }) => {
+            let hash = bundle::export(dir)?;
+            println!(
+                "Exported {} with hash {hash} to {}",
+                profile::current().artifact(),
+                dir.display()
+            );
+            return Ok(());
+        }
+        Some(Commands::ImportBundle { dir }) => {
+            let hash = bundle::import(dir)?;
+            println!(
+                "Installed {} with hash {hash}",
+                profile::current().artifact()
+            );
+            return Ok(());
+        }
+        _ => {}
+    }
+    update::provider::select(
+        cli.update_provider
+            .or(config.update_provider)
+            .unwrap_or(ProviderKind::Github),
+        cli.update_url.clone().or(config.update_url),
+    )?;
+    launcher::use_pty(cli.pty);
+    if let Some(dir) = cli.game_workdir.clone() {
+        game_env::set_working_dir(dir);
+    }
+    game_env::set_extra_args(cli.game_args.clone());
+    for (name, value) in &cli.game_env {
+        game_env::set(name, value, "command line");
+    }
+    for (hook, command) in [
+        (Hook::DownloadComplete, &cli.on_download_complete),
+        (Hook::Error, &cli.on_error),
+        (Hook::GameExit, &cli.on_game_exit),
+    ] {
+        if let Some(command) = command {
+            hooks::set(hook, command.clone());
+        }
+    }
+    install_record::always_verify(cli.verify_install);
+    priority::use_background_priority(cli.background_priority);
+    if let Some(weight) = cli.game_weight {
+        priority::set_game_weight(weight);
+    }
+    if let Some(cpus) = cli.game_cpus.clone() {
+        priority::set_game_cpus(cpus);
+    }
+    if let Some(nice) = cli.game_nice {
+        priority::set_game_nice(nice);
+    }
+    priority::use_game_scope(cli.game_scope);
+    if let Some(adj) = cli.game_oom_score_adj {
+        priority::set_game_oom_score_adj(adj);
+    }
+    if let Some(adj) = cli.launcher_oom_score_adj
+        && let Err(e) = priority::set_launcher_oom_score_adj(adj)
+    {
+        eprintln!("Failed to set the launcher's OOM score adjustment: {e}");
+    }
+    if let Some(url) = config
+        .builds_url
+        .or_else(|| config.channel.as_deref().map(profile::channel_url))
+    {
+        profile::set_builds_url(url);
+    }
+    profile::set_mirrors(config.mirrors);
+    if let Some(Commands::Regions) = cli.command {
+        print_regions();
+        return Ok(());
+    }
+    if config.self_update == Some(false) {
+        update::disable_self_update();
+    }
+    // Apply an update the user chose to install on the next start, unless the
+    // config or the package manager took self-update away since
+    match update::apply_staged_update() {
+        Ok(Some(version)) => {
+            println!("Applied launcher update {version}, restarting...");
+            update::restart_launcher()?;
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("Failed to apply staged launcher update: {e}"),
+    }
+    prefetch::set_channels(config.prefetch);
+    launcher::use_manual_launch(cli.manual_launch || config.manual_launch == Some(true));
+    if let Some(seconds) = cli.launch_countdown.or(config.launch_countdown) {
+        launcher::set_launch_countdown(Duration::from_secs(seconds));
+    }
+    if let Some(seconds) = cli.check_jitter.or(config.check_jitter) {
+        launcher::set_check_jitter(Duration::from_secs(seconds));
+    }
+    if let Some(count) = cli.keep_versions.or(config.keep_versions) {
+        versions::set_keep(count as usize);
+    }
+    if let Some(seconds) = cli.startup_timeout {
+        launcher::set_startup_timeout(Duration::from_secs(seconds));
+    }
+    if let Some(address) = cli.game_server.clone() {
+        latency::set_server(address);
+    }
+    if let Some(address) = &cli.status_addr {
+        status::serve(address)?;
+    }
+    if let Some(fd) = cli.progress_fd {
+        progress_fd::open(fd)?;
+    }
+
+    // Shared bandwidth limit for all downloads
+    if let Some(kib_per_second) = cli.limit_rate {
+        downloads::set_rate_limit(kib_per_second * 1024);
+    }
+    metered::allow_metered(cli.allow_metered || config.allow_metered == Some(true));
+    if let Some(mib) = cli.monthly_cap {
+        bandwidth::set_monthly_cap(mib * 1024 * 1024);
+    }
+
+    let detach = cli.detach || config.detach == Some(true);
+    launcher::detach_game(detach);
+
+    if cli.headless {
+        let trace_guard = cli.trace_output.as_deref().map(start_tracing).transpose()?;
+        let (tx, rx) = bus::channel();
+        let code = headless::run(tx, &rx);
+        drop(trace_guard);
+        exit(code);
+    }
 
     // Check if running in terminal
-    if !skip_terminal_check && !io::stdout().is_terminal() {
+    if !cli.no_terminal && !io::stdout().is_terminal() {
         println!("Not running in a terminal, relaunching...");
-        relaunch_in_terminal()?;
+        relaunch_in_terminal(config.terminal.as_deref(), detach)?;
         return Ok(());
     }
 
+    // Flushed when dropped, kept until the launcher exits
+    let trace_guard = cli.trace_output.as_deref().map(start_tracing).transpose()?;
+
     let mut terminal = ratatui::init();
-    let (tx, rx) = mpsc::channel();
+    let (tx, rx) = bus::channel();
 
     // Enable terminal focus event reporting
     enable_focus_reporting()?;
 
+    // Remember the original window title so progress titles can be undone
+    save_terminal_title()?;
+
     // Initialize controller input handling
-    controller_input_handling(tx.clone());
+    let inputs = InputTiming {
+        debounce: Duration::from_millis(cli.input_debounce),
+        min_hold: Duration::from_millis(cli.min_hold),
+    };
+    controller_input_handling(
+        tx.clone(),
+        Duration::from_millis(cli.controller_poll),
+        &inputs,
+    );
 
     // Initialize keyboard input handler
-    input_handling(tx.clone());
+    input_handling(tx.clone(), Duration::from_millis(cli.tick_rate), &inputs);
 
     // Check for launcher update
     let update_tx = tx.clone();
     thread::spawn(move || {
+        match update::self_update_blocked() {
+            Some(NoSelfUpdate::ManagedExternally) => {
+                let _ = update_tx.send(UpdateEvent::ManagedExternally);
+                return;
+            }
+            Some(NoSelfUpdate::Disabled) => {
+                let _ = update_tx.send(UpdateEvent::Disabled);
+                return;
+            }
+            Some(NoSelfUpdate::ReadOnly(user_path)) => {
+                let _ = update_tx.send(UpdateEvent::ReadOnlyInstall(user_path));
+                return;
+            }
+            None => {}
+        }
         // Check for new updates
-        let _ = update_tx.send(Event::CheckingForLauncherUpdate);
+        let _ = update_tx.send(UpdateEvent::Checking);
         match update::check_for_update(VERSION) {
             Ok(Some(version)) => {
-                let _ = update_tx.send(Event::LauncherUpdateAvailable(version));
+                let _ = update_tx.send(UpdateEvent::Available(version));
             }
             Ok(None) => {
-                let _ = update_tx.send(Event::LauncherNoUpdateAvailable);
+                let _ = update_tx.send(UpdateEvent::NoUpdateAvailable);
             }
             Err(e) => {
                 let _ = update_tx.send(Event::LauncherError(format!(
@@ -170,24 +517,106 @@ fn main() -> Result<()> {
         }
     });
 
+    // The launcher thread keeps supervising the game after launch,
+    // so it is not joined on exit
     let launcher_tx = tx.clone();
-    let thread_join_handle = thread::spawn(move || launcher::launcher_logic(launcher_tx));
+    thread::spawn(move || launcher::launcher_logic(launcher_tx));
 
-    let app_result = app::run(&mut terminal, &rx, tx);
+    let mut theme = Theme::detect();
+    if cli.no_color {
+        theme = theme.monochrome();
+    }
+    if let Some(gauge) = cli.gauge.or(config.gauge) {
+        theme.gauge = gauge;
+    }
+    let kiosk = cli.kiosk || config.kiosk == Some(true);
+    let app_result = app::run(&mut terminal, &rx, tx, &cli, theme, kiosk);
 
     // Cleanup
+    restore_terminal_title()?;
     disable_focus_reporting()?;
     ratatui::restore();
 
-    let _res = thread_join_handle.join();
-    app_result
+    drop(trace_guard);
+    if app_result? == app::AppExit::Restart {
+        update::restart_launcher()?;
+    }
+    Ok(())
+}
+
+// Recommend the fastest region, it's only switched to when asked
+fn print_regions() {
+    let latencies = region::measure();
+    for (name, latency) in &latencies {
+        match latency {
+            Some(latency) => println!("{name:<6}{} ms", latency.as_millis()),
+            None => println!("{name:<6}unreachable"),
+        }
+    }
+    let current = region::saved();
+    match region::fastest(&latencies) {
+        Some(fastest) if current.as_deref() == Some(fastest) => {
+            println!("{fastest} is the fastest and already in use");
+        }
+        Some(fastest) => println!("{fastest} is the fastest, switch with --region {fastest}"),
+        None => println!("No region answered"),
+    }
+}
+
+// The administrator's policy beats the command line too
+fn enforce_policy(cli: &mut Cli) {
+    for key in config::enforced() {
+        match key.as_str() {
+            "data_dir" => cli.data_dir = None,
+            "shared_install" => cli.shared_install = None,
+            "region" => cli.region = None,
+            "allow_metered" => cli.allow_metered = false,
+            "kiosk" => cli.kiosk = false,
+            "update_provider" => cli.update_provider = None,
+            "update_url" => cli.update_url = None,
+            "manual_launch" => cli.manual_launch = false,
+            "detach" => cli.detach = false,
+            "launch_countdown" => cli.launch_countdown = None,
+            "keep_versions" => cli.keep_versions = None,
+            "check_jitter" => cli.check_jitter = None,
+            "gauge" => cli.gauge = None,
+            _ => {}
+        }
+    }
 }
 
-fn input_handling(tx: mpsc::Sender<Event>) {
-    let tick_rate = Duration::from_millis(200);
+/// Holding a button this long does something else than tapping it
+const LONG_PRESS: Duration = Duration::from_millis(600);
+
+/// With nothing happening for this long, ticks and controller polling slow down
+const IDLE_AFTER: Duration = Duration::from_secs(5);
+/// How many times longer the intervals get while idle
+const IDLE_SLOWDOWN: u32 = 5;
+
+/// Interval of a periodic task, longer while the launcher is idle so it
+/// doesn't keep laptop CPUs awake
+fn paced(interval: Duration, tx: &bus::Sender) -> Duration {
+    if tx.idle_for() >= IDLE_AFTER {
+        interval * IDLE_SLOWDOWN
+    } else {
+        interval
+    }
+}
+
+/// Accessibility settings of controller and keyboard input
+struct InputTiming {
+    /// The same input repeated sooner than this is ignored
+    debounce: Duration,
+    /// Controller buttons have to be held this long, keyboards don't report it
+    min_hold: Duration,
+}
+
+fn input_handling(tx: bus::Sender, base_tick_rate: Duration, inputs: &InputTiming) {
+    let mut debounce = Debounce::new(inputs.debounce);
     thread::spawn(move || {
         let mut last_tick = Instant::now();
         loop {
+            let tick_rate = paced(base_tick_rate, &tx);
             // poll for tick rate duration, if no events, sent tick event.
             let timeout = tick_rate.saturating_sub(last_tick.elapsed());
             if let Ok(poll_ready) = terminal_event::poll(timeout) {
@@ -195,13 +624,19 @@ fn input_handling(tx: mpsc::Sender<Event>) {
                     match terminal_event::read() {
                         Ok(event) => {
                             let send_result = match event {
-                                CrosstermEvent::Key(key) => tx.send(Event::Input(key)),
-                                CrosstermEvent::Resize(_, _) => tx.send(Event::Resize),
+                                CrosstermEvent::Key(key) => {
+                                    if debounce.accept(key.code, Instant::now()) {
+                                        tx.send(InputEvent::Key(key))
+                                    } else {
+                                        Ok(())
+                                    }
+                                }
+                                CrosstermEvent::Resize(_, _) => tx.send(InputEvent::Resize),
                                 CrosstermEvent::FocusGained => {
-                                    tx.send(Event::TerminalFocusChanged(true))
+                                    tx.send(InputEvent::TerminalFocusChanged(true))
                                 }
                                 CrosstermEvent::FocusLost => {
-                                    tx.send(Event::TerminalFocusChanged(false))
+                                    tx.send(InputEvent::TerminalFocusChanged(false))
                                 }
                                 _ => Ok(()),
                             };
@@ -223,7 +658,7 @@ fn input_handling(tx: mpsc::Sender<Event>) {
             }
 
             if last_tick.elapsed() >= tick_rate {
-                if tx.send(Event::Tick).is_err() {
+                if tx.send(InputEvent::Tick).is_err() {
                     eprintln!("Tick event receiver disconnected, shutting down input thread");
                     return;
                 }
@@ -233,7 +668,36 @@ fn input_handling(tx: mpsc::Sender<Event>) {
     });
 }
 
-fn controller_input_handling(tx: mpsc::Sender<Event>) {
+// Returns false once the receiver is gone
+fn send_button(tx: &bus::Sender, debounce: &mut Debounce<Button>, button: Button) -> bool {
+    if !debounce.accept(button, Instant::now()) {
+        return true;
+    }
+    if tx.send(InputEvent::ControllerInput(button)).is_err() {
+        eprintln!("Controller event receiver disconnected, shutting down controller thread");
+        return false;
+    }
+    true
+}
+
+fn send_axis(
+    tx: &bus::Sender,
+    debounce: &mut Debounce<(Axis, bool)>,
+    axis: Axis,
+    value: f32,
+) -> bool {
+    !debounce.accept((axis, value > 0.0), Instant::now())
+        || tx
+            .send(InputEvent::ControllerAxisMoved(axis, value))
+            .is_ok()
+}
+
+fn controller_input_handling(tx: bus::Sender, poll_interval: Duration, inputs: &InputTiming) {
+    let mut debounce = Debounce::new(inputs.debounce);
+    let mut stick_debounce = Debounce::new(inputs.debounce);
+    let mut hold = MinHold::new(inputs.min_hold);
+    // A tap on Select filters the log, holding it opens the versions menu
+    let mut select = LongPress::new(Button::Select, inputs.min_hold, LONG_PRESS);
     thread::spawn(move || {
         let mut gilrs = match Gilrs::new() {
             Ok(gilrs) => gilrs,
@@ -257,14 +721,27 @@ fn controller_input_handling(tx: mpsc::Sender<Event>) {
             // Process controller events
             while let Some(gilrs_event) = gilrs.next_event() {
                 match gilrs_event.event {
-                    EventType::ButtonPressed(button, _) => {
-                        if tx.send(Event::ControllerInput(button)).is_err() {
-                            eprintln!(
-                                "Controller event receiver disconnected, shutting down controller thread"
-                            );
+                    EventType::ButtonPressed(button, _)
+                        if select.pressed(button, Instant::now()) => {}
+                    // Select and Start together export the logs
+                    EventType::ButtonPressed(Button::Start, _) if select.chord() => {
+                        let Ok(()) = tx.send(InputEvent::ControllerChord(Button::Start)) else {
                             return;
-                        }
+                        };
+                    }
+                    EventType::ButtonReleased(button, _)
+                        if let Some(tap) = select.released(button, Instant::now())
+                            && !send_button(&tx, &mut debounce, tap) =>
+                    {
+                        return;
+                    }
+                    EventType::ButtonPressed(button, _)
+                        if hold.pressed(button, Instant::now())
+                            && !send_button(&tx, &mut debounce, button) =>
+                    {
+                        return;
                     }
+                    EventType::ButtonReleased(button, _) => hold.released(button),
                     EventType::AxisChanged(axis, value, _) => {
                         match axis {
                             Axis::LeftStickX => {
@@ -272,13 +749,13 @@ fn controller_input_handling(tx: mpsc::Sender<Event>) {
                                 if value > HIGH_THRESHOLD && !right_triggered {
                                     // Right movement crossing high threshold
                                     right_triggered = true;
-                                    if tx.send(Event::ControllerAxisMoved(axis, value)).is_err() {
+                                    if !send_axis(&tx, &mut stick_debounce, axis, value) {
                                         return;
                                     }
                                 } else if value < -HIGH_THRESHOLD && !left_triggered {
                                     // Left movement crossing high threshold
                                     left_triggered = true;
-                                    if tx.send(Event::ControllerAxisMoved(axis, value)).is_err() {
+                                    if !send_axis(&tx, &mut stick_debounce, axis, value) {
                                         return;
                                     }
                                 } else if value.abs() < LOW_THRESHOLD {
@@ -292,13 +769,13 @@ fn controller_input_handling(tx: mpsc::Sender<Event>) {
                                 if value > HIGH_THRESHOLD && !down_triggered {
                                     // Down movement crossing high threshold
                                     down_triggered = true;
-                                    if tx.send(Event::ControllerAxisMoved(axis, value)).is_err() {
+                                    if !send_axis(&tx, &mut stick_debounce, axis, value) {
                                         return;
                                     }
                                 } else if value < -HIGH_THRESHOLD && !up_triggered {
                                     // Up movement crossing high threshold
                                     up_triggered = true;
-                                    if tx.send(Event::ControllerAxisMoved(axis, value)).is_err() {
+                                    if !send_axis(&tx, &mut stick_debounce, axis, value) {
                                         return;
                                     }
                                 } else if value.abs() < LOW_THRESHOLD {
@@ -314,8 +791,19 @@ fn controller_input_handling(tx: mpsc::Sender<Event>) {
                 }
             }
 
+            for button in hold.long_enough(Instant::now()) {
+                if !send_button(&tx, &mut debounce, button) {
+                    return;
+                }
+            }
+            if let Some(button) = select.held(Instant::now())
+                && tx.send(InputEvent::ControllerHold(button)).is_err()
+            {
+                return;
+            }
+
             // Sleep to prevent high CPU usage
-            thread::sleep(Duration::from_millis(10));
+            thread::sleep(paced(poll_interval, &tx));
         }
     });
 }
@@ -0,0 +1,63 @@
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ALLOW_METERED: AtomicBool = AtomicBool::new(false);
+
+/// Download on metered connections without asking
+pub fn allow_metered(enabled: bool) {
+    ALLOW_METERED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_allowed() -> bool {
+    ALLOW_METERED.load(Ordering::Relaxed)
+}
+
+pub fn downloads_need_confirmation() -> bool {
+    !is_allowed() && is_metered() == Some(true)
+}
+
+/// Whether NetworkManager considers the active connection metered (a phone
+/// hotspot, or one marked so by the user). `None` without NetworkManager
+pub fn is_metered() -> Option<bool> {
+    // busctl ships with systemd, it saves pulling in a D-Bus library
+    let output = Command::new("busctl")
+        .args([
+            "--system",
+            "get-property",
+            "org.freedesktop.NetworkManager",
+            "/org/freedesktop/NetworkManager",
+            "org.freedesktop.NetworkManager",
+            "Metered",
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_metered(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse busctl's `u 4` output of an `NMMetered` value
+fn parse_metered(output: &str) -> Option<bool> {
+    match output.trim().strip_prefix("u ")?.parse::<u32>().ok()? {
+        // yes, guessed yes
+        1 | 3 => Some(true),
+        // no, guessed no
+        2 | 4 => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_metered_reads_guesses() {
+        assert_eq!(parse_metered("u 1\n"), Some(true));
+        assert_eq!(parse_metered("u 3\n"), Some(true));
+        assert_eq!(parse_metered("u 4\n"), Some(false));
+        assert_eq!(parse_metered("u 0\n"), None);
+        assert_eq!(parse_metered("s \"yes\""), None);
+    }
+}
@@ -0,0 +1,72 @@
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::Duration;
+use tracing::warn;
+
+/// A server that doesn't accept the connection by then is unreachable
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest wait for the answer and then for each read of the body, so a
+/// connection that hangs halfway fails instead of stalling the progress
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// Tries of a request that failed on the network
+const ATTEMPTS: u32 = 3;
+/// Wait before the first repeat, doubled for each one after
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+static CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// The client every request of the launcher goes through, sharing its
+/// connections and timeouts
+pub fn client() -> &'static Client {
+    CLIENT.get_or_init(|| {
+        Client::builder()
+            .user_agent(concat!("grav-launcher/", env!("CARGO_PKG_VERSION")))
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(READ_TIMEOUT)
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+/// Send the request made by `request`, repeating it with a growing delay
+/// while the server can't be reached or doesn't answer in time. The
+/// response status is left to the caller
+pub fn send(request: impl Fn(&Client) -> RequestBuilder) -> reqwest::Result<Response> {
+    let mut attempt = 1;
+    loop {
+        match request(client()).send() {
+            Err(e) if attempt < ATTEMPTS && is_network_failure(&e) => {
+                warn!("Request failed, attempt {attempt} of {ATTEMPTS}: {e}");
+                thread::sleep(retry_delay(attempt));
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// GET `url` through the shared client
+pub fn get(url: &str) -> reqwest::Result<Response> {
+    send(|client| client.get(url))
+}
+
+fn is_network_failure(e: &reqwest::Error) -> bool {
+    e.is_connect() || e.is_timeout()
+}
+
+fn retry_delay(attempt: u32) -> Duration {
+    FIRST_RETRY_DELAY * 2u32.pow(attempt - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay_doubles() {
+        assert_eq!(retry_delay(1), Duration::from_secs(1));
+        assert_eq!(retry_delay(2), Duration::from_secs(2));
+        assert_eq!(retry_delay(3), Duration::from_secs(4));
+    }
+}
@@ -0,0 +1,53 @@
+use chrono::Local;
+use color_eyre::Result;
+use eyre::WrapErr;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::dirs;
+
+/// Lines of game output saved by the player, e.g. repro snippets collected
+/// during a playtest. They're kept in `$XDG_DATA_HOME/GRAV/notes`
+fn notes_path(name: &str) -> Result<PathBuf> {
+    dirs::base()
+        .place_data_file(format!("notes/{name}"))
+        .wrap_err("Can't create notes path")
+}
+
+/// Save the lines to a new file named after the current time
+pub fn save_snippet(lines: &[String]) -> Result<PathBuf> {
+    save_timestamped("snippet", lines)
+}
+
+/// Save the game's last error lines after it exited with `status`
+pub fn save_crash_snippet(status: &str, lines: &[String]) -> Result<PathBuf> {
+    let mut snippet = vec![format!("Game exited {status}, last error lines:")];
+    snippet.extend_from_slice(lines);
+    save_timestamped("crash", &snippet)
+}
+
+fn save_timestamped(prefix: &str, lines: &[String]) -> Result<PathBuf> {
+    let path = notes_path(&format!(
+        "{prefix}-{}.txt",
+        Local::now().format("%Y%m%d-%H%M%S")
+    ))?;
+    fs::write(&path, lines.join("\n") + "\n")
+        .wrap_err_with(|| format!("Failed to write {path:?}"))?;
+    Ok(path)
+}
+
+/// Append the lines to the shared notes file under a timestamp header
+pub fn append_to_notes(lines: &[String]) -> Result<PathBuf> {
+    let path = notes_path("notes.txt")?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .wrap_err_with(|| format!("Failed to open {path:?}"))?;
+    writeln!(file, "--- {} ---", Local::now().format("%Y-%m-%d %H:%M:%S"))?;
+    for line in lines {
+        writeln!(file, "{line}")?;
+    }
+    Ok(path)
+}
@@ -0,0 +1,111 @@
+use color_eyre::Result;
+use eyre::{WrapErr, eyre};
+use std::env;
+use std::ffi::CStr;
+use std::fs;
+use std::os::unix::fs::{MetadataExt, lchown};
+use std::path::{Path, PathBuf};
+
+use crate::dirs;
+
+/// Launcher directories relative to a home directory, at their XDG defaults
+const GRAV_DIRS: [&str; 4] = [
+    ".local/share/GRAV",
+    ".local/state/GRAV",
+    ".cache/GRAV",
+    ".config/GRAV",
+];
+
+/// Running under sudo leaves root owned files in the XDG directories, which
+/// break updates once the launcher runs as the regular user again
+pub fn is_root() -> bool {
+    // SAFETY: geteuid has no preconditions and can't fail
+    unsafe { libc::geteuid() == 0 }
+}
+
+/// Files in the launcher directories the current user doesn't own
+pub fn foreign_files() -> usize {
+    let dirs = dirs::base();
+    // SAFETY: geteuid has no preconditions and can't fail
+    let uid = unsafe { libc::geteuid() };
+    let mut count = 0;
+    // Files of a shared install belong to whoever updated it last
+    let shared_cache = dirs.is_shared().then(|| dirs.cache_home());
+    for dir in [
+        dirs.data_home(),
+        dirs.state_home(),
+        dirs.cache_home(),
+        dirs.config_home(),
+    ] {
+        if Some(dir) == shared_cache {
+            continue;
+        }
+        walk(dir, &mut |_, metadata| {
+            if metadata.uid() != uid {
+                count += 1;
+            }
+        });
+    }
+    count
+}
+
+/// Give the launcher files of the user who ran `sudo` back to them.
+/// Returns how many files changed owner
+pub fn repair() -> Result<usize> {
+    if !is_root() {
+        return Err(eyre!(
+            "Only root can change file owners, run `sudo grav-launcher repair-ownership`"
+        ));
+    }
+    let id = |name| -> Result<u32> {
+        env::var(name)
+            .wrap_err_with(|| format!("{name} isn't set, run the repair through sudo"))?
+            .parse()
+            .wrap_err_with(|| format!("{name} isn't a number"))
+    };
+    let (uid, gid) = (id("SUDO_UID")?, id("SUDO_GID")?);
+    let home = home_of(uid)?;
+
+    let mut repaired = 0;
+    let mut error = None;
+    for dir in GRAV_DIRS {
+        walk(&home.join(dir), &mut |path, metadata| {
+            if metadata.uid() == uid && metadata.gid() == gid {
+                return;
+            }
+            match lchown(path, Some(uid), Some(gid)) {
+                Ok(()) => repaired += 1,
+                Err(e) => error = Some(eyre!("Failed to change the owner of {path:?}: {e}")),
+            }
+        });
+    }
+    error.map_or(Ok(repaired), Err)
+}
+
+// sudo usually resets HOME, the user's home comes from the password database
+fn home_of(uid: u32) -> Result<PathBuf> {
+    // SAFETY: the returned entry is copied out before any other passwd call
+    unsafe {
+        let entry = libc::getpwuid(uid);
+        if entry.is_null() {
+            return Err(eyre!("No user with uid {uid}"));
+        }
+        let home = CStr::from_ptr((*entry).pw_dir);
+        Ok(PathBuf::from(home.to_string_lossy().into_owned()))
+    }
+}
+
+/// Call `visit` for `path` and everything below it, symlinks aren't followed
+fn walk(path: &Path, visit: &mut impl FnMut(&Path, &fs::Metadata)) {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return;
+    };
+    visit(path, &metadata);
+    if metadata.is_dir()
+        && let Ok(entries) = fs::read_dir(path)
+    {
+        for entry in entries.flatten() {
+            walk(&entry.path(), visit);
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+
+// Process handling the launcher needs from the system beyond std
+
+/// Start `command` on its own, so it outlives the launcher and the terminal
+/// it runs in. A new session has no controlling terminal, so closing the
+/// terminal doesn't hang it up. Output written once the launcher is gone
+/// fails instead of killing it with SIGPIPE
+pub fn detach(command: &mut Command) {
+    // SAFETY: setsid and signal are async-signal-safe
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setsid() == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            libc::signal(libc::SIGPIPE, libc::SIG_IGN);
+            Ok(())
+        });
+    }
+}
+
+/// Start `command` so it survives the launcher closing its output pipes and
+/// its terminal later on. Outside the terminal's foreground process group it
+/// isn't hung up when the terminal closes
+pub fn releasable(command: &mut Command) {
+    command.process_group(0);
+    // SAFETY: signal is async-signal-safe
+    unsafe {
+        command.pre_exec(|| {
+            libc::signal(libc::SIGPIPE, libc::SIG_IGN);
+            Ok(())
+        });
+    }
+}
+
+/// Ask the process `pid` to quit, letting it save and clean up
+pub fn terminate(pid: u32) -> io::Result<()> {
+    let pid = libc::pid_t::try_from(pid).map_err(|_| io::ErrorKind::InvalidInput)?;
+    // SAFETY: kill only sends a signal
+    if unsafe { libc::kill(pid, libc::SIGTERM) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use color_eyre::Result;
+use eyre::eyre;
+use std::fs;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use tracing::info_span;
+
+use crate::bandwidth;
+use crate::bus;
+use crate::cache;
+use crate::downloads::{self, Job, JobKind};
+use crate::elf;
+use crate::event::DownloadEvent;
+use crate::file_mode;
+use crate::hash;
+use crate::metered;
+use crate::priority;
+use crate::profile;
+use crate::versions;
+
+/// Build servers of other channels, e.g. nightlies for a player on stable
+static CHANNELS: OnceLock<Vec<String>> = OnceLock::new();
+/// A restarted game doesn't start a second pass next to the running one
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Keep the latest builds of these channels in the download cache, so
+/// switching to them from the version list is instant
+pub fn set_channels(urls: Vec<String>) {
+    let _ = CHANNELS.set(urls);
+}
+
+/// Download the latest build of every channel that isn't cached yet, in the
+/// background at the lowest priority. Done while the game runs, so it never
+/// holds up a launch
+pub fn start(tx: &bus::Sender) {
+    let Some(channels) = CHANNELS.get().filter(|channels| !channels.is_empty()) else {
+        return;
+    };
+    // Nobody is there to ask, the player's own updates get asked about
+    if bandwidth::over_cap()
+        || metered::downloads_need_confirmation()
+        || RUNNING.swap(true, Ordering::Relaxed)
+    {
+        return;
+    }
+    let tx = tx.clone();
+    thread::spawn(move || {
+        priority::lower_current_thread();
+        prefetch_all(channels, &tx);
+        RUNNING.store(false, Ordering::Relaxed);
+    });
+}
+
+fn prefetch_all(channels: &[String], tx: &bus::Sender) {
+    for channel in channels {
+        let event = match prefetch(channel) {
+            Ok(Some(hash)) => DownloadEvent::Prefetched {
+                channel: channel.clone(),
+                hash,
+            },
+            Ok(None) => continue,
+            Err(e) => DownloadEvent::PrefetchFailed {
+                channel: channel.clone(),
+                error: format!("{e}"),
+            },
+        };
+        if tx.send(event).is_err() {
+            return;
+        }
+    }
+}
+
+/// Hash of the build put into the cache, `None` if it was there already
+fn prefetch(channel: &str) -> Result<Option<String>> {
+    let _span = info_span!("prefetch", channel).entered();
+    let url = format!("{channel}/{}", profile::current().artifact());
+    let hash = hash::get_remote_hash(&url)?;
+    if cache::lookup(&hash)?.is_some() {
+        return Ok(None);
+    }
+    let partial = cache::partial_path(&hash)?;
+    let job = Job {
+        kind: JobKind::Prefetch,
+        url,
+        destination: partial.clone(),
+        resume: true,
+    };
+    downloads::run(&job, |_| Ok(()))?;
+    let checked = elf::check_executable(&partial).and_then(|()| {
+        let actual = hash::compute_file_hash(&partial)?;
+        if actual == hash {
+            Ok(())
+        } else {
+            Err(eyre!("The download's hash is {actual} instead of {hash}"))
+        }
+    });
+    if let Err(e) = checked {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    file_mode::make_executable(&partial)?;
+    cache::store(&hash, &partial)?;
+    versions::record(&hash)?;
+    Ok(Some(hash))
+}
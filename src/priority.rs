@@ -0,0 +1,215 @@
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+
+/// Niceness of background threads, the lowest CPU priority
+const BACKGROUND_NICE: libc::c_int = 19;
+const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+const IOPRIO_CLASS_IDLE: libc::c_int = 3;
+const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+
+static BACKGROUND_PRIORITY: AtomicBool = AtomicBool::new(false);
+// 0 runs the game without its own scope
+static GAME_WEIGHT: AtomicU64 = AtomicU64::new(0);
+static GAME_CPUS: OnceLock<String> = OnceLock::new();
+static GAME_NICE: OnceLock<i32> = OnceLock::new();
+static GAME_SCOPE: AtomicBool = AtomicBool::new(false);
+static GAME_OOM_SCORE_ADJ: OnceLock<i32> = OnceLock::new();
+
+/// Run downloads and hashing that happen while the game is running with idle
+/// CPU and I/O priority, so they don't make it hitch on slow machines
+pub fn use_background_priority(enabled: bool) {
+    BACKGROUND_PRIORITY.store(enabled, Ordering::Relaxed);
+}
+
+/// Start the game in its own systemd scope with this CPU and I/O weight,
+/// 100 being the default of every other scope
+pub fn set_game_weight(weight: u64) {
+    GAME_WEIGHT.store(weight, Ordering::Relaxed);
+}
+
+/// Start the game in its own systemd scope even without a weight, so a
+/// memory leak gets the game killed and not the launcher with it
+pub fn use_game_scope(enabled: bool) {
+    GAME_SCOPE.store(enabled, Ordering::Relaxed);
+}
+
+/// Have the OOM killer pick the launcher less (negative) or more (positive)
+/// often. Going below 0 needs privileges
+pub fn set_launcher_oom_score_adj(adj: i32) -> io::Result<()> {
+    fs::write("/proc/self/oom_score_adj", adj.to_string())
+}
+
+/// Start the game with this OOM score adjustment, through `choom`. A high
+/// one makes a leaking game the first to go when memory runs out
+pub fn set_game_oom_score_adj(adj: i32) {
+    let _ = GAME_OOM_SCORE_ADJ.set(adj);
+}
+
+/// Lower the calling thread's priority if background priority is enabled.
+/// Linux can't raise it back without privileges, so this is only done on
+/// threads that won't start the game
+pub fn lower_current_thread() {
+    if !BACKGROUND_PRIORITY.load(Ordering::Relaxed) {
+        return;
+    }
+    // Best effort, the work still gets done at normal priority
+    // SAFETY: both calls only change the scheduling of the calling thread
+    unsafe {
+        let tid = libc::gettid();
+        libc::setpriority(libc::PRIO_PROCESS, tid as libc::id_t, BACKGROUND_NICE);
+        libc::syscall(
+            libc::SYS_ioprio_set,
+            IOPRIO_WHO_PROCESS,
+            tid,
+            IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+        );
+    }
+}
+
+/// Run `work` on a thread with background priority and wait for it
+pub fn in_background<T: Send>(work: impl FnOnce() -> T + Send) -> T {
+    thread::scope(|scope| {
+        scope
+            .spawn(|| {
+                lower_current_thread();
+                work()
+            })
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })
+}
+
+/// Keep the game on these CPUs, a `taskset --cpu-list` list like `0-7,16`
+pub fn set_game_cpus(cpus: String) {
+    let _ = GAME_CPUS.set(cpus);
+}
+
+/// Start the game with this niceness, below 0 needs privileges
+pub fn set_game_nice(nice: i32) {
+    let _ = GAME_NICE.set(nice);
+}
+
+/// Check a CPU list for `--game-cpus`
+pub fn parse_cpu_list(list: &str) -> Result<String, String> {
+    let valid = !list.is_empty()
+        && list.split(',').all(|range| {
+            let (first, last) = range.split_once('-').unwrap_or((range, range));
+            matches!(
+                (first.parse::<u32>(), last.parse::<u32>()),
+                (Ok(first), Ok(last)) if first <= last
+            )
+        });
+    if valid {
+        Ok(list.to_string())
+    } else {
+        Err(format!("{list:?} isn't a CPU list like 0-3,8"))
+    }
+}
+
+/// How the game is wrapped, see `game_command`
+#[derive(Debug, Default)]
+struct Wrappers<'a> {
+    scope: bool,
+    weight: u64,
+    cpus: Option<&'a str>,
+    nice: Option<i32>,
+    oom_score_adj: Option<i32>,
+}
+
+/// Program and arguments starting the game: wrapped in `systemd-run` when
+/// the game gets its own scope, in `taskset` and `nice` when it's pinned
+/// to CPUs or reniced, and in `choom` for its OOM score. Each of them execs
+/// the next, the game keeps the PID
+pub fn game_command(game_path: &Path, args: &[String]) -> (OsString, Vec<OsString>) {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    wrap_command(
+        game_path,
+        &args,
+        &Wrappers {
+            scope: GAME_SCOPE.load(Ordering::Relaxed),
+            weight: GAME_WEIGHT.load(Ordering::Relaxed),
+            cpus: GAME_CPUS.get().map(String::as_str),
+            nice: GAME_NICE.get().copied(),
+            oom_score_adj: GAME_OOM_SCORE_ADJ.get().copied(),
+        },
+    )
+}
+
+fn wrap_command(game_path: &Path, args: &[&str], wrappers: &Wrappers) -> (OsString, Vec<OsString>) {
+    let mut command: Vec<OsString> = Vec::new();
+    if wrappers.scope || wrappers.weight != 0 {
+        command.extend(
+            ["systemd-run", "--user", "--scope", "--quiet", "--collect"].map(OsString::from),
+        );
+        if wrappers.weight != 0 {
+            let weight = wrappers.weight;
+            command.push(format!("--property=CPUWeight={weight}").into());
+            command.push(format!("--property=IOWeight={weight}").into());
+        }
+        command.push("--".into());
+    }
+    if let Some(cpus) = wrappers.cpus {
+        command.extend(["taskset", "--cpu-list", cpus].map(OsString::from));
+    }
+    if let Some(nice) = wrappers.nice {
+        command.extend(["nice".into(), "-n".into(), nice.to_string().into()]);
+    }
+    if let Some(adj) = wrappers.oom_score_adj {
+        command.extend([
+            "choom".into(),
+            "-n".into(),
+            adj.to_string().into(),
+            "--".into(),
+        ]);
+    }
+    command.push(game_path.into());
+    command.extend(args.iter().map(OsString::from));
+    let program = command.remove(0);
+    (program, command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_game_command_wraps_scheduling_tools() {
+        let game = Path::new("/games/GRAV.x86_64");
+        assert_eq!(
+            wrap_command(game, &["--headless"], &Wrappers::default()),
+            (game.into(), vec!["--headless".into()])
+        );
+        let wrappers = Wrappers {
+            cpus: Some("0-3"),
+            nice: Some(-5),
+            oom_score_adj: Some(500),
+            ..Wrappers::default()
+        };
+        let (program, args) = wrap_command(game, &[], &wrappers);
+        assert_eq!(program, "taskset");
+        assert_eq!(
+            args,
+            [
+                "--cpu-list",
+                "0-3",
+                "nice",
+                "-n",
+                "-5",
+                "choom",
+                "-n",
+                "500",
+                "--",
+                "/games/GRAV.x86_64"
+            ]
+        );
+
+        assert!(parse_cpu_list("0-7,16").is_ok());
+        assert!(parse_cpu_list("7-0").is_err());
+        assert!(parse_cpu_list("a,b").is_err());
+    }
+}
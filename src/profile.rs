@@ -0,0 +1,139 @@
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::BUILDS_URL;
+
+/// What the launcher installs and runs: the game itself or the dedicated server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Profile {
+    #[default]
+    Game,
+    Server,
+}
+
+static PROFILE: OnceLock<Profile> = OnceLock::new();
+static BUILDS_URL_OVERRIDE: OnceLock<String> = OnceLock::new();
+static ARTIFACT_OVERRIDE: OnceLock<String> = OnceLock::new();
+static EXTRA_EXECUTABLES: OnceLock<Vec<String>> = OnceLock::new();
+static MIRRORS: OnceLock<Vec<String>> = OnceLock::new();
+static REGION: OnceLock<String> = OnceLock::new();
+/// Index into `build_servers` of the server builds come from now
+static ACTIVE_SERVER: AtomicUsize = AtomicUsize::new(0);
+
+/// Select the profile once at startup, before the launcher logic runs
+pub fn set(profile: Profile) {
+    let _ = PROFILE.set(profile);
+}
+
+pub fn current() -> Profile {
+    PROFILE.get().copied().unwrap_or_default()
+}
+
+/// Download builds from another server, e.g. a staging one
+pub fn set_builds_url(url: String) {
+    let _ = BUILDS_URL_OVERRIDE.set(url);
+}
+
+/// Download builds from this region's path of the build server
+pub fn set_region(region: String) {
+    let _ = REGION.set(region);
+}
+
+/// Build server of a release channel. `stable` is the default server, the
+/// others are next to it, e.g. `nightly`
+pub fn channel_url(channel: &str) -> String {
+    if channel == "stable" {
+        return BUILDS_URL.to_string();
+    }
+    let site = BUILDS_URL
+        .rsplit_once('/')
+        .map_or(BUILDS_URL, |(site, _)| site);
+    format!("{site}/{channel}")
+}
+
+/// The build server without a region
+pub fn base_url() -> &'static str {
+    BUILDS_URL_OVERRIDE.get().map_or(BUILDS_URL, String::as_str)
+}
+
+/// Servers publishing the same builds, tried in order when the build server
+/// fails
+pub fn set_mirrors(urls: Vec<String>) {
+    let _ = MIRRORS.set(urls);
+}
+
+/// The build server of the picked region followed by the mirrors
+pub fn build_servers() -> Vec<String> {
+    let mut servers = vec![match REGION.get() {
+        Some(region) => format!("{}/{region}", base_url()),
+        None => base_url().to_string(),
+    }];
+    if let Some(mirrors) = MIRRORS.get() {
+        servers.extend(mirrors.iter().cloned());
+    }
+    servers
+}
+
+pub fn active_server() -> usize {
+    ACTIVE_SERVER.load(Ordering::Relaxed)
+}
+
+/// Get builds from the server at `index` of `build_servers` from now on
+pub fn use_server(index: usize) {
+    ACTIVE_SERVER.store(index, Ordering::Relaxed);
+}
+
+/// Install the game under another file name, for builds published as e.g.
+/// `GRAV-playtest.x86_64`
+pub fn set_artifact(name: String) {
+    let _ = ARTIFACT_OVERRIDE.set(name);
+}
+
+/// Other programs shipped next to the game build, e.g. `GRAV-editor.x86_64`,
+/// that can be launched instead of it
+pub fn set_extra_executables(names: Vec<String>) {
+    let _ = EXTRA_EXECUTABLES.set(names);
+}
+
+impl Profile {
+    /// File name of the build, both on the build server and once installed
+    pub fn artifact(self) -> &'static str {
+        match self {
+            Self::Game => ARTIFACT_OVERRIDE
+                .get()
+                .map_or("GRAV.x86_64", String::as_str),
+            Self::Server => "GRAV-server.x86_64",
+        }
+    }
+
+    /// Programs in the install that can be launched, the build itself first
+    pub fn launch_targets(self) -> Vec<&'static str> {
+        let mut targets = vec![self.artifact()];
+        if self == Self::Game
+            && let Some(extra) = EXTRA_EXECUTABLES.get()
+        {
+            targets.extend(extra.iter().map(String::as_str));
+        }
+        targets
+    }
+
+    /// Where the build is downloaded from the active server
+    pub fn url(self) -> String {
+        let servers = build_servers();
+        let builds_url = servers.get(active_server()).unwrap_or(&servers[0]);
+        format!("{builds_url}/{}", self.artifact())
+    }
+
+    /// Arguments the build is started with, the server runs without a window
+    pub const fn args(self) -> &'static [&'static str] {
+        match self {
+            Self::Game => &[],
+            Self::Server => &["--headless"],
+        }
+    }
+
+    /// A crashed server is started again, a crashed game is left for the player
+    pub const fn restarts_on_crash(self) -> bool {
+        matches!(self, Self::Server)
+    }
+}
@@ -0,0 +1,136 @@
+use color_eyre::Result;
+use eyre::eyre;
+use serde::Serialize;
+use std::fs::File;
+use std::io::Write;
+use std::os::fd::{FromRawFd, RawFd};
+use std::sync::{Mutex, PoisonError};
+
+use crate::event::{DownloadEvent, Event, GameEvent};
+
+/// Where progress records go, with the size of the running download
+struct Output {
+    file: File,
+    total: Option<u64>,
+}
+
+static OUTPUT: Mutex<Option<Output>> = Mutex::new(None);
+
+/// One line of progress for wrapper scripts, e.g.
+/// `{"event":"download","downloaded":1048576,"total":4194304,"percent":25}`
+#[derive(Debug, PartialEq, Eq, Serialize)]
+struct Record<'a> {
+    event: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    downloaded: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    percent: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<&'a str>,
+}
+
+impl Record<'_> {
+    const fn new(event: &'static str) -> Self {
+        Self {
+            event,
+            downloaded: None,
+            total: None,
+            percent: None,
+            message: None,
+        }
+    }
+}
+
+/// Write progress records to the file descriptor `fd`, opened by whoever
+/// started the launcher, e.g. `grav-launcher --progress-fd 3 3>progress`
+pub fn open(fd: RawFd) -> Result<()> {
+    // SAFETY: fcntl only queries the descriptor, it fails if `fd` isn't open
+    if unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+        return Err(eyre!("File descriptor {fd} for --progress-fd isn't open"));
+    }
+    // SAFETY: the descriptor is open and nothing else in the launcher uses it
+    let file = unsafe { File::from_raw_fd(fd) };
+    *OUTPUT.lock().unwrap_or_else(PoisonError::into_inner) = Some(Output { file, total: None });
+    Ok(())
+}
+
+/// Write the record of a launcher event, if progress records are on
+pub fn on_event(event: &Event) {
+    let mut output = OUTPUT.lock().unwrap_or_else(PoisonError::into_inner);
+    let Some(Output { file, total }) = output.as_mut() else {
+        return;
+    };
+    let Some(record) = record(event, total) else {
+        return;
+    };
+    let Ok(line) = serde_json::to_string(&record) else {
+        return;
+    };
+    // The reader went away, nobody is left to tell
+    if writeln!(file, "{line}").is_err() {
+        *output = None;
+    }
+}
+
+fn record<'a>(event: &'a Event, total: &mut Option<u64>) -> Option<Record<'a>> {
+    let progress = |downloaded: u64, total: Option<u64>| Record {
+        downloaded: Some(downloaded),
+        total,
+        percent: total
+            .filter(|total| *total > 0)
+            .map(|total| downloaded.min(total) * 100 / total),
+        ..Record::new("download")
+    };
+    let failure = |message| Record {
+        message: Some(message),
+        ..Record::new("error")
+    };
+    Some(match event {
+        Event::Download(event) => match event {
+            DownloadEvent::AccessingOnlineHash => Record::new("checking"),
+            DownloadEvent::HashAreEqual => Record::new("up_to_date"),
+            DownloadEvent::StartDownloadingBinary(size) => {
+                *total = *size;
+                progress(0, *size)
+            }
+            DownloadEvent::DownloadResumed(downloaded)
+            | DownloadEvent::DownloadProgress(downloaded) => progress(*downloaded, *total),
+            DownloadEvent::RemoteBinaryDownloaded => Record::new("downloaded"),
+            DownloadEvent::GameBinaryUpdated => Record::new("installed"),
+            DownloadEvent::BinaryDownloadError(message) => failure(message),
+            _ => return None,
+        },
+        Event::Game(GameEvent::Launching) => Record::new("launching"),
+        Event::Game(GameEvent::ExecutionError(message)) | Event::LauncherError(message) => {
+            failure(message)
+        }
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_download_records_carry_percent() {
+        let mut total = None;
+        let started = Event::Download(DownloadEvent::StartDownloadingBinary(Some(400)));
+        record(&started, &mut total);
+        let event = Event::Download(DownloadEvent::DownloadProgress(100));
+        let line = serde_json::to_string(&record(&event, &mut total).unwrap()).unwrap();
+        assert_eq!(
+            line,
+            r#"{"event":"download","downloaded":100,"total":400,"percent":25}"#
+        );
+        assert!(
+            record(
+                &Event::Download(DownloadEvent::ComputingLocalHash),
+                &mut total
+            )
+            .is_none()
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use color_eyre::Result;
+use eyre::eyre;
+use portable_pty::{CommandBuilder, MasterPty, PtySize, native_pty_system};
+use std::ffi::{OsStr, OsString};
+use std::io::{Read, Write};
+use std::os::unix::process::ExitStatusExt;
+use std::path::Path;
+use std::process::ExitStatus;
+
+use crate::game_env::Override;
+
+/// Size reported to the game, wide enough that its own line wrapping rarely kicks in
+const PTY_SIZE: PtySize = PtySize {
+    rows: 50,
+    cols: 200,
+    pixel_width: 0,
+    pixel_height: 0,
+};
+
+/// The game running with a pseudo-terminal as its stdin, stdout and stderr,
+/// for engines that only print progress bars and colors to a TTY
+pub struct PtyGame {
+    child: Box<dyn portable_pty::Child + Send + Sync>,
+    // Closing the master would hang up the game's terminal
+    _master: Box<dyn MasterPty + Send>,
+}
+
+/// Both ends of the game's terminal as seen from the launcher
+pub struct PtyStreams {
+    /// Everything the game prints
+    pub output: Box<dyn Read + Send>,
+    pub input: Box<dyn Write + Send>,
+}
+
+/// Start the game under a new pseudo-terminal
+pub fn spawn(
+    program: &OsStr,
+    args: &[OsString],
+    cwd: &Path,
+    overrides: &[Override],
+) -> Result<(PtyGame, PtyStreams)> {
+    let pair = native_pty_system()
+        .openpty(PTY_SIZE)
+        .map_err(|e| eyre!("Failed to open a pseudo-terminal: {e}"))?;
+    let mut command = CommandBuilder::new(program);
+    command.args(args);
+    // Without it the game would start in the home directory, unlike with pipes
+    command.cwd(cwd);
+    for o in overrides {
+        command.env(&o.name, &o.value);
+    }
+    let child = pair
+        .slave
+        .spawn_command(command)
+        .map_err(|e| eyre!("Failed to launch game binary: {e}"))?;
+    let output = pair
+        .master
+        .try_clone_reader()
+        .map_err(|e| eyre!("Failed to capture terminal output: {e}"))?;
+    let input = pair
+        .master
+        .take_writer()
+        .map_err(|e| eyre!("Failed to capture terminal input: {e}"))?;
+    Ok((
+        PtyGame {
+            child,
+            _master: pair.master,
+        },
+        PtyStreams { output, input },
+    ))
+}
+
+impl PtyGame {
+    pub fn try_wait(&mut self) -> std::io::Result<Option<ExitStatus>> {
+        Ok(self
+            .child
+            .try_wait()?
+            .map(|status| ExitStatus::from_raw((status.exit_code() as i32) << 8)))
+    }
+
+    pub fn kill(&mut self) -> std::io::Result<()> {
+        self.child.kill()
+    }
+
+    pub fn id(&self) -> Option<u32> {
+        self.child.process_id()
+    }
+}
+
+/// Plain text of a line as a terminal would show it: escape sequences are
+/// removed and a carriage return starts the line over, as progress bars do
+pub fn clean_line(raw: &[u8]) -> String {
+    let raw = raw.strip_suffix(b"\r").unwrap_or(raw);
+    let visible = raw
+        .iter()
+        .rposition(|&byte| byte == b'\r')
+        .map_or(raw, |start| &raw[start + 1..]);
+    strip_escapes(&String::from_utf8_lossy(visible))
+}
+
+fn strip_escapes(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            plain.push(c);
+            continue;
+        }
+        match chars.next() {
+            // CSI: parameters up to a final byte, e.g. colors "\x1b[1;31m"
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: up to BEL or ST, e.g. window titles
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    plain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_line_keeps_visible_text() {
+        assert_eq!(clean_line(b"\x1b[1;32mready\x1b[0m\r"), "ready");
+        assert_eq!(clean_line(b"loading 10%\rloading 90%"), "loading 90%");
+        assert_eq!(clean_line(b"\x1b]0;GRAV\x07title"), "title");
+    }
+}
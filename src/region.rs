@@ -0,0 +1,107 @@
+use color_eyre::Result;
+use eyre::WrapErr;
+use std::fs;
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::dirs;
+use crate::net;
+use crate::profile;
+
+/// Regions the builds are published for, each under its own path of the
+/// build server
+pub const REGIONS: &[&str] = &["eu", "us", "asia"];
+/// Requests timed per region, the fastest one counts
+const PROBES: usize = 3;
+
+static TRIED: OnceLock<String> = OnceLock::new();
+
+// Kept per profile, the server may be hosted elsewhere than the players
+fn file_name() -> String {
+    format!("{}.region", profile::current().artifact())
+}
+
+/// The region picked for the current profile
+pub fn saved() -> Option<String> {
+    let path = dirs::base().find_state_file(file_name())?;
+    let region = fs::read_to_string(path).ok()?.trim().to_string();
+    REGIONS.contains(&region.as_str()).then_some(region)
+}
+
+/// Download the builds of the current profile from `region` from now on
+pub fn save(region: &str) -> Result<()> {
+    let path = dirs::base()
+        .place_state_file(file_name())
+        .wrap_err("Can't create region path")?;
+    fs::write(&path, region).wrap_err_with(|| format!("Failed to write {path:?}"))
+}
+
+/// Download from `region` this time, it's saved once its build server answered
+pub fn try_out(region: String) {
+    let _ = TRIED.set(region);
+}
+
+/// Save the region tried out, now that the hash came from its build server
+/// and not from a mirror
+pub fn confirm() -> Result<()> {
+    match TRIED.get() {
+        Some(region) if profile::active_server() == 0 => save(region),
+        _ => Ok(()),
+    }
+}
+
+/// Time the build server of every region, `None` where it didn't answer.
+/// The regions are measured at the same time
+pub fn measure() -> Vec<(&'static str, Option<Duration>)> {
+    let probes: Vec<_> = REGIONS
+        .iter()
+        .map(|region| {
+            let url = format!(
+                "{}/{region}/{}",
+                profile::base_url(),
+                profile::current().artifact()
+            );
+            (*region, thread::spawn(move || probe(&url)))
+        })
+        .collect();
+    probes
+        .into_iter()
+        .map(|(region, probe)| (region, probe.join().ok().flatten()))
+        .collect()
+}
+
+// Asking for the build's headers times the way to the server, not the download
+fn probe(url: &str) -> Option<Duration> {
+    (0..PROBES)
+        .filter_map(|_| {
+            let started = Instant::now();
+            let response = net::client().head(url).send().ok()?;
+            response.status().is_success().then(|| started.elapsed())
+        })
+        .min()
+}
+
+/// The region that answered fastest
+pub fn fastest(latencies: &[(&'static str, Option<Duration>)]) -> Option<&'static str> {
+    latencies
+        .iter()
+        .filter_map(|(region, latency)| Some((*region, (*latency)?)))
+        .min_by_key(|(_, latency)| *latency)
+        .map(|(region, _)| region)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fastest_region_skips_unreachable_ones() {
+        let ms = |ms| Some(Duration::from_millis(ms));
+        assert_eq!(
+            fastest(&[("eu", ms(80)), ("us", None), ("asia", ms(40))]),
+            Some("asia")
+        );
+        assert_eq!(fastest(&[("eu", None)]), None);
+    }
+}
@@ -0,0 +1,178 @@
+use chrono::Local;
+use color_eyre::Result;
+use eyre::{WrapErr, eyre};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::dirs;
+use crate::event::{Event, GameEvent};
+use crate::log_stream::LogStream;
+
+const SESSION_LOG: &str = "session.log";
+const PREVIOUS_SESSION_LOG: &str = "previous-session.log";
+/// Plain game output of the current run, for `tail -f` and other tools
+const GAME_OUTPUT: &str = "game-output.log";
+
+/// The launcher log and game output mirrored to `$XDG_STATE_HOME/GRAV` as
+/// they happen, so a failed session can be looked into after a restart.
+/// Logging is best effort, the launcher works without it
+pub struct SessionLog {
+    file: Option<File>,
+    stream: Option<LogStream>,
+    // Game output only, without timestamps, started over on every launch
+    mirror: Option<File>,
+    // How many times each launcher log line was written, entries are
+    // snapshotted on every change and only new lines are appended
+    written: HashMap<String, usize>,
+}
+
+impl SessionLog {
+    /// Keep the last session's log as the previous one and start a new log.
+    /// Lines are also sent to the stream when there is one
+    pub fn start(stream: Option<LogStream>) -> Self {
+        let mut session_log = Self {
+            file: open_session_log().ok(),
+            stream,
+            mirror: open_mirror().ok(),
+            written: HashMap::new(),
+        };
+        session_log.write_line(
+            "launcher",
+            &format!("Session started, version {}", env!("CARGO_PKG_VERSION")),
+        );
+        session_log
+    }
+
+    pub fn write_line(&mut self, source: &str, line: &str) {
+        if self.file.is_none() && self.stream.is_none() {
+            return;
+        }
+        let time = Local::now().format("%Y-%m-%d %H:%M:%S");
+        let line = format!("{time} [{source}] {line}");
+        if let Some(file) = &mut self.file {
+            // A failed write shouldn't interrupt the session
+            if writeln!(file, "{line}").is_err() {
+                self.file = None;
+            }
+        }
+        if let Some(stream) = &self.stream {
+            stream.send(line);
+        }
+    }
+
+    /// Append the launcher log lines that weren't written yet
+    pub fn record_launcher_log(&mut self, lines: Vec<String>) {
+        let mut seen: HashMap<&str, usize> = HashMap::new();
+        let mut new_lines = Vec::new();
+        for line in &lines {
+            let count = seen.entry(line).or_default();
+            *count += 1;
+            if *count > self.written.get(line).copied().unwrap_or_default() {
+                new_lines.push(line.clone());
+            }
+        }
+        for line in new_lines {
+            self.write_line("launcher", &line);
+            *self.written.entry(line).or_default() += 1;
+        }
+    }
+
+    /// Write game output, including lines later cleared from the panes
+    pub fn record_game_output(&mut self, event: &Event) {
+        match event {
+            Event::Game(GameEvent::Launching) => {
+                self.mirror = open_mirror().ok();
+            }
+            Event::Game(GameEvent::Output(lines)) => {
+                for line in lines {
+                    self.write_line("stdout", line);
+                }
+                self.mirror_lines(lines);
+            }
+            Event::Game(GameEvent::ErrorOutput(lines)) => {
+                for line in lines {
+                    self.write_line("stderr", line);
+                }
+                self.mirror_lines(lines);
+            }
+            Event::Game(GameEvent::Environment(report)) => {
+                for line in &report.lines() {
+                    self.write_line("environment", line);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn mirror_lines(&mut self, lines: &[String]) {
+        if let Some(mirror) = &mut self.mirror
+            && lines
+                .iter()
+                .try_for_each(|line| writeln!(mirror, "{line}"))
+                .is_err()
+        {
+            self.mirror = None;
+        }
+    }
+}
+
+/// Where the game output of the current run is mirrored
+pub fn mirror_path() -> Option<PathBuf> {
+    dirs::base().place_state_file(GAME_OUTPUT).ok()
+}
+
+// Truncated rather than replaced, so a running `tail -f` keeps following it
+fn open_mirror() -> Result<File> {
+    let path = mirror_path().ok_or_else(|| eyre!("Can't create game output path"))?;
+    File::create(&path).wrap_err_with(|| format!("Failed to create {path:?}"))
+}
+
+fn open_session_log() -> Result<File> {
+    let path = dirs::base()
+        .place_state_file(SESSION_LOG)
+        .wrap_err("Can't create session log path")?;
+    if path.exists() {
+        fs::rename(&path, path.with_file_name(PREVIOUS_SESSION_LOG))
+            .wrap_err("Failed to keep the previous session log")?;
+    }
+    File::create(&path).wrap_err_with(|| format!("Failed to create {path:?}"))
+}
+
+/// Write the launcher log and the game's stdout and stderr to a new file in
+/// `$XDG_STATE_HOME/GRAV/exports`, e.g. to attach to a bug report
+pub fn export(launcher: &[String], stdout: &[String], stderr: &[String]) -> Result<PathBuf> {
+    let path = dirs::base()
+        .place_state_file(format!(
+            "exports/session-{}.log",
+            Local::now().format("%Y%m%d-%H%M%S")
+        ))
+        .wrap_err("Can't create export path")?;
+    let mut text = String::new();
+    for (section, lines) in [
+        ("Launcher log", launcher),
+        ("Game stdout", stdout),
+        ("Game stderr", stderr),
+    ] {
+        text.push_str(&format!("---- {section} ----\n"));
+        for line in lines {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+    fs::write(&path, text).wrap_err_with(|| format!("Failed to write {path:?}"))?;
+    Ok(path)
+}
+
+fn previous_session_path() -> Option<PathBuf> {
+    dirs::base().find_state_file(PREVIOUS_SESSION_LOG)
+}
+
+/// Lines of the previous session's log, empty if there was none
+pub fn previous_session() -> Vec<String> {
+    previous_session_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|log| log.lines().map(str::to_owned).collect())
+        .unwrap_or_default()
+}
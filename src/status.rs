@@ -0,0 +1,184 @@
+use color_eyre::Result;
+use eyre::WrapErr;
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::event::{DownloadEvent, Event, GameEvent};
+use crate::profile;
+
+/// Launcher state served over HTTP for monitoring, mostly for dedicated
+/// server hosts: `/status` as JSON and `/metrics` in the Prometheus format
+#[derive(Serialize)]
+struct Status {
+    profile: &'static str,
+    phase: &'static str,
+    build: Option<String>,
+    launches: u64,
+    restarts: u64,
+    output_lines: u64,
+    error_lines: u64,
+    dropped_lines: u64,
+    downloaded_this_month: u64,
+    #[serde(skip)]
+    running_since: Option<Instant>,
+}
+
+static STATUS: Mutex<Status> = Mutex::new(Status {
+    profile: "game",
+    phase: "starting",
+    build: None,
+    launches: 0,
+    restarts: 0,
+    output_lines: 0,
+    error_lines: 0,
+    dropped_lines: 0,
+    downloaded_this_month: 0,
+    running_since: None,
+});
+
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Update the status from an event on its way to the UI
+pub fn record(event: &Event) {
+    let Ok(mut status) = STATUS.lock() else {
+        return;
+    };
+    match event {
+        Event::Download(event) => match event {
+            DownloadEvent::AccessingOnlineHash => status.phase = "checking",
+            DownloadEvent::RemoteHash(hash) | DownloadEvent::LocalHash(hash)
+                if status.build.is_none() =>
+            {
+                status.build = Some(hash.clone());
+            }
+            DownloadEvent::StartDownloadingBinary(_) => status.phase = "downloading",
+            DownloadEvent::GameUpdateInstalled(hash) => status.build = Some(hash.clone()),
+            DownloadEvent::BinaryDownloadError(_) => status.phase = "error",
+            DownloadEvent::MonthlyUsage(bytes) => status.downloaded_this_month = *bytes,
+            _ => {}
+        },
+        Event::Game(event) => match event {
+            GameEvent::Launching => {
+                status.phase = "running";
+                status.launches += 1;
+                status.running_since = Some(Instant::now());
+            }
+            GameEvent::ServerRestarting(_) => {
+                status.phase = "restarting";
+                status.restarts += 1;
+                status.running_since = None;
+            }
+            GameEvent::Output(lines) | GameEvent::ErrorOutput(lines) => {
+                if matches!(event, GameEvent::Output(_)) {
+                    status.output_lines += lines.len() as u64;
+                } else {
+                    status.error_lines += lines.len() as u64;
+                }
+                // A game reported as stuck on start printed something after all
+                if status.phase == "stalled" {
+                    status.phase = "running";
+                }
+            }
+            GameEvent::OutputDropped(count) => status.dropped_lines += *count as u64,
+            GameEvent::ExecutionError(_) => status.phase = "error",
+            GameEvent::Exited(_) => {
+                status.phase = "exited";
+                status.running_since = None;
+            }
+            GameEvent::FallingBack { good, .. } => {
+                status.build = Some(good.clone());
+                status.running_since = None;
+            }
+            GameEvent::StartupStalled(_) => status.phase = "stalled",
+            GameEvent::Countdown { .. }
+            | GameEvent::ReadyToPlay(_)
+            | GameEvent::StdinReady(_)
+            | GameEvent::Environment(_)
+            | GameEvent::ServerLatency(_)
+            | GameEvent::Usage(_) => {}
+        },
+        _ => {}
+    }
+}
+
+/// Listen on `address` (host:port) and answer status requests from a background thread
+pub fn serve(address: &str) -> Result<()> {
+    let listener = TcpListener::bind(address)
+        .wrap_err_with(|| format!("Failed to listen for status requests on {address}"))?;
+    if let Ok(mut status) = STATUS.lock() {
+        status.profile = match profile::current() {
+            profile::Profile::Game => "game",
+            profile::Profile::Server => "server",
+        };
+    }
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // A broken client only affects its own request
+            let _ = respond(stream);
+        }
+    });
+    Ok(())
+}
+
+fn respond(mut stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+
+    let (code, content_type, body) = match path {
+        "/status" => ("200 OK", "application/json", status_json()),
+        "/metrics" => ("200 OK", "text/plain; version=0.0.4", metrics()),
+        _ => ("404 Not Found", "text/plain", "not found\n".to_string()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {code}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+fn status_json() -> String {
+    STATUS
+        .lock()
+        .ok()
+        .and_then(|status| serde_json::to_string(&*status).ok())
+        .unwrap_or_default()
+}
+
+fn metrics() -> String {
+    let Ok(status) = STATUS.lock() else {
+        return String::new();
+    };
+    let uptime = status
+        .running_since
+        .map_or(0, |since| since.elapsed().as_secs());
+    format!(
+        "# TYPE grav_launches_total counter\n\
+         grav_launches_total {}\n\
+         # TYPE grav_restarts_total counter\n\
+         grav_restarts_total {}\n\
+         # TYPE grav_output_lines_total counter\n\
+         grav_output_lines_total{{stream=\"stdout\"}} {}\n\
+         grav_output_lines_total{{stream=\"stderr\"}} {}\n\
+         # TYPE grav_dropped_output_lines_total counter\n\
+         grav_dropped_output_lines_total {}\n\
+         # TYPE grav_downloaded_this_month_bytes gauge\n\
+         grav_downloaded_this_month_bytes {}\n\
+         # TYPE grav_uptime_seconds gauge\n\
+         grav_uptime_seconds {uptime}\n\
+         # TYPE grav_running gauge\n\
+         grav_running {}\n",
+        status.launches,
+        status.restarts,
+        status.output_lines,
+        status.error_lines,
+        status.dropped_lines,
+        status.downloaded_this_month,
+        u8::from(status.phase == "running"),
+    )
+}
@@ -0,0 +1,78 @@
+use ksni::blocking::{Handle, TrayMethods};
+use ksni::menu::StandardItem;
+use ksni::{MenuItem, ToolTip};
+
+use crate::bus;
+use crate::event::InputEvent;
+
+/// Minimal system tray companion mirroring the launcher phase
+pub struct GravTray {
+    tx: bus::Sender,
+    status: String,
+    game_launched: bool,
+}
+
+impl ksni::Tray for GravTray {
+    fn id(&self) -> String {
+        "grav-launcher".into()
+    }
+
+    fn title(&self) -> String {
+        "GRAV launcher".into()
+    }
+
+    fn icon_name(&self) -> String {
+        "applications-games".into()
+    }
+
+    fn tool_tip(&self) -> ToolTip {
+        ToolTip {
+            title: "GRAV launcher".into(),
+            description: self.status.clone(),
+            ..Default::default()
+        }
+    }
+
+    fn menu(&self) -> Vec<MenuItem<Self>> {
+        vec![
+            StandardItem {
+                label: "Launch".into(),
+                enabled: !self.game_launched,
+                activate: Box::new(|tray: &mut Self| {
+                    let _ = tray.tx.send(InputEvent::TrayLaunchRequested);
+                }),
+                ..Default::default()
+            }
+            .into(),
+            MenuItem::Separator,
+            StandardItem {
+                label: "Quit".into(),
+                icon_name: "application-exit".into(),
+                activate: Box::new(|tray: &mut Self| {
+                    let _ = tray.tx.send(InputEvent::TrayQuitRequested);
+                }),
+                ..Default::default()
+            }
+            .into(),
+        ]
+    }
+}
+
+/// Publish the tray icon. Returns None when no StatusNotifier host is available
+pub fn spawn(tx: bus::Sender) -> Option<Handle<GravTray>> {
+    GravTray {
+        tx,
+        status: String::new(),
+        game_launched: false,
+    }
+    .spawn()
+    .ok()
+}
+
+/// Refresh the tooltip and menu state
+pub fn set_status(handle: &Handle<GravTray>, status: &str, game_launched: bool) {
+    handle.update(|tray| {
+        tray.status = status.to_string();
+        tray.game_launched = game_launched;
+    });
+}
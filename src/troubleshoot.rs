@@ -0,0 +1,221 @@
+use reqwest::blocking::Client;
+use std::ffi::CString;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::mem::MaybeUninit;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+use tracing::info_span;
+
+use crate::bus;
+use crate::dirs;
+use crate::event::Event;
+use crate::profile;
+
+const MIB: u64 = 1024 * 1024;
+/// How long the connection check waits for the build server
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// A build and its cached copy need about this much
+const MIN_FREE_SPACE: u64 = 1024 * MIB;
+/// Vulkan drivers register themselves here
+const VULKAN_ICD_DIRS: [&str; 3] = [
+    "/usr/share/vulkan/icd.d",
+    "/etc/vulkan/icd.d",
+    "/usr/local/share/vulkan/icd.d",
+];
+
+/// What went wrong, it decides which checks are worth running
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Problem {
+    Download,
+    Launch,
+}
+
+impl Problem {
+    pub const fn checks(self) -> &'static [Check] {
+        match self {
+            Self::Download => &[Check::Connectivity, Check::DiskSpace, Check::Permissions],
+            Self::Launch => &[Check::Permissions, Check::GpuDrivers, Check::DiskSpace],
+        }
+    }
+
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::Download => "The game download failed",
+            Self::Launch => "The game failed to start",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Check {
+    Connectivity,
+    DiskSpace,
+    Permissions,
+    GpuDrivers,
+}
+
+impl Check {
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Connectivity => "Connection",
+            Self::DiskSpace => "Disk space",
+            Self::Permissions => "Permissions",
+            Self::GpuDrivers => "GPU drivers",
+        }
+    }
+}
+
+/// What a check found, a failure says what to do about it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Passed(String),
+    Failed(String),
+}
+
+/// Run the checks for `problem` one after another in the background,
+/// reporting each result as it's known
+pub fn start(problem: Problem, tx: &bus::Sender) {
+    let tx = tx.clone();
+    thread::spawn(move || {
+        let _span = info_span!("troubleshoot", ?problem).entered();
+        for &check in problem.checks() {
+            if tx.send(Event::CheckFinished(check, run(check))).is_err() {
+                return;
+            }
+        }
+    });
+}
+
+fn run(check: Check) -> Outcome {
+    match check {
+        Check::Connectivity => connectivity(),
+        Check::DiskSpace => disk_space(),
+        Check::Permissions => permissions(),
+        Check::GpuDrivers => gpu_drivers(),
+    }
+}
+
+fn connectivity() -> Outcome {
+    let client = match Client::builder().timeout(CONNECT_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => return Outcome::Failed(format!("Can't set up a connection: {e}")),
+    };
+    match client.head(profile::current().url()).send() {
+        Ok(response) if response.status().is_success() => {
+            Outcome::Passed("The build server answers".into())
+        }
+        Ok(response) => Outcome::Failed(format!(
+            "The build server answered {}, it may be down. Try again later",
+            response.status()
+        )),
+        Err(e) if e.is_timeout() => Outcome::Failed(
+            "The build server didn't answer in time. Check your internet connection".into(),
+        ),
+        Err(e) => Outcome::Failed(format!(
+            "Can't reach the build server ({e}). Check your internet connection, DNS and proxy"
+        )),
+    }
+}
+
+fn disk_space() -> Outcome {
+    let dir = dirs::base().install_home();
+    match filesystem(dir) {
+        Some(stat) => {
+            let free = stat.f_bavail.saturating_mul(stat.f_frsize);
+            if free < MIN_FREE_SPACE {
+                Outcome::Failed(format!(
+                    "Only {} MiB free in {dir:?}, free up at least {} MiB",
+                    free / MIB,
+                    MIN_FREE_SPACE / MIB
+                ))
+            } else {
+                Outcome::Passed(format!("{} MiB free", free / MIB))
+            }
+        }
+        None => Outcome::Failed(format!("Can't tell the free space in {dir:?}")),
+    }
+}
+
+fn permissions() -> Outcome {
+    let dir = dirs::base().install_home();
+    let probe = dir.join(".grav-write-test");
+    if let Err(e) = fs::create_dir_all(dir).and_then(|()| {
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&probe)
+    }) {
+        if e.kind() == io::ErrorKind::ReadOnlyFilesystem {
+            return Outcome::Failed(format!(
+                "{dir:?} is on a read-only filesystem, as system paths are on immutable \
+                 distributions. Install the game per user: drop --shared-install or pick \
+                 a --data-dir in your home directory"
+            ));
+        }
+        return Outcome::Failed(format!(
+            "Can't write to {dir:?} ({e}), check its owner and permissions"
+        ));
+    }
+    let _ = fs::remove_file(&probe);
+
+    if filesystem(dir).is_some_and(|stat| stat.f_flag & libc::ST_NOEXEC != 0) {
+        return Outcome::Failed(format!(
+            "{dir:?} is on a filesystem mounted with noexec, programs can't run from it"
+        ));
+    }
+    let game = dir.join(profile::current().artifact());
+    match fs::metadata(&game) {
+        Ok(metadata) if metadata.permissions().mode() & 0o111 == 0 => {
+            Outcome::Failed(format!("{game:?} isn't executable, run chmod +x on it"))
+        }
+        Ok(_) => Outcome::Passed(format!("{dir:?} is writable and the game is executable")),
+        Err(_) => Outcome::Passed(format!("{dir:?} is writable")),
+    }
+}
+
+fn gpu_drivers() -> Outcome {
+    let render_nodes = fs::read_dir("/dev/dri").map_or(0, |entries| {
+        entries
+            .flatten()
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with("renderD"))
+            .count()
+    });
+    if render_nodes == 0 {
+        return Outcome::Failed(
+            "No GPU render device in /dev/dri, the graphics driver isn't loaded".into(),
+        );
+    }
+    let drivers: Vec<String> = VULKAN_ICD_DIRS
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flatten()
+        .flatten()
+        .filter_map(|entry| Some(entry.path().file_stem()?.to_string_lossy().into_owned()))
+        .collect();
+    if drivers.is_empty() {
+        Outcome::Failed(
+            "No Vulkan driver is installed, install your distribution's Vulkan package \
+             (e.g. mesa-vulkan-drivers)"
+                .into(),
+        )
+    } else {
+        Outcome::Passed(format!("Vulkan drivers: {}", drivers.join(", ")))
+    }
+}
+
+fn filesystem(dir: &Path) -> Option<libc::statvfs> {
+    let path = CString::new(dir.as_os_str().as_bytes()).ok()?;
+    let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is a valid C string and `stat` is only read once the call filled it
+    unsafe {
+        if libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) != 0 {
+            return None;
+        }
+        Some(stat.assume_init())
+    }
+}
@@ -1,5 +1,18 @@
+pub mod fps;
 pub mod log;
-use crate::ui::log::{Entry, Log};
+pub mod perf;
+pub mod saved_state;
+pub mod theme;
+use crate::VERSION;
+use crate::config;
+use crate::profile;
+use crate::troubleshoot::{Check, Outcome, Problem};
+use crate::ui::fps::FpsHistory;
+use crate::ui::log::{Download, Entry, Log, OutputLine, Severity, marker_line};
+use crate::ui::perf::PerfStats;
+use crate::ui::theme::Theme;
+use crate::usage;
+use crate::versions::Build;
 mod list;
 use crate::ui::list::ListItem as WListItem;
 
@@ -8,22 +21,38 @@ use ratatui::{
     Frame,
     prelude::*,
     style::{Color, Style, Stylize},
-    symbols::{border, scrollbar},
     text::Line,
     widgets::{
-        Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, Scrollbar,
-        ScrollbarOrientation, ScrollbarState,
+        Block, Borders, Clear, List, ListItem, ListState, Paragraph, Scrollbar,
+        ScrollbarOrientation, ScrollbarState, Sparkline, Wrap,
     },
 };
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
 use tui_widget_list::{ListBuilder, ListState as WListState, ListView};
+use unicode_width::UnicodeWidthStr;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Borders and two rows of frame rate graph
+const FPS_PANEL_HEIGHT: u16 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FocusedLog {
     LauncherLog,
     GameStdout,
     GameStderr,
 }
 
+impl FocusedLog {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::LauncherLog => "Launcher log",
+            Self::GameStdout => "Game text output",
+            Self::GameStderr => "Game errors",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum InputMethod {
     Controller,
@@ -42,6 +71,25 @@ pub enum ExitPopupState {
     Visible,
 }
 
+/// A game download waiting for the player's go-ahead
+pub struct DownloadQuestion {
+    pub reason: String,
+    pub answer: mpsc::Sender<bool>,
+}
+
+/// The game is about to start, unless the player cancels
+pub struct LaunchCountdown {
+    pub until: Instant,
+    pub answer: mpsc::Sender<bool>,
+}
+
+/// An explanation the user has to acknowledge, e.g. how to fix the environment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notice {
+    pub title: String,
+    pub text: String,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TerminalFocus {
     Focused,
@@ -52,17 +100,116 @@ pub enum TerminalFocus {
 pub enum UpdateStatus {
     NotRequested,
     Requested,
+    Downloaded,
+    Applied,
+    Staged,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateChoice {
+    ApplyAndRestart,
+    ApplyOnNextStart,
+    Discard,
+}
+
+impl UpdateChoice {
+    pub const ALL: [Self; 3] = [Self::ApplyAndRestart, Self::ApplyOnNextStart, Self::Discard];
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::ApplyAndRestart => "Apply and restart now",
+            Self::ApplyOnNextStart => "Apply on next start",
+            Self::Discard => "Discard",
+        }
+    }
+
+    pub const fn next(self) -> Self {
+        match self {
+            Self::ApplyAndRestart => Self::ApplyOnNextStart,
+            Self::ApplyOnNextStart => Self::Discard,
+            Self::Discard => Self::ApplyAndRestart,
+        }
+    }
+
+    pub const fn prev(self) -> Self {
+        match self {
+            Self::ApplyAndRestart => Self::Discard,
+            Self::ApplyOnNextStart => Self::ApplyAndRestart,
+            Self::Discard => Self::ApplyOnNextStart,
+        }
+    }
+}
+
+/// Something to do once the game is downloaded, queued from the actions menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueuedAction {
+    LaunchGame,
+    PowerOff,
+}
+
+impl QueuedAction {
+    pub const ALL: [Self; 2] = [Self::LaunchGame, Self::PowerOff];
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::LaunchGame => "Launch the game when the download completes",
+            Self::PowerOff => "Shut down the PC when the download completes",
+        }
+    }
+
+    pub const fn other(self) -> Self {
+        match self {
+            Self::LaunchGame => Self::PowerOff,
+            Self::PowerOff => Self::LaunchGame,
+        }
+    }
+}
+
+/// Downloaded builds to pick from, the first entry follows updates instead
+pub struct VersionsMenu {
+    pub builds: Vec<Build>,
+    pub installed: Option<String>,
+    pub pinned: Option<String>,
+    pub selected: usize,
+}
+
+impl VersionsMenu {
+    pub const fn next(&mut self) {
+        self.selected = (self.selected + 1) % (self.builds.len() + 1);
+    }
+
+    pub const fn prev(&mut self) {
+        self.selected = (self.selected + self.builds.len()) % (self.builds.len() + 1);
+    }
+
+    /// Hash of the selected build, `None` to follow updates
+    pub fn choice(&self) -> Option<String> {
+        let index = self.selected.checked_sub(1)?;
+        Some(self.builds.get(index)?.hash.clone())
+    }
+}
+
+/// Troubleshooting checks started after an error, with the results so far
+pub struct Wizard {
+    pub problem: Problem,
+    pub results: Vec<(Check, Option<Outcome>)>,
 }
 
 pub struct AppState {
     pub log: Log,
-    pub game_stdout: Vec<String>,
-    pub game_stderr: Vec<String>,
+    pub game_stdout: Vec<OutputLine>,
+    pub game_stderr: Vec<OutputLine>,
     pub list_state: WListState,
     pub stdout_state: ListState,
     pub stderr_state: ListState,
     pub stdout_scroll: usize,
     pub stderr_scroll: usize,
+    /// Selected range of game stdout lines as (anchor, cursor)
+    pub stdout_selection: Option<(usize, usize)>,
+    /// Line count of each pane when it last lost focus, indexed by `FocusedLog`
+    pub seen_lines: [usize; 3],
+    /// Move focus to the stderr pane when the game reports an error
+    pub focus_errors: bool,
     pub focused_log: FocusedLog,
     pub display_mode: DisplayMode,
     pub exit_popup: ExitPopupState,
@@ -70,6 +217,61 @@ pub struct AppState {
     pub input_method: InputMethod,
     pub launcher_update_available: Option<String>,
     pub update_status: UpdateStatus,
+    pub update_popup: Option<UpdateChoice>,
+    /// Launcher log of the previous session, shown instead of the current one
+    /// in the fullscreen launcher log when toggled
+    pub previous_session: Vec<String>,
+    pub show_previous_session: bool,
+    pub previous_session_scroll: usize,
+    /// Launcher log entries less important than this are hidden
+    pub min_severity: Severity,
+    /// Writes lines to the game's stdin while it runs
+    pub game_stdin: Option<mpsc::Sender<String>>,
+    /// Line being typed into the game console prompt
+    pub stdin_input: Option<String>,
+    /// Name of the marker being typed
+    pub marker_input: Option<String>,
+    pub markers_added: usize,
+    /// Pane waiting for a confirmation to be cleared
+    pub clear_popup: Option<FocusedLog>,
+    /// Asks whether to stop a game that seems stuck on start
+    pub stalled_popup: bool,
+    /// Asks before the game's output stops being captured
+    pub release_popup: bool,
+    /// Asks before the running game is stopped
+    pub terminate_popup: bool,
+    /// Hash of the original build to restore over a modified one
+    pub restore_popup: Option<String>,
+    pub download_popup: Option<DownloadQuestion>,
+    pub launch_countdown: Option<LaunchCountdown>,
+    /// Starts the game, shown as a play button until pressed
+    pub ready_to_play: Option<mpsc::Sender<()>>,
+    /// Highlighted entry of the open actions menu
+    pub actions_menu: Option<QueuedAction>,
+    pub queued_actions: Vec<QueuedAction>,
+    pub versions_menu: Option<VersionsMenu>,
+    /// Highlighted entry of the open launch target menu
+    pub targets_menu: Option<usize>,
+    /// Program from the install launched instead of the game build
+    pub launch_target: Option<String>,
+    pub notice: Option<Notice>,
+    /// Offers to troubleshoot the error that just happened
+    pub troubleshoot_offer: Option<Problem>,
+    pub wizard: Option<Wizard>,
+    pub auto_restart: bool,
+    /// The player can't quit, pick another build or run another program
+    pub kiosk: bool,
+    pub game_launched: bool,
+    /// Latest memory and CPU use of the running game, with its peaks
+    pub game_usage: Option<usage::Report>,
+    /// The game exited and can be started again
+    pub launch_again: bool,
+    pub theme: Theme,
+    pub perf: PerfStats,
+    /// Show the event loop measurements over the panes
+    pub show_perf: bool,
+    /// Frame rate from the game's PERF lines
+    pub fps: FpsHistory,
 }
 
 impl AppState {
@@ -83,6 +285,9 @@ impl AppState {
             stderr_state: ListState::default(),
             stdout_scroll: 0,
             stderr_scroll: 0,
+            stdout_selection: None,
+            seen_lines: [0; 3],
+            focus_errors: false,
             focused_log: FocusedLog::LauncherLog,
             display_mode: DisplayMode::Normal,
             exit_popup: ExitPopupState::Hidden,
@@ -90,10 +295,45 @@ impl AppState {
             input_method: InputMethod::Controller,
             launcher_update_available: None,
             update_status: UpdateStatus::NotRequested,
+            update_popup: None,
+            previous_session: Vec::new(),
+            show_previous_session: false,
+            previous_session_scroll: 0,
+            min_severity: Severity::Info,
+            game_stdin: None,
+            stdin_input: None,
+            marker_input: None,
+            markers_added: 0,
+            clear_popup: None,
+            stalled_popup: false,
+            release_popup: false,
+            terminate_popup: false,
+            restore_popup: None,
+            download_popup: None,
+            launch_countdown: None,
+            ready_to_play: None,
+            actions_menu: None,
+            queued_actions: Vec::new(),
+            versions_menu: None,
+            targets_menu: None,
+            launch_target: None,
+            notice: None,
+            troubleshoot_offer: None,
+            wizard: None,
+            auto_restart: false,
+            kiosk: false,
+            game_launched: false,
+            launch_again: false,
+            game_usage: None,
+            theme: Theme::detect(),
+            perf: PerfStats::default(),
+            show_perf: false,
+            fps: FpsHistory::default(),
         }
     }
 
-    pub const fn next_log(&mut self) {
+    pub fn next_log(&mut self) {
+        self.mark_read(self.focused_log);
         self.focused_log = match self.focused_log {
             FocusedLog::LauncherLog => FocusedLog::GameStdout,
             FocusedLog::GameStdout => FocusedLog::GameStderr,
@@ -101,7 +341,8 @@ impl AppState {
         };
     }
 
-    pub const fn prev_log(&mut self) {
+    pub fn prev_log(&mut self) {
+        self.mark_read(self.focused_log);
         self.focused_log = match self.focused_log {
             FocusedLog::LauncherLog => FocusedLog::GameStderr,
             FocusedLog::GameStdout => FocusedLog::LauncherLog,
@@ -109,22 +350,262 @@ impl AppState {
         };
     }
 
+    const fn pane_len(&self, pane: FocusedLog) -> usize {
+        match pane {
+            FocusedLog::LauncherLog => self.log.extra_log.len(),
+            FocusedLog::GameStdout => self.game_stdout.len(),
+            FocusedLog::GameStderr => self.game_stderr.len(),
+        }
+    }
+
+    const fn mark_read(&mut self, pane: FocusedLog) {
+        self.seen_lines[pane as usize] = self.pane_len(pane);
+    }
+
+    /// Lines that arrived in a pane while it wasn't focused
+    pub fn unread(&self, pane: FocusedLog) -> usize {
+        if pane == self.focused_log {
+            return 0;
+        }
+        self.pane_len(pane)
+            .saturating_sub(self.seen_lines[pane as usize])
+    }
+
+    pub fn push_game_stderr(&mut self, line: String) {
+        self.game_stderr.push(OutputLine::Text(line));
+        // Don't swap the pane out from under a fullscreen view
+        if self.focus_errors
+            && self.display_mode == DisplayMode::Normal
+            && self.focused_log != FocusedLog::GameStderr
+        {
+            self.mark_read(self.focused_log);
+            self.focused_log = FocusedLog::GameStderr;
+        }
+    }
+
     pub const fn enter_fullscreen(&mut self, visible_height: usize) {
         self.display_mode = DisplayMode::Fullscreen(visible_height);
     }
 
     pub const fn exit_fullscreen(&mut self) {
         self.display_mode = DisplayMode::Normal;
+        self.show_previous_session = false;
+        self.stdout_selection = None;
+    }
+
+    /// Start selecting stdout lines from the top visible one, or drop the selection
+    pub fn toggle_stdout_selection(&mut self) {
+        if self.focused_log != FocusedLog::GameStdout || self.game_stdout.is_empty() {
+            return;
+        }
+        self.stdout_selection = match self.stdout_selection {
+            Some(_) => None,
+            None => Some((self.stdout_scroll, self.stdout_scroll)),
+        };
+    }
+
+    // Move the selection cursor, the view follows it on the next draw
+    fn move_selection_cursor(&mut self, down: bool) {
+        if let Some((anchor, cursor)) = self.stdout_selection {
+            let cursor = if down {
+                (cursor + 1).min(self.game_stdout.len().saturating_sub(1))
+            } else {
+                cursor.saturating_sub(1)
+            };
+            self.stdout_selection = Some((anchor, cursor));
+        }
+    }
+
+    pub fn selected_stdout(&self) -> Option<&[OutputLine]> {
+        let (anchor, cursor) = self.stdout_selection?;
+        self.game_stdout
+            .get(anchor.min(cursor)..=anchor.max(cursor))
+    }
+
+    pub fn open_stdin_prompt(&mut self) {
+        if self.game_stdin.is_some() && !self.kiosk {
+            self.stdin_input = Some(String::new());
+        }
+    }
+
+    /// Echo a console line in the output pane before it's sent to the game.
+    /// Returns false if there is no game to send it to
+    pub fn echo_console_line(&mut self, line: &str) -> bool {
+        if self.game_stdin.is_none() {
+            return false;
+        }
+        self.game_stdout.push(OutputLine::Text(format!("> {line}")));
+        true
+    }
+
+    pub fn game_input_closed(&mut self) {
+        self.game_stdin = None;
+        self.stdin_input = None;
+        self.log
+            .add_warning("Game console", "the game doesn't accept input anymore");
+    }
+
+    /// Drop a marker into every log, unnamed ones are numbered
+    pub fn add_marker(&mut self, name: String) {
+        self.marker_input = None;
+        self.markers_added += 1;
+        let name = if name.is_empty() {
+            format!("Marker {}", self.markers_added)
+        } else {
+            name
+        };
+        self.game_stdout.push(OutputLine::Marker(name.clone()));
+        self.game_stderr.push(OutputLine::Marker(name.clone()));
+        self.log.add_marker(name);
+    }
+
+    pub const fn cycle_severity_filter(&mut self) {
+        self.min_severity = self.min_severity.next_filter();
+    }
+
+    /// Switch the fullscreen launcher log between this and the previous session
+    pub fn toggle_previous_session(&mut self) {
+        if self.focused_log == FocusedLog::LauncherLog {
+            self.show_previous_session = !self.show_previous_session;
+            self.previous_session_scroll = 0;
+        }
+    }
+
+    /// Show the launcher version and where its settings come from
+    pub fn show_about(&mut self) {
+        self.notice = Some(Notice {
+            title: "About".into(),
+            text: about_text(self.kiosk),
+        });
     }
 
     pub const fn show_exit_popup(&mut self) {
-        self.exit_popup = ExitPopupState::Visible;
+        if !self.kiosk {
+            self.exit_popup = ExitPopupState::Visible;
+        }
     }
 
     pub const fn hide_exit_popup(&mut self) {
         self.exit_popup = ExitPopupState::Hidden;
     }
 
+    pub const fn show_update_popup(&mut self) {
+        self.update_popup = Some(UpdateChoice::ApplyAndRestart);
+    }
+
+    pub const fn hide_update_popup(&mut self) {
+        self.update_popup = None;
+    }
+
+    /// Open the actions menu, only useful until the game starts
+    pub const fn show_actions_menu(&mut self) {
+        if !self.game_launched {
+            self.actions_menu = Some(QueuedAction::LaunchGame);
+        }
+    }
+
+    /// Open the launch target menu at the current choice, if the install
+    /// has more than one program
+    pub fn show_targets_menu(&mut self) {
+        let targets = profile::current().launch_targets();
+        if targets.len() > 1 && !self.kiosk {
+            let current = self
+                .launch_target
+                .as_deref()
+                .and_then(|target| targets.iter().position(|name| *name == target));
+            self.targets_menu = Some(current.unwrap_or(0));
+        }
+    }
+
+    /// Queue the action, or take it back if it's queued already. Launching
+    /// and shutting down exclude each other
+    pub fn toggle_queued_action(&mut self, action: QueuedAction) {
+        if self.queued_actions.contains(&action) {
+            self.queued_actions.retain(|queued| *queued != action);
+        } else {
+            self.queued_actions = vec![action];
+        }
+    }
+
+    /// Take the action out of the queue, true if it was queued
+    pub fn take_queued_action(&mut self, action: QueuedAction) -> bool {
+        let queued = self.queued_actions.contains(&action);
+        self.queued_actions.retain(|other| *other != action);
+        queued
+    }
+
+    /// Offer the troubleshooting checks, unless they're already shown
+    pub const fn offer_troubleshooting(&mut self, problem: Problem) {
+        if self.wizard.is_none() {
+            self.troubleshoot_offer = Some(problem);
+        }
+    }
+
+    /// Open the wizard for the offered checks. Returns what to run them for
+    pub fn start_troubleshooting(&mut self) -> Option<Problem> {
+        let problem = self.troubleshoot_offer.take()?;
+        self.wizard = Some(Wizard {
+            problem,
+            results: problem
+                .checks()
+                .iter()
+                .map(|check| (*check, None))
+                .collect(),
+        });
+        Some(problem)
+    }
+
+    /// Show a check result in the wizard, and keep it in the log
+    pub fn record_check(&mut self, check: Check, outcome: Outcome) {
+        if let Some(wizard) = &mut self.wizard
+            && let Some((_, result)) = wizard.results.iter_mut().find(|(c, _)| *c == check)
+        {
+            *result = Some(outcome.clone());
+        }
+        let title = format!("{} check", check.label());
+        match outcome {
+            Outcome::Passed(text) => self.log.add_success(title, text),
+            Outcome::Failed(text) => self.log.add_warning(title, text),
+        }
+    }
+
+    pub const fn show_clear_popup(&mut self) {
+        self.clear_popup = Some(self.focused_log);
+    }
+
+    pub const fn hide_clear_popup(&mut self) {
+        self.clear_popup = None;
+    }
+
+    /// Drop the lines shown in a pane
+    pub fn clear_pane(&mut self, pane: FocusedLog) {
+        match pane {
+            FocusedLog::LauncherLog => self.log.extra_log.clear(),
+            FocusedLog::GameStdout => {
+                self.game_stdout.clear();
+                self.stdout_scroll = 0;
+                self.stdout_selection = None;
+            }
+            FocusedLog::GameStderr => {
+                self.game_stderr.clear();
+                self.stderr_scroll = 0;
+            }
+        }
+        self.mark_read(pane);
+    }
+
+    pub const fn next_update_choice(&mut self) {
+        if let Some(choice) = self.update_popup {
+            self.update_popup = Some(choice.next());
+        }
+    }
+
+    pub const fn prev_update_choice(&mut self) {
+        if let Some(choice) = self.update_popup {
+            self.update_popup = Some(choice.prev());
+        }
+    }
+
     pub fn set_terminal_focus(&mut self, focused: bool) {
         if (focused && self.terminal_focus == TerminalFocus::Unfocused)
             || (!focused && self.terminal_focus == TerminalFocus::Focused)
@@ -141,21 +622,43 @@ impl AppState {
         self.input_method = InputMethod::Controller;
     }
 
+    /// Rough size of the text kept in the panes and logs
+    pub fn log_buffer_bytes(&self) -> usize {
+        let output = |lines: &[OutputLine]| -> usize {
+            lines
+                .iter()
+                .map(|line| match line {
+                    OutputLine::Text(text) | OutputLine::Marker(text) => text.capacity(),
+                })
+                .sum()
+        };
+        let previous: usize = self.previous_session.iter().map(String::capacity).sum();
+        output(&self.game_stdout)
+            + output(&self.game_stderr)
+            + previous
+            + launcher_log_lines(&self.log)
+                .iter()
+                .map(String::len)
+                .sum::<usize>()
+    }
+
     pub const fn keyboard_input_used(&mut self) {
         self.input_method = InputMethod::Keyboard;
     }
 
-    pub const fn scroll_up(&mut self) {
+    pub fn scroll_up(&mut self) {
         match self.focused_log {
-            FocusedLog::GameStdout => {
-                if self.stdout_scroll > 0 {
-                    self.stdout_scroll = self.stdout_scroll.saturating_sub(1);
-                }
+            FocusedLog::GameStdout if self.stdout_selection.is_some() => {
+                self.move_selection_cursor(false);
             }
-            FocusedLog::GameStderr => {
-                if self.stderr_scroll > 0 {
-                    self.stderr_scroll = self.stderr_scroll.saturating_sub(1);
-                }
+            FocusedLog::LauncherLog if self.show_previous_session => {
+                self.previous_session_scroll = self.previous_session_scroll.saturating_sub(1);
+            }
+            FocusedLog::GameStdout if self.stdout_scroll > 0 => {
+                self.stdout_scroll = self.stdout_scroll.saturating_sub(1);
+            }
+            FocusedLog::GameStderr if self.stderr_scroll > 0 => {
+                self.stderr_scroll = self.stderr_scroll.saturating_sub(1);
             }
             _ => {}
         }
@@ -163,18 +666,29 @@ impl AppState {
 
     pub const fn scroll_to_top(&mut self) {
         match self.focused_log {
+            FocusedLog::LauncherLog => {
+                self.previous_session_scroll = 0;
+            }
             FocusedLog::GameStdout => {
                 self.stdout_scroll = 0;
             }
             FocusedLog::GameStderr => {
                 self.stderr_scroll = 0;
             }
-            _ => {}
         }
     }
 
     pub fn scroll_down(&mut self) {
         match self.focused_log {
+            FocusedLog::GameStdout if self.stdout_selection.is_some() => {
+                self.move_selection_cursor(true);
+            }
+            FocusedLog::LauncherLog if self.show_previous_session => {
+                let max_scroll = self.previous_session.len().saturating_sub(1);
+                if self.previous_session_scroll < max_scroll {
+                    self.previous_session_scroll += 1;
+                }
+            }
             FocusedLog::GameStdout => {
                 let max_scroll = self.game_stdout.len().saturating_sub(1);
                 if self.stdout_scroll < max_scroll {
@@ -193,6 +707,9 @@ impl AppState {
 
     pub fn scroll_to_bottom(&mut self) {
         match self.focused_log {
+            FocusedLog::LauncherLog => {
+                self.previous_session_scroll = self.previous_session.len().saturating_sub(1);
+            }
             FocusedLog::GameStdout => {
                 let max_scroll = self.game_stdout.len().saturating_sub(1);
                 self.stdout_scroll = max_scroll;
@@ -201,9 +718,45 @@ impl AppState {
                 let max_scroll = self.game_stderr.len().saturating_sub(1);
                 self.stderr_scroll = max_scroll;
             }
-            _ => {}
         }
     }
+
+    /// Terminal window title reflecting the current phase and progress,
+    /// e.g. "GRAV: downloading 63%"
+    pub fn window_title(&self) -> String {
+        if let Some(download) = &self.log.game_download
+            && let DownloadStatus::InProgress = download.status()
+        {
+            return format!("GRAV: downloading {}", download_progress_text(download));
+        }
+        if let Some(download) = &self.log.launcher_update
+            && let DownloadStatus::InProgress = download.status()
+        {
+            return format!(
+                "GRAV: updating launcher {}",
+                download_progress_text(download)
+            );
+        }
+        if self.log.remote_hash_msg.as_deref() == Some("accessing")
+            || self.log.local_hash_msg.as_deref() == Some("Computing")
+        {
+            return "GRAV: checking for updates".into();
+        }
+        if self.game_launched {
+            return "GRAV: running".into();
+        }
+        "GRAV launcher".into()
+    }
+}
+
+// Percentage if the total size is known, downloaded size otherwise
+fn download_progress_text(download: &Download) -> String {
+    match download.total() {
+        Some(total) if *total > 0 => {
+            format!("{}%", download.current().saturating_mul(100) / *total)
+        }
+        _ => format_file_size(download.current()),
+    }
 }
 
 pub fn draw(frame: &mut Frame, app_state: &mut AppState) {
@@ -222,14 +775,144 @@ pub fn draw(frame: &mut Frame, app_state: &mut AppState) {
 
     if app_state.display_mode == DisplayMode::Normal {
         render_normal_view(frame, area, app_state);
+        if app_state.ready_to_play.is_some() {
+            render_play_button(frame, area, app_state);
+        }
     } else {
         render_fullscreen_view(frame, area, app_state);
     }
 
+    // Render update choice popup if needed
+    if let Some(choice) = app_state.update_popup {
+        render_update_popup(frame, area, app_state, choice);
+    }
+
+    if let Some(selected) = app_state.actions_menu {
+        render_actions_menu(frame, area, app_state, selected);
+    }
+
+    if let Some(menu) = &app_state.versions_menu {
+        render_versions_menu(frame, area, app_state, menu);
+    }
+
+    if let Some(selected) = app_state.targets_menu {
+        render_targets_menu(frame, area, app_state, selected);
+    }
+
+    if let Some(line) = &app_state.stdin_input {
+        render_stdin_prompt(frame, area, app_state, line);
+    }
+
+    if let Some(name) = &app_state.marker_input {
+        render_marker_popup(frame, area, app_state, name);
+    }
+
+    if let Some(pane) = app_state.clear_popup {
+        let question = format!("Clear the {}?", pane.name().to_lowercase());
+        render_confirm_popup(frame, area, app_state, &question);
+    }
+
+    if app_state.stalled_popup {
+        render_confirm_popup(
+            frame,
+            area,
+            app_state,
+            "The game appears to have failed to start. Stop it?",
+        );
+    }
+
+    if app_state.terminate_popup {
+        render_confirm_popup(
+            frame,
+            area,
+            app_state,
+            "Stop the game? Unsaved progress may be lost",
+        );
+    }
+
+    if app_state.release_popup {
+        render_confirm_popup(
+            frame,
+            area,
+            app_state,
+            "Let the game run on its own? Its output stops being recorded, \
+             the launcher can be closed",
+        );
+    }
+
+    if app_state.restore_popup.is_some() {
+        let question = format!(
+            "{} was modified since it was installed. Restore the original?",
+            profile::current().artifact()
+        );
+        render_confirm_popup(frame, area, app_state, &question);
+    }
+
+    if let Some(question) = &app_state.download_popup {
+        let question = format!("{}. Download the update anyway?", question.reason);
+        render_confirm_popup(frame, area, app_state, &question);
+    }
+
+    if let Some(countdown) = &app_state.launch_countdown {
+        let left = countdown.until.saturating_duration_since(Instant::now());
+        let question = format!(
+            "Launching the game in {}... Start it now?",
+            left.as_secs() + 1
+        );
+        render_confirm_popup(frame, area, app_state, &question);
+    }
+
+    if let Some(notice) = &app_state.notice {
+        render_notice_popup(frame, area, app_state, notice);
+    }
+
+    if let Some(problem) = app_state.troubleshoot_offer {
+        let question = format!("{}. Run the troubleshooting checks?", problem.description());
+        render_confirm_popup(frame, area, app_state, &question);
+    }
+
+    if let Some(wizard) = &app_state.wizard {
+        render_wizard(frame, area, app_state, wizard);
+    }
+
+    if app_state.show_perf {
+        render_perf_overlay(frame, area, app_state);
+    }
+
     // Render exit confirmation popup if needed
     if app_state.exit_popup == ExitPopupState::Visible {
-        render_exit_popup(frame, area, app_state);
+        render_confirm_popup(frame, area, app_state, "Are you sure you want to exit?");
+    }
+}
+
+// The version and the settings read from each config file, the ones the
+// administrator enforces marked as such
+fn about_text(kiosk: bool) -> String {
+    let mut text = format!("grav-launcher {VERSION}");
+    let mut from_files = false;
+    for (path, keys) in config::sources() {
+        if keys.is_empty() {
+            continue;
+        }
+        from_files = true;
+        let origin = if path.as_os_str() == config::POLICY_FILE {
+            ", enforced by the administrator"
+        } else {
+            ""
+        };
+        text.push_str(&format!(
+            "\n\nFrom {}{origin}:\n{}",
+            path.display(),
+            keys.join(", ")
+        ));
     }
+    if !from_files {
+        text.push_str("\n\nNo settings from config files");
+    }
+    if kiosk {
+        text.push_str("\n\nKiosk mode: quitting, other builds and other programs are locked");
+    }
+    text
 }
 
 fn render_main_frame(frame: &mut Frame, area: Rect, app_state: &AppState) {
@@ -240,12 +923,40 @@ fn render_main_frame(frame: &mut Frame, area: Rect, app_state: &AppState) {
     let block = Block::bordered()
         .title(title.centered())
         .title_bottom(help_line.right_aligned())
-        .border_set(border::THICK);
+        .border_set(app_state.theme.border_set());
     frame.render_widget(block, area);
 }
 
-fn get_help_text(app_state: &AppState) -> Vec<Span> {
-    if app_state.exit_popup == ExitPopupState::Visible {
+// What Enter or A does in the normal view
+const fn select_label(app_state: &AppState) -> &'static str {
+    if app_state.ready_to_play.is_some() {
+        " Play | "
+    } else if app_state.launch_again {
+        " Launch again | "
+    } else {
+        " Open a Log | "
+    }
+}
+
+fn get_help_text(app_state: &AppState) -> Vec<Span<'_>> {
+    if app_state.exit_popup == ExitPopupState::Visible
+        || app_state.update_popup.is_some()
+        || app_state.clear_popup.is_some()
+        || app_state.stalled_popup
+        || app_state.release_popup
+        || app_state.terminate_popup
+        || app_state.restore_popup.is_some()
+        || app_state.download_popup.is_some()
+        || app_state.launch_countdown.is_some()
+        || app_state.actions_menu.is_some()
+        || app_state.versions_menu.is_some()
+        || app_state.targets_menu.is_some()
+        || app_state.notice.is_some()
+        || app_state.troubleshoot_offer.is_some()
+        || app_state.wizard.is_some()
+        || app_state.marker_input.is_some()
+        || app_state.stdin_input.is_some()
+    {
         // Hide normal controls when popup is shown
         vec![]
     } else if let DisplayMode::Fullscreen(visible_height) = app_state.display_mode {
@@ -253,6 +964,9 @@ fn get_help_text(app_state: &AppState) -> Vec<Span> {
 
         // Add scrolling instructions if content is scrollable
         let is_scrollable = match app_state.focused_log {
+            FocusedLog::LauncherLog if app_state.show_previous_session => {
+                app_state.previous_session.len() > visible_height
+            }
             FocusedLog::LauncherLog => app_state.log.entries().len() > visible_height,
             FocusedLog::GameStdout => app_state.game_stdout.len() > visible_height,
             FocusedLog::GameStderr => app_state.game_stderr.len() > visible_height,
@@ -264,29 +978,63 @@ fn get_help_text(app_state: &AppState) -> Vec<Span> {
                 InputMethod::Controller => {
                     controls.push(Span::styled(
                         "D-Pad Up/Down",
-                        Style::default().fg(Color::Yellow).bold(),
+                        app_state.theme.key(Color::Yellow),
                     ));
                     controls.push(Span::raw(" Scroll "));
                 }
                 InputMethod::Keyboard => {
-                    controls.push(Span::styled("↑/↓", Style::default().fg(Color::Blue).bold()));
+                    controls.push(Span::styled(
+                        app_state.theme.symbol("↑/↓", "Up/Down"),
+                        app_state.theme.key(Color::Blue),
+                    ));
                     controls.push(Span::raw(" Scroll "));
                 }
             }
             controls.push(Span::raw(" |"));
         }
 
+        // Line selection in the game output, keyboard only
+        if app_state.focused_log == FocusedLog::GameStdout
+            && app_state.input_method == InputMethod::Keyboard
+        {
+            if app_state.stdout_selection.is_some() {
+                controls.push(Span::styled(" s", app_state.theme.key(Color::Blue)));
+                controls.push(Span::raw(" Save | "));
+                controls.push(Span::styled("a", app_state.theme.key(Color::Blue)));
+                controls.push(Span::raw(" Append to notes |"));
+            } else {
+                controls.push(Span::styled(" v", app_state.theme.key(Color::Blue)));
+                controls.push(Span::raw(" Select |"));
+            }
+        }
+
+        // Toggle between this and the previous session in the launcher log
+        if app_state.focused_log == FocusedLog::LauncherLog {
+            let label = if app_state.show_previous_session {
+                " Current session "
+            } else {
+                " Previous session "
+            };
+            match app_state.input_method {
+                InputMethod::Controller => {
+                    controls.push(Span::styled(" Y", app_state.theme.key(Color::Yellow)));
+                }
+                InputMethod::Keyboard => {
+                    controls.push(Span::styled(" p", app_state.theme.key(Color::Blue)));
+                }
+            }
+            controls.push(Span::raw(label));
+            controls.push(Span::raw("|"));
+        }
+
         // Add back control
         match app_state.input_method {
             InputMethod::Controller => {
-                controls.push(Span::styled(" B", Style::default().fg(Color::Red).bold()));
+                controls.push(Span::styled(" B", app_state.theme.key(Color::Red)));
                 controls.push(Span::raw(" Back "));
             }
             InputMethod::Keyboard => {
-                controls.push(Span::styled(
-                    " Esc",
-                    Style::default().fg(Color::Blue).bold(),
-                ));
+                controls.push(Span::styled(" Esc", app_state.theme.key(Color::Blue)));
                 controls.push(Span::raw(" Back "));
             }
         }
@@ -295,7 +1043,7 @@ fn get_help_text(app_state: &AppState) -> Vec<Span> {
     } else if app_state.terminal_focus == TerminalFocus::Unfocused {
         vec![
             Span::raw(" Terminal "),
-            Span::styled("NOT FOCUSED", Style::default().fg(Color::Red).bold()),
+            Span::styled("NOT FOCUSED", app_state.theme.key(Color::Red)),
             Span::raw(" - Controller disabled "),
         ]
     } else {
@@ -309,22 +1057,43 @@ fn get_help_text(app_state: &AppState) -> Vec<Span> {
                     && app_state.update_status == UpdateStatus::NotRequested
                 {
                     controls.push(Span::raw(" "));
-                    controls.push(Span::styled("Y", Style::default().fg(Color::Yellow).bold()));
+                    controls.push(Span::styled("Y", app_state.theme.key(Color::Yellow)));
                     controls.push(Span::raw(" Update"));
                     controls.push(Span::raw(" |"));
+                } else if app_state.update_status == UpdateStatus::Downloaded {
+                    controls.push(Span::raw(" "));
+                    controls.push(Span::styled("Y", app_state.theme.key(Color::Yellow)));
+                    controls.push(Span::raw(" Install update"));
+                    controls.push(Span::raw(" |"));
                 }
 
                 controls.push(Span::raw(" "));
-                controls.push(Span::styled(
-                    "D-Pad",
-                    Style::default().fg(Color::Yellow).bold(),
-                ));
+                controls.push(Span::styled("D-Pad", app_state.theme.key(Color::Yellow)));
                 controls.push(Span::raw(" Navigate |"));
 
-                controls.push(Span::styled(" A", Style::default().fg(Color::Green).bold()));
-                controls.push(Span::raw(" Open a Log | "));
-                controls.push(Span::styled("B", Style::default().fg(Color::Red).bold()));
-                controls.push(Span::raw(" Exit "));
+                controls.push(Span::styled(" A", app_state.theme.key(Color::Green)));
+                controls.push(Span::raw(select_label(app_state)));
+                controls.push(Span::styled("X", app_state.theme.key(Color::Blue)));
+                controls.push(Span::raw(" Clear | "));
+                controls.push(Span::styled("Select", app_state.theme.key(Color::Blue)));
+                controls.push(Span::raw(" Filter, hold for versions | "));
+                controls.push(Span::styled("Start", app_state.theme.key(Color::Blue)));
+                controls.push(Span::raw(" Marker | "));
+                controls.push(Span::styled(
+                    "Select+Start",
+                    app_state.theme.key(Color::Blue),
+                ));
+                controls.push(Span::raw(" Export logs | "));
+                if !app_state.game_launched {
+                    controls.push(Span::styled("R3", app_state.theme.key(Color::Blue)));
+                    controls.push(Span::raw(" Actions | "));
+                }
+                if !app_state.kiosk {
+                    controls.push(Span::styled("L3", app_state.theme.key(Color::Blue)));
+                    controls.push(Span::raw(" Versions | "));
+                    controls.push(Span::styled("B", app_state.theme.key(Color::Red)));
+                    controls.push(Span::raw(" Exit "));
+                }
 
                 controls
             }
@@ -336,26 +1105,59 @@ fn get_help_text(app_state: &AppState) -> Vec<Span> {
                     && app_state.update_status == UpdateStatus::NotRequested
                 {
                     controls.push(Span::raw(" "));
-                    controls.push(Span::styled("u", Style::default().fg(Color::Yellow).bold()));
+                    controls.push(Span::styled("u", app_state.theme.key(Color::Yellow)));
                     controls.push(Span::raw(" Update"));
                     controls.push(Span::raw(" |"));
+                } else if app_state.update_status == UpdateStatus::Downloaded {
+                    controls.push(Span::raw(" "));
+                    controls.push(Span::styled("u", app_state.theme.key(Color::Yellow)));
+                    controls.push(Span::raw(" Install update"));
+                    controls.push(Span::raw(" |"));
                 }
 
                 controls.push(Span::raw(" "));
-                controls.push(Span::styled(
-                    "Arrows",
-                    Style::default().fg(Color::Blue).bold(),
-                ));
+                controls.push(Span::styled("Arrows", app_state.theme.key(Color::Blue)));
                 controls.push(Span::raw(" Navigate |"));
 
                 // Then add other controls
-                controls.push(Span::styled(
-                    " Enter",
-                    Style::default().fg(Color::Blue).bold(),
-                ));
-                controls.push(Span::raw(" Open a Log | "));
-                controls.push(Span::styled("Esc", Style::default().fg(Color::Blue).bold()));
-                controls.push(Span::raw(" Exit "));
+                controls.push(Span::styled(" Enter", app_state.theme.key(Color::Blue)));
+                controls.push(Span::raw(select_label(app_state)));
+                controls.push(Span::styled("c", app_state.theme.key(Color::Blue)));
+                controls.push(Span::raw(" Clear | "));
+                controls.push(Span::styled("f", app_state.theme.key(Color::Blue)));
+                controls.push(Span::raw(" Filter | "));
+                controls.push(Span::styled("m", app_state.theme.key(Color::Blue)));
+                controls.push(Span::raw(" Marker | "));
+                controls.push(Span::styled("s", app_state.theme.key(Color::Blue)));
+                controls.push(Span::raw(" Export logs | "));
+                if !app_state.game_launched {
+                    controls.push(Span::styled("a", app_state.theme.key(Color::Blue)));
+                    controls.push(Span::raw(" Actions | "));
+                }
+                if !app_state.kiosk {
+                    controls.push(Span::styled("v", app_state.theme.key(Color::Blue)));
+                    controls.push(Span::raw(" Versions | "));
+                }
+                if profile::current().launch_targets().len() > 1 && !app_state.kiosk {
+                    controls.push(Span::styled("t", app_state.theme.key(Color::Blue)));
+                    controls.push(Span::raw(" Target | "));
+                }
+                if app_state.game_stdin.is_some() && !app_state.kiosk {
+                    controls.push(Span::styled("i", app_state.theme.key(Color::Blue)));
+                    controls.push(Span::raw(" Console | "));
+                    controls.push(Span::styled("r", app_state.theme.key(Color::Blue)));
+                    controls.push(Span::raw(" Release | "));
+                }
+                if app_state.game_launched {
+                    controls.push(Span::styled("x", app_state.theme.key(Color::Blue)));
+                    controls.push(Span::raw(" Stop game | "));
+                }
+                controls.push(Span::styled("?", app_state.theme.key(Color::Blue)));
+                controls.push(Span::raw(" About "));
+                if !app_state.kiosk {
+                    controls.push(Span::styled("| Esc", app_state.theme.key(Color::Blue)));
+                    controls.push(Span::raw(" Exit "));
+                }
 
                 controls
             }
@@ -374,6 +1176,9 @@ fn render_fullscreen_view(frame: &mut Frame, area: Rect, app_state: &mut AppStat
         .split(outer_layout[0])[0];
 
     match app_state.focused_log {
+        FocusedLog::LauncherLog if app_state.show_previous_session => {
+            render_previous_session(frame, content_area, app_state);
+        }
         FocusedLog::LauncherLog => render_fullscreen_launcher_log(frame, content_area, app_state),
         FocusedLog::GameStdout => render_fullscreen_game_stdout(frame, content_area, app_state),
         FocusedLog::GameStderr => render_fullscreen_game_stderr(frame, content_area, app_state),
@@ -381,7 +1186,7 @@ fn render_fullscreen_view(frame: &mut Frame, area: Rect, app_state: &mut AppStat
 }
 
 // Helper function to format file sizes in a human-readable way
-fn format_file_size(size: u64) -> String {
+pub fn format_file_size(size: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = KB * 1024;
     const GB: u64 = MB * 1024;
@@ -397,66 +1202,185 @@ fn format_file_size(size: u64) -> String {
     }
 }
 
-fn render_fullscreen_launcher_log(frame: &mut Frame, area: Rect, app_state: &mut AppState) {
-    // Build the list of items for the log
-    let mut items: Vec<WListItem> = Vec::new();
+// Helper function to format durations like "2m 14s"
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}h {}m", secs / 3600, (secs % 3600) / 60)
+    } else if secs >= 60 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else {
+        format!("{secs}s")
+    }
+}
 
-    // We'll use entries() from Log which now includes everything
-    items.extend(app_state.log.entries().iter().map(|i| match i {
-        Entry::Text(title_opt, text) => match title_opt {
-            Some(title) => WListItem::with_title(title, text),
-            None => WListItem::new(text),
-        },
+// Progress text for a running download: "12MB / 40MB, 1.20MB/s, 24s remaining"
+fn download_progress_details(download: &Download) -> String {
+    let mut text = format_file_size(download.current());
+    if let Some(total) = download.total() {
+        text = format!("{text} / {}", format_file_size(*total));
+    }
+    if download.resumed_from > 0 {
+        text = format!(
+            "{text}, resumed at {}",
+            format_file_size(download.resumed_from)
+        );
+    }
+    if let Some(speed) = download.speed_bps() {
+        text = format!("{text}, {}/s", format_file_size(speed));
+    }
+    if let Some(eta) = download.eta() {
+        text = format!("{text}, {} remaining", format_duration(eta));
+    }
+    text
+}
+
+// Pane title with a counter of lines that arrived while it was unfocused
+fn pane_title(pane: FocusedLog, app_state: &AppState) -> Line<'static> {
+    let name = match (pane, app_state.min_severity) {
+        (FocusedLog::LauncherLog, Severity::Warn) => "Launcher log: warnings and errors",
+        (FocusedLog::LauncherLog, Severity::Error) => "Launcher log: errors",
+        _ => pane.name(),
+    };
+    match app_state.unread(pane) {
+        0 => Line::from(format!(" {name} ").bold()),
+        unread => Line::from(format!(" {name} ({unread} new) ").bold()),
+    }
+}
+
+/// Launcher log as plain text, leaving out downloads that are still running
+pub fn launcher_log_lines(log: &Log) -> Vec<String> {
+    log.entries()
+        .iter()
+        .filter(|entry| match entry {
+            Entry::Downloand(download)
+            | Entry::LauncherUpdate(download)
+            | Entry::GameDownload(download) => {
+                !matches!(download.status(), DownloadStatus::InProgress)
+            }
+            Entry::Text(..) | Entry::Marker(..) | Entry::HashDiff { .. } => true,
+        })
+        .map(|entry| {
+            // Records are written without their timestamp, the session log has its own
+            let (title, text) = match entry {
+                Entry::Text(record) => (record.title.clone(), record.text.clone()),
+                Entry::Marker(record) => (None, marker_line(&record.text)),
+                _ => {
+                    let item = entry_item(entry, Theme::default());
+                    (item.title, item.text)
+                }
+            };
+            match title {
+                Some(title) if text.is_empty() => title,
+                Some(title) => format!("{title}: {text}"),
+                None => text,
+            }
+        })
+        .collect()
+}
+
+// Render a download as a gauge if the total size is known
+fn download_item(title: &str, download: &Download, theme: Theme) -> WListItem {
+    let text = download_progress_details(download);
+    match download.total() {
+        Some(total) if *total > 0 => {
+            WListItem::new_gauge(title, text, (download.current() as f64) / (*total as f64))
+                .theme(theme)
+        }
+        _ => WListItem::with_title(title, text),
+    }
+}
+
+fn severity_style(severity: Severity, theme: Theme) -> Style {
+    match severity {
+        Severity::Info => Style::default(),
+        Severity::Success => theme.fg(Color::Green),
+        Severity::Warn => theme.fg(Color::Yellow),
+        Severity::Error => theme.fg(Color::Red).bold(),
+    }
+}
+
+// Game output line, markers are highlighted dividers
+fn output_item(line: &OutputLine, theme: Theme) -> ListItem<'_> {
+    match line {
+        OutputLine::Text(text) => ListItem::new(text.as_str()),
+        OutputLine::Marker(name) => {
+            ListItem::new(marker_line(name)).style(theme.key(Color::Magenta))
+        }
+    }
+}
+
+// Convert a log entry into a list item
+fn entry_item(entry: &Entry, theme: Theme) -> WListItem {
+    match entry {
+        Entry::Text(record) => {
+            let time = record
+                .time
+                .map(|time| time.format("%H:%M:%S ").to_string())
+                .unwrap_or_default();
+            let mut item = match &record.title {
+                Some(title) => WListItem::with_title(format!("{time}{title}"), &record.text),
+                None => WListItem::new(format!("{time}{}", record.text)),
+            };
+            item.style = severity_style(record.severity, theme);
+            item
+        }
+        Entry::Marker(record) => {
+            WListItem::new(marker_line(&record.text)).style(theme.key(Color::Magenta))
+        }
+        Entry::HashDiff { title, text, marks } => {
+            WListItem::highlighted(title, text, marks.clone()).theme(theme)
+        }
         Entry::Downloand(download) => {
             WListItem::with_title("Download", format_file_size(download.current()))
         }
         Entry::LauncherUpdate(download) => match download.status() {
-            DownloadStatus::InProgress => {
-                if let Some(total) = download.total() {
-                    WListItem::new_gauge(
-                        "Launcher update",
-                        format!(
-                            "{} / {}",
-                            format_file_size(download.current()),
-                            format_file_size(*total)
-                        ),
-                        (download.current() as f64) / (*total as f64),
-                    )
-                } else {
-                    WListItem::with_title("Launcher update", format_file_size(download.current()))
-                }
-            }
+            DownloadStatus::InProgress => download_item("Launcher update", download, theme),
+            DownloadStatus::Verifying => WListItem::with_title(
+                "Launcher update",
+                format!(
+                    "{} downloaded, verifying{}",
+                    format_file_size(download.current()),
+                    theme.symbol("…", "...")
+                ),
+            ),
             DownloadStatus::Comple => WListItem::with_title(
                 "Launcher update",
                 format!(
-                    "{} Downloaded. Restart needed.",
+                    "{} downloaded and verified",
                     format_file_size(download.current())
                 ),
             ),
-            DownloadStatus::Errored(err) => WListItem::with_title("Launcher update error", err),
+            DownloadStatus::Errored(err) => WListItem::with_title("Launcher update error", err)
+                .style(severity_style(Severity::Error, theme)),
         },
         Entry::GameDownload(download) => match download.status() {
-            DownloadStatus::InProgress => {
-                if let Some(total) = download.total() {
-                    WListItem::new_gauge(
-                        "Downloading game",
-                        format!(
-                            "{} / {}",
-                            format_file_size(download.current()),
-                            format_file_size(*total)
-                        ),
-                        (download.current() as f64) / (*total as f64),
-                    )
-                } else {
-                    WListItem::with_title("Downloading game", format_file_size(download.current()))
-                }
+            DownloadStatus::InProgress => download_item("Downloading game", download, theme),
+            DownloadStatus::Verifying => {
+                WListItem::with_title("Verifying game", format_file_size(download.current()))
             }
             DownloadStatus::Comple => {
                 WListItem::with_title("Game downloaded", format_file_size(download.current()))
             }
-            DownloadStatus::Errored(err) => WListItem::with_title("Game download error", err),
+            DownloadStatus::Errored(err) => WListItem::with_title("Game download error", err)
+                .style(severity_style(Severity::Error, theme)),
         },
-    }));
+    }
+}
+
+fn render_fullscreen_launcher_log(frame: &mut Frame, area: Rect, app_state: &mut AppState) {
+    // Build the list of items for the log
+    let mut items: Vec<WListItem> = Vec::new();
+
+    let theme = app_state.theme;
+    items.extend(
+        app_state
+            .log
+            .entries()
+            .iter()
+            .filter(|entry| entry.is_shown(app_state.min_severity))
+            .map(|entry| entry_item(entry, theme)),
+    );
 
     let builder = ListBuilder::new(|context| {
         let item = items[context.index].clone();
@@ -464,15 +1388,63 @@ fn render_fullscreen_launcher_log(frame: &mut Frame, area: Rect, app_state: &mut
         (item, main_axis_size)
     });
 
-    let title = Line::from(" Launcher log ".bold());
+    let title = pane_title(FocusedLog::LauncherLog, app_state);
     let block = Block::bordered()
         .title(title.centered())
-        .border_set(border::THICK);
+        .border_set(app_state.theme.border_set());
 
     let list = ListView::new(builder, items.len()).block(block);
     frame.render_stateful_widget(list, area, &mut app_state.list_state);
 }
 
+fn render_previous_session(frame: &mut Frame, area: Rect, app_state: &mut AppState) {
+    let visible_height = area.height.saturating_sub(2) as usize; // Account for borders
+    let total_items = app_state.previous_session.len();
+    let max_scroll = total_items.saturating_sub(visible_height);
+    app_state.previous_session_scroll = app_state.previous_session_scroll.min(max_scroll);
+
+    let start_idx = app_state.previous_session_scroll;
+    let lines: Vec<ListItem> = if total_items == 0 {
+        vec![ListItem::new("No log from a previous session")]
+    } else {
+        app_state
+            .previous_session
+            .iter()
+            .skip(start_idx)
+            .take(visible_height)
+            .map(|line| ListItem::new(line.as_str()))
+            .collect()
+    };
+
+    let title = Line::from(" Launcher log: previous session ".bold());
+    let block = Block::bordered()
+        .title(title.centered())
+        .border_set(app_state.theme.border_set());
+    frame.render_widget(List::new(lines).block(block), area);
+
+    if total_items > visible_height {
+        let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+            .symbols(app_state.theme.scrollbar_set())
+            .begin_symbol(None)
+            .track_symbol(None)
+            .end_symbol(None);
+
+        let mut scrollbar_state = ScrollbarState::default()
+            .content_length(max_scroll + 1)
+            .viewport_content_length(visible_height)
+            .position(start_idx);
+
+        frame.render_stateful_widget(
+            scrollbar,
+            area.inner(Margin {
+                vertical: 1,
+                horizontal: 0,
+            }),
+            &mut scrollbar_state,
+        );
+    }
+}
+
 fn render_fullscreen_game_stdout(frame: &mut Frame, area: Rect, app_state: &mut AppState) {
     let visible_height = area.height.saturating_sub(2) as usize; // Account for borders
     let total_items = app_state.game_stdout.len();
@@ -484,27 +1456,52 @@ fn render_fullscreen_game_stdout(frame: &mut Frame, area: Rect, app_state: &mut
         total_items.saturating_sub(visible_height)
     };
 
+    // Keep the selection cursor in view
+    if let Some((_, cursor)) = app_state.stdout_selection {
+        if cursor < app_state.stdout_scroll {
+            app_state.stdout_scroll = cursor;
+        } else if cursor >= app_state.stdout_scroll + visible_height {
+            app_state.stdout_scroll = cursor + 1 - visible_height;
+        }
+    }
+
     // Ensure scroll position doesn't exceed max
     app_state.stdout_scroll = app_state.stdout_scroll.min(max_scroll);
 
     let start_idx = app_state.stdout_scroll;
     let end_idx = (start_idx + visible_height).min(total_items);
+    let selected = app_state
+        .stdout_selection
+        .map(|(anchor, cursor)| anchor.min(cursor)..=anchor.max(cursor));
 
     let stdouts: Vec<ListItem> = app_state
         .game_stdout
         .iter()
+        .enumerate()
         .skip(start_idx)
         .take(end_idx - start_idx)
-        .map(|i| {
-            let content = Line::from(Span::raw(i.to_string()));
-            ListItem::new(content)
+        .map(|(index, line)| {
+            let item = output_item(line, app_state.theme);
+            if selected
+                .as_ref()
+                .is_some_and(|range| range.contains(&index))
+            {
+                item.reversed()
+            } else {
+                item
+            }
         })
         .collect();
 
-    let title = Line::from(" Game text output ".bold());
+    let title = match app_state.selected_stdout() {
+        Some(lines) => {
+            Line::from(format!(" Game text output: {} lines selected ", lines.len()).bold())
+        }
+        None => Line::from(" Game text output ".bold()),
+    };
     let block = Block::bordered()
         .title(title.centered())
-        .border_set(border::THICK);
+        .border_set(app_state.theme.border_set());
 
     let stdout = List::new(stdouts).block(block);
     frame.render_stateful_widget(stdout, area, &mut app_state.stdout_state);
@@ -512,7 +1509,7 @@ fn render_fullscreen_game_stdout(frame: &mut Frame, area: Rect, app_state: &mut
     // Add scrollbar integrated into the border
     if total_items > visible_height {
         let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
-            .symbols(scrollbar::VERTICAL)
+            .symbols(app_state.theme.scrollbar_set())
             .begin_symbol(None)
             .track_symbol(None)
             .end_symbol(None);
@@ -555,16 +1552,13 @@ fn render_fullscreen_game_stderr(frame: &mut Frame, area: Rect, app_state: &mut
         .iter()
         .skip(start_idx)
         .take(end_idx - start_idx)
-        .map(|i| {
-            let content = Line::from(Span::raw(i.to_string()));
-            ListItem::new(content)
-        })
+        .map(|line| output_item(line, app_state.theme))
         .collect();
 
     let title = Line::from(" Game errors ".bold());
     let block = Block::bordered()
         .title(title.centered())
-        .border_set(border::THICK);
+        .border_set(app_state.theme.border_set());
 
     let stderr = List::new(stderrs).block(block);
     frame.render_stateful_widget(stderr, area, &mut app_state.stderr_state);
@@ -572,7 +1566,7 @@ fn render_fullscreen_game_stderr(frame: &mut Frame, area: Rect, app_state: &mut
     // Add scrollbar integrated into the border
     if total_items > visible_height {
         let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
-            .symbols(scrollbar::VERTICAL)
+            .symbols(app_state.theme.scrollbar_set())
             .begin_symbol(None)
             .track_symbol(None)
             .end_symbol(None);
@@ -609,71 +1603,49 @@ fn render_normal_view(frame: &mut Frame, area: Rect, app_state: &mut AppState) {
         .constraints(vec![Constraint::Percentage(50), Constraint::Percentage(50)])
         .split(inner_layout[1]);
 
-    render_launcher_log(frame, inner_layout[0], app_state);
+    if app_state.fps.is_empty() {
+        render_launcher_log(frame, inner_layout[0], app_state);
+    } else {
+        let log_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(5), Constraint::Max(FPS_PANEL_HEIGHT)])
+            .split(inner_layout[0]);
+        render_launcher_log(frame, log_layout[0], app_state);
+        render_fps_panel(frame, log_layout[1], app_state);
+    }
     render_game_stdout(frame, game_output_layout[0], app_state);
     render_game_stderr(frame, game_output_layout[1], app_state);
 }
 
+fn render_fps_panel(frame: &mut Frame, area: Rect, app_state: &AppState) {
+    let Some(stats) = app_state.fps.stats() else {
+        return;
+    };
+    let title = format!(
+        "FPS {:.0} · avg {:.0} · min {:.0} · 1% low {:.0}",
+        stats.current, stats.average, stats.min, stats.low_1_percent
+    );
+    let block = Block::bordered()
+        .title(Line::from(title).centered())
+        .border_set(app_state.theme.border_set())
+        .border_style(app_state.theme.focus_border(false));
+    let samples = app_state.fps.recent(area.width.saturating_sub(2) as usize);
+    frame.render_widget(Sparkline::default().data(&samples).block(block), area);
+}
+
 fn render_launcher_log(frame: &mut Frame, area: Rect, app_state: &mut AppState) {
     // Build the list of items for the log
     let mut items: Vec<WListItem> = Vec::new();
 
-    // We'll use entries() from Log which now includes everything
-    items.extend(app_state.log.entries().iter().map(|i| match i {
-        Entry::Text(title_opt, text) => match title_opt {
-            Some(title) => WListItem::with_title(title, text),
-            None => WListItem::new(text),
-        },
-        Entry::Downloand(download) => {
-            WListItem::with_title("Download", format_file_size(download.current()))
-        }
-        Entry::LauncherUpdate(download) => match download.status() {
-            DownloadStatus::InProgress => {
-                if let Some(total) = download.total() {
-                    WListItem::new_gauge(
-                        "Launcher update",
-                        format!(
-                            "{} / {}",
-                            format_file_size(download.current()),
-                            format_file_size(*total)
-                        ),
-                        (download.current() as f64) / (*total as f64),
-                    )
-                } else {
-                    WListItem::with_title("Launcher update", format_file_size(download.current()))
-                }
-            }
-            DownloadStatus::Comple => WListItem::with_title(
-                "Launcher update",
-                format!(
-                    "{} Downloaded. Restart needed.",
-                    format_file_size(download.current())
-                ),
-            ),
-            DownloadStatus::Errored(err) => WListItem::with_title("Launcher update error", err),
-        },
-        Entry::GameDownload(download) => match download.status() {
-            DownloadStatus::InProgress => {
-                if let Some(total) = download.total() {
-                    WListItem::new_gauge(
-                        "Downloading game",
-                        format!(
-                            "{} / {}",
-                            format_file_size(download.current()),
-                            format_file_size(*total)
-                        ),
-                        (download.current() as f64) / (*total as f64),
-                    )
-                } else {
-                    WListItem::with_title("Downloading game", format_file_size(download.current()))
-                }
-            }
-            DownloadStatus::Comple => {
-                WListItem::with_title("Game downloaded", format_file_size(download.current()))
-            }
-            DownloadStatus::Errored(err) => WListItem::with_title("Game download error", err),
-        },
-    }));
+    let theme = app_state.theme;
+    items.extend(
+        app_state
+            .log
+            .entries()
+            .iter()
+            .filter(|entry| entry.is_shown(app_state.min_severity))
+            .map(|entry| entry_item(entry, theme)),
+    );
 
     let builder = ListBuilder::new(|context| {
         let item = items[context.index].clone();
@@ -682,16 +1654,14 @@ fn render_launcher_log(frame: &mut Frame, area: Rect, app_state: &mut AppState)
     });
 
     // Define border style based on focus
-    let launcher_log_border_style = if app_state.focused_log == FocusedLog::LauncherLog {
-        Style::default().fg(Color::Green)
-    } else {
-        Style::default()
-    };
+    let launcher_log_border_style = app_state
+        .theme
+        .focus_border(app_state.focused_log == FocusedLog::LauncherLog);
 
-    let title = Line::from(" Launcher log ".bold());
+    let title = pane_title(FocusedLog::LauncherLog, app_state);
     let block = Block::bordered()
         .title(title.centered())
-        .border_set(border::THICK)
+        .border_set(app_state.theme.border_set())
         .border_style(launcher_log_border_style);
 
     let list = ListView::new(builder, items.len()).block(block);
@@ -715,23 +1685,18 @@ fn render_game_stdout(frame: &mut Frame, area: Rect, app_state: &mut AppState) {
         .iter()
         .skip(start_idx)
         .take(end_idx - start_idx)
-        .map(|i| {
-            let content = Line::from(Span::raw(i.to_string()));
-            ListItem::new(content)
-        })
+        .map(|line| output_item(line, app_state.theme))
         .collect();
 
     // Define border style based on focus
-    let stdout_border_style = if app_state.focused_log == FocusedLog::GameStdout {
-        Style::default().fg(Color::Green)
-    } else {
-        Style::default()
-    };
+    let stdout_border_style = app_state
+        .theme
+        .focus_border(app_state.focused_log == FocusedLog::GameStdout);
 
-    let title = Line::from(" Game text output ".bold());
+    let title = pane_title(FocusedLog::GameStdout, app_state);
     let block = Block::bordered()
         .title(title.centered())
-        .border_set(border::THICK)
+        .border_set(app_state.theme.border_set())
         .border_style(stdout_border_style);
 
     let stdout = List::new(stdouts).block(block);
@@ -740,7 +1705,7 @@ fn render_game_stdout(frame: &mut Frame, area: Rect, app_state: &mut AppState) {
     // Add scrollbar if there's more content than can be displayed
     if total_items > visible_height {
         let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
-            .symbols(scrollbar::VERTICAL)
+            .symbols(app_state.theme.scrollbar_set())
             .begin_symbol(None)
             .track_symbol(None)
             .end_symbol(None);
@@ -779,23 +1744,18 @@ fn render_game_stderr(frame: &mut Frame, area: Rect, app_state: &mut AppState) {
         .iter()
         .skip(start_idx)
         .take(end_idx - start_idx)
-        .map(|i| {
-            let content = Line::from(Span::raw(i.to_string()));
-            ListItem::new(content)
-        })
+        .map(|line| output_item(line, app_state.theme))
         .collect();
 
     // Define border style based on focus
-    let stderr_border_style = if app_state.focused_log == FocusedLog::GameStderr {
-        Style::default().fg(Color::Green)
-    } else {
-        Style::default()
-    };
+    let stderr_border_style = app_state
+        .theme
+        .focus_border(app_state.focused_log == FocusedLog::GameStderr);
 
-    let title = Line::from(" Game errors ".bold());
+    let title = pane_title(FocusedLog::GameStderr, app_state);
     let block = Block::bordered()
         .title(title.centered())
-        .border_set(border::THICK)
+        .border_set(app_state.theme.border_set())
         .border_style(stderr_border_style);
 
     let stderr = List::new(stderrs).block(block);
@@ -804,7 +1764,7 @@ fn render_game_stderr(frame: &mut Frame, area: Rect, app_state: &mut AppState) {
     // Add scrollbar if there's more content than can be displayed
     if total_items > visible_height {
         let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
-            .symbols(scrollbar::VERTICAL)
+            .symbols(app_state.theme.scrollbar_set())
             .begin_symbol(None)
             .track_symbol(None)
             .end_symbol(None);
@@ -826,25 +1786,502 @@ fn render_game_stderr(frame: &mut Frame, area: Rect, app_state: &mut AppState) {
     }
 }
 
-fn render_exit_popup(frame: &mut Frame, area: Rect, app_state: &AppState) {
-    let popup_area = centered_rect(area);
+fn render_update_popup(frame: &mut Frame, area: Rect, app_state: &AppState, choice: UpdateChoice) {
+    let popup_area = centered_rect(area, 40, 7);
+
+    let controls_text = match app_state.input_method {
+        InputMethod::Controller => Line::from(vec![
+            Span::styled(" A", app_state.theme.key(Color::Green)),
+            Span::raw(" - Select    "),
+            Span::styled("B", app_state.theme.key(Color::Red)),
+            Span::raw(" - Later "),
+        ]),
+        InputMethod::Keyboard => Line::from(vec![
+            Span::styled(" Enter", app_state.theme.key(Color::Blue)),
+            Span::raw(" - Select | "),
+            Span::styled("Esc", app_state.theme.key(Color::Blue)),
+            Span::raw(" - Later "),
+        ]),
+    };
+
+    let title = match &app_state.launcher_update_available {
+        Some(version) => format!(" Launcher {version} downloaded "),
+        None => " Launcher update downloaded ".to_string(),
+    };
+
+    let popup_block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(app_state.theme.fg(Color::Yellow))
+        .border_set(app_state.theme.popup_border_set())
+        .title(Line::from(title.bold()).centered())
+        .title_bottom(controls_text.right_aligned());
+
+    // One line per choice, the selected one highlighted
+    let lines: Vec<Line> = UpdateChoice::ALL
+        .iter()
+        .map(|option| {
+            if *option == choice {
+                Line::from(Span::styled(
+                    format!("> {} <", option.label()),
+                    app_state.theme.key(Color::Yellow),
+                ))
+            } else {
+                Line::from(option.label())
+            }
+        })
+        .collect();
+    let popup_text = Paragraph::new(lines).alignment(Alignment::Center);
+
+    let inner_area = popup_area.inner(Margin {
+        vertical: 2,
+        horizontal: 1,
+    });
+
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(popup_block, popup_area);
+    frame.render_widget(popup_text, inner_area);
+}
+
+// Game console prompt along the bottom of the screen
+fn render_actions_menu(
+    frame: &mut Frame,
+    area: Rect,
+    app_state: &AppState,
+    selected: QueuedAction,
+) {
+    let popup_area = centered_rect(area, 56, 6);
+
+    let controls_text = match app_state.input_method {
+        InputMethod::Controller => Line::from(vec![
+            Span::styled(" A", app_state.theme.key(Color::Green)),
+            Span::raw(" - Toggle    "),
+            Span::styled("B", app_state.theme.key(Color::Red)),
+            Span::raw(" - Close "),
+        ]),
+        InputMethod::Keyboard => Line::from(vec![
+            Span::styled(" Enter", app_state.theme.key(Color::Blue)),
+            Span::raw(" - Toggle | "),
+            Span::styled("Esc", app_state.theme.key(Color::Blue)),
+            Span::raw(" - Close "),
+        ]),
+    };
+
+    let popup_block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(app_state.theme.fg(Color::Yellow))
+        .border_set(app_state.theme.popup_border_set())
+        .title(Line::from(" Actions ".bold()).centered())
+        .title_bottom(controls_text.right_aligned());
+
+    let lines: Vec<Line> = QueuedAction::ALL
+        .iter()
+        .map(|action| {
+            let mark = if app_state.queued_actions.contains(action) {
+                "[x]"
+            } else {
+                "[ ]"
+            };
+            let text = format!("{mark} {}", action.label());
+            if *action == selected {
+                Line::from(Span::styled(text, app_state.theme.key(Color::Yellow)))
+            } else {
+                Line::from(text)
+            }
+        })
+        .collect();
+
+    let inner_area = popup_area.inner(Margin {
+        vertical: 2,
+        horizontal: 2,
+    });
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(popup_block, popup_area);
+    frame.render_widget(Paragraph::new(lines), inner_area);
+}
+
+fn render_targets_menu(frame: &mut Frame, area: Rect, app_state: &AppState, selected: usize) {
+    let targets = profile::current().launch_targets();
+    let height = (targets.len() as u16 + 4).min(area.height.saturating_sub(2));
+    let popup_area = centered_rect(area, 56, height);
+
+    let controls_text = match app_state.input_method {
+        InputMethod::Controller => Line::from(vec![
+            Span::styled(" A", app_state.theme.key(Color::Green)),
+            Span::raw(" - Pick    "),
+            Span::styled("B", app_state.theme.key(Color::Red)),
+            Span::raw(" - Close "),
+        ]),
+        InputMethod::Keyboard => Line::from(vec![
+            Span::styled(" Enter", app_state.theme.key(Color::Blue)),
+            Span::raw(" - Pick | "),
+            Span::styled("Esc", app_state.theme.key(Color::Blue)),
+            Span::raw(" - Close "),
+        ]),
+    };
+
+    let popup_block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(app_state.theme.fg(Color::Yellow))
+        .border_set(app_state.theme.popup_border_set())
+        .title(Line::from(" Launch target ".bold()).centered())
+        .title_bottom(controls_text.right_aligned());
+
+    let current = app_state.launch_target.as_deref().unwrap_or(targets[0]);
+    let lines: Vec<Line> = targets
+        .iter()
+        .enumerate()
+        .map(|(index, target)| {
+            let mut text = (*target).to_string();
+            if *target == current {
+                text.push_str("  (current)");
+            }
+            if index == selected {
+                Line::from(Span::styled(
+                    format!("> {text}"),
+                    app_state.theme.key(Color::Yellow),
+                ))
+            } else {
+                Line::from(format!("  {text}"))
+            }
+        })
+        .collect();
+
+    let inner_area = popup_area.inner(Margin {
+        vertical: 2,
+        horizontal: 2,
+    });
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(popup_block, popup_area);
+    frame.render_widget(Paragraph::new(lines), inner_area);
+}
+
+fn render_versions_menu(frame: &mut Frame, area: Rect, app_state: &AppState, menu: &VersionsMenu) {
+    let height = (menu.builds.len() as u16 + 5).min(area.height.saturating_sub(2));
+    let popup_area = centered_rect(area, 64, height);
+
+    let controls_text = match app_state.input_method {
+        InputMethod::Controller => Line::from(vec![
+            Span::styled(" A", app_state.theme.key(Color::Green)),
+            Span::raw(" - Install    "),
+            Span::styled("B", app_state.theme.key(Color::Red)),
+            Span::raw(" - Close "),
+        ]),
+        InputMethod::Keyboard => Line::from(vec![
+            Span::styled(" Enter", app_state.theme.key(Color::Blue)),
+            Span::raw(" - Install | "),
+            Span::styled("Esc", app_state.theme.key(Color::Blue)),
+            Span::raw(" - Close "),
+        ]),
+    };
+
+    let popup_block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(app_state.theme.fg(Color::Yellow))
+        .border_set(app_state.theme.popup_border_set())
+        .title(Line::from(" Game versions ".bold()).centered())
+        .title_bottom(controls_text.right_aligned());
+
+    let follow = if menu.pinned.is_none() {
+        "Latest published build (current)"
+    } else {
+        "Latest published build"
+    };
+    let mut entries = vec![follow.to_string()];
+    entries.extend(menu.builds.iter().map(|build| {
+        let date = chrono::DateTime::from_timestamp(build.downloaded, 0)
+            .map(|date| {
+                date.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_default();
+        let hash = build.hash.get(..12).unwrap_or(&build.hash);
+        let mut text = format!("{hash}  downloaded {date}");
+        if menu.installed.as_ref() == Some(&build.hash) {
+            text.push_str("  installed");
+        }
+        if menu.pinned.as_ref() == Some(&build.hash) {
+            text.push_str(", pinned");
+        }
+        text
+    }));
+    let lines: Vec<Line> = entries
+        .into_iter()
+        .enumerate()
+        .map(|(index, text)| {
+            if index == menu.selected {
+                Line::from(Span::styled(
+                    format!("> {text}"),
+                    app_state.theme.key(Color::Yellow),
+                ))
+            } else {
+                Line::from(format!("  {text}"))
+            }
+        })
+        .collect();
+
+    let inner_area = popup_area.inner(Margin {
+        vertical: 2,
+        horizontal: 2,
+    });
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(popup_block, popup_area);
+    frame.render_widget(Paragraph::new(lines), inner_area);
+}
+
+fn render_stdin_prompt(frame: &mut Frame, area: Rect, app_state: &AppState, line: &str) {
+    let prompt_area = Rect {
+        x: area.x + 2,
+        y: area.bottom().saturating_sub(4),
+        width: area.width.saturating_sub(4),
+        height: 3.min(area.height),
+    };
+
+    let controls_text = Line::from(vec![
+        Span::styled(" Enter", app_state.theme.key(Color::Blue)),
+        Span::raw(" - Send | "),
+        Span::styled("Esc", app_state.theme.key(Color::Blue)),
+        Span::raw(" - Close "),
+    ]);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(app_state.theme.fg(Color::Yellow))
+        .border_set(app_state.theme.popup_border_set())
+        .title(Line::from(" Game console ".bold()))
+        .title_bottom(controls_text.right_aligned());
+
+    let text = format!(
+        "> {}_",
+        tail(line, usize::from(prompt_area.width.saturating_sub(5)))
+    );
+    frame.render_widget(Clear, prompt_area);
+    frame.render_widget(Paragraph::new(text).block(block), prompt_area);
+}
+
+// Last `width` characters of the text, so the cursor end stays in view
+fn tail(text: &str, width: usize) -> String {
+    let skip = text.chars().count().saturating_sub(width);
+    text.chars().skip(skip).collect()
+}
+
+fn render_marker_popup(frame: &mut Frame, area: Rect, app_state: &AppState, name: &str) {
+    let popup_area = centered_rect(area, 40, 3);
+
+    let controls_text = Line::from(vec![
+        Span::styled(" Enter", app_state.theme.key(Color::Blue)),
+        Span::raw(" - Add | "),
+        Span::styled("Esc", app_state.theme.key(Color::Blue)),
+        Span::raw(" - Cancel "),
+    ]);
+
+    let popup_block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(app_state.theme.fg(Color::Yellow))
+        .border_set(app_state.theme.popup_border_set())
+        .title(Line::from(" New marker ".bold()).centered())
+        .title_bottom(controls_text.right_aligned());
+
+    let visible = tail(name, usize::from(popup_area.width.saturating_sub(3)));
+    let popup_text = Paragraph::new(format!("{visible}_"));
+
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(popup_text.block(popup_block), popup_area);
+}
+
+// Event loop measurements in the top right corner
+fn render_perf_overlay(frame: &mut Frame, area: Rect, app_state: &AppState) {
+    let perf = &app_state.perf;
+    let mut lines = vec![
+        Line::from(format!(
+            "draw {:.1}ms, max {:.1}ms",
+            perf.last_draw.as_secs_f64() * 1000.0,
+            perf.max_draw.as_secs_f64() * 1000.0
+        )),
+        Line::from(format!(
+            "queued {} input, {} state, {} progress, {} output",
+            perf.queue_depth[0], perf.queue_depth[1], perf.queue_depth[2], perf.queue_depth[3]
+        )),
+        Line::from(format!(
+            "log buffers {}",
+            format_file_size(app_state.log_buffer_bytes() as u64)
+        )),
+    ];
+    lines.extend(
+        perf.rates
+            .iter()
+            .map(|(kind, rate)| Line::from(format!("{rate:>6}/s {kind}"))),
+    );
+
+    let width = lines
+        .iter()
+        .map(Line::width)
+        .max()
+        .unwrap_or_default()
+        .saturating_add(2) as u16;
+    let height = lines.len() as u16 + 2;
+    let overlay_area = Rect {
+        x: area.right().saturating_sub(width + 1),
+        y: area.y + 1,
+        width: width.min(area.width),
+        height: height.min(area.height),
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(app_state.theme.fg(Color::Magenta))
+        .border_set(app_state.theme.popup_border_set())
+        .title(Line::from(" Performance ".bold()));
+
+    frame.render_widget(Clear, overlay_area);
+    frame.render_widget(Paragraph::new(lines).block(block), overlay_area);
+}
+
+// Wrapped explanation, dismissed with any of the confirm keys
+fn render_notice_popup(frame: &mut Frame, area: Rect, app_state: &AppState, notice: &Notice) {
+    let width = area.width.saturating_sub(4).min(70);
+    let text_width = usize::from(width.saturating_sub(4)).max(1);
+    // Rough height of the wrapped text, paragraphs are separated by empty lines
+    let text_height: usize = notice
+        .text
+        .lines()
+        .map(|line| line.width().div_ceil(text_width).max(1))
+        .sum();
+    let popup_area = centered_rect(area, width, text_height as u16 + 4);
+
+    let controls_text = match app_state.input_method {
+        InputMethod::Controller => Line::from(vec![
+            Span::styled(" A", app_state.theme.key(Color::Green)),
+            Span::raw(" - OK "),
+        ]),
+        InputMethod::Keyboard => Line::from(vec![
+            Span::styled(" Enter", app_state.theme.key(Color::Blue)),
+            Span::raw(" - OK "),
+        ]),
+    };
+    let popup_block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(app_state.theme.fg(Color::Red))
+        .border_set(app_state.theme.popup_border_set())
+        .title(Line::from(format!(" {} ", notice.title).bold()).centered())
+        .title_bottom(controls_text.right_aligned());
+    let popup_text = Paragraph::new(notice.text.as_str()).wrap(Wrap { trim: true });
+
+    let inner_area = popup_area.inner(Margin {
+        vertical: 1,
+        horizontal: 1,
+    });
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(popup_block, popup_area);
+    frame.render_widget(popup_text, inner_area);
+}
+
+fn render_wizard(frame: &mut Frame, area: Rect, app_state: &AppState, wizard: &Wizard) {
+    let width = area.width.saturating_sub(4).min(80);
+    let text_width = usize::from(width.saturating_sub(4)).max(1);
+    let theme = app_state.theme;
+    let mut running = false;
+    let lines: Vec<Line> = wizard
+        .results
+        .iter()
+        .map(|(check, result)| {
+            let (mark, text, style) = match result {
+                Some(Outcome::Passed(text)) => ("[ok]", text.as_str(), theme.fg(Color::Green)),
+                Some(Outcome::Failed(text)) => ("[!!]", text.as_str(), theme.fg(Color::Red)),
+                // Checks run in order, only the first unfinished one is running
+                None if !running => {
+                    running = true;
+                    ("[..]", "checking...", Style::default())
+                }
+                None => ("[  ]", "", Style::default()),
+            };
+            Line::from(vec![
+                Span::styled(format!("{mark} {}", check.label()), style.bold()),
+                Span::styled(format!(" {text}"), style),
+            ])
+        })
+        .collect();
+    let text_height: usize = lines
+        .iter()
+        .map(|line| line.width().div_ceil(text_width).max(1))
+        .sum();
+    let popup_area = centered_rect(area, width, text_height as u16 + 4);
+
+    let controls_text = match app_state.input_method {
+        InputMethod::Controller => Line::from(vec![
+            Span::styled(" B", theme.key(Color::Red)),
+            Span::raw(" - Close "),
+        ]),
+        InputMethod::Keyboard => Line::from(vec![
+            Span::styled(" Esc", theme.key(Color::Blue)),
+            Span::raw(" - Close "),
+        ]),
+    };
+    let popup_block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(theme.fg(Color::Yellow))
+        .border_set(theme.popup_border_set())
+        .title(Line::from(format!(" {} ", wizard.problem.description()).bold()).centered())
+        .title_bottom(controls_text.right_aligned());
+
+    let inner_area = popup_area.inner(Margin {
+        vertical: 1,
+        horizontal: 1,
+    });
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(popup_block, popup_area);
+    frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: true }), inner_area);
+}
+
+fn render_play_button(frame: &mut Frame, area: Rect, app_state: &AppState) {
+    let popup_area = centered_rect(area, 34, 5);
+    let key = match app_state.input_method {
+        InputMethod::Controller => Span::styled("A", app_state.theme.key(Color::Green)),
+        InputMethod::Keyboard => Span::styled("Enter", app_state.theme.key(Color::Blue)),
+    };
+    let text = vec![
+        Line::from(app_state.theme.symbol("▶ PLAY", "PLAY").bold()),
+        Line::from(vec![
+            Span::raw("Ready - press "),
+            key,
+            Span::raw(" to play"),
+        ]),
+    ];
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(app_state.theme.fg(Color::Green))
+        .border_set(app_state.theme.popup_border_set());
+    let inner_area = popup_area.inner(Margin {
+        vertical: 1,
+        horizontal: 1,
+    });
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(block, popup_area);
+    frame.render_widget(
+        Paragraph::new(text).alignment(Alignment::Center),
+        inner_area,
+    );
+}
+
+// Yes/no question with the answer keys in the border
+fn render_confirm_popup(frame: &mut Frame, area: Rect, app_state: &AppState, question: &str) {
+    let popup_area = centered_rect(area, (question.width() as u16 + 4).max(34), 5);
 
     // Controls text to display in the popup
     let controls_text = match app_state.input_method {
         InputMethod::Controller => Line::from(vec![
-            Span::styled(" A", Style::default().fg(Color::Green).bold()),
+            Span::styled(" A", app_state.theme.key(Color::Green)),
             Span::raw(" - Yes    "),
-            Span::styled("B", Style::default().fg(Color::Red).bold()),
+            Span::styled("B", app_state.theme.key(Color::Red)),
             Span::raw(" - No "),
         ]),
         InputMethod::Keyboard => Line::from(vec![
-            Span::styled(" Enter", Style::default().fg(Color::Blue).bold()),
+            Span::styled(" Enter", app_state.theme.key(Color::Blue)),
             Span::raw(" - ("),
-            Span::styled("Y", Style::default().fg(Color::Blue).bold()),
+            Span::styled("Y", app_state.theme.key(Color::Blue)),
             Span::raw(")es | "),
-            Span::styled("Esc", Style::default().fg(Color::Blue).bold()),
+            Span::styled("Esc", app_state.theme.key(Color::Blue)),
             Span::raw(" - ("),
-            Span::styled("N", Style::default().fg(Color::Blue).bold()),
+            Span::styled("N", app_state.theme.key(Color::Blue)),
             Span::raw(")o "),
         ]),
     };
@@ -852,11 +2289,11 @@ fn render_exit_popup(frame: &mut Frame, area: Rect, app_state: &AppState) {
     // Create a popup with no title and controls in the border
     let popup_block = Block::default()
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Yellow))
-        .border_type(BorderType::Rounded)
+        .border_style(app_state.theme.fg(Color::Yellow))
+        .border_set(app_state.theme.popup_border_set())
         .title_bottom(controls_text.right_aligned());
 
-    let popup_text = Paragraph::new("Are you sure you want to exit?")
+    let popup_text = Paragraph::new(question)
         .alignment(Alignment::Center)
         .style(Style::default());
 
@@ -881,16 +2318,12 @@ fn render_exit_popup(frame: &mut Frame, area: Rect, app_state: &AppState) {
 }
 
 // Helper function to create a centered rectangle of the given size
-fn centered_rect(r: Rect) -> Rect {
-    // Minimum dimensions to ensure popup content is visible
-    const MIN_WIDTH: u16 = 34;
-    const MIN_HEIGHT: u16 = 5;
-
+fn centered_rect(r: Rect, width: u16, height: u16) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Min(0),
-            Constraint::Length(MIN_HEIGHT),
+            Constraint::Length(height),
             Constraint::Min(0),
         ])
         .split(r);
@@ -899,7 +2332,7 @@ fn centered_rect(r: Rect) -> Rect {
         .direction(Direction::Horizontal)
         .constraints([
             Constraint::Min(0),
-            Constraint::Length(MIN_WIDTH),
+            Constraint::Length(width),
             Constraint::Min(0),
         ])
         .split(popup_layout[1])[1]
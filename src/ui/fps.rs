@@ -0,0 +1,100 @@
+use std::collections::VecDeque;
+
+/// Samples kept for the graph and the statistics
+const MAX_SAMPLES: usize = 600;
+
+/// Frame rate the game reports with `PERF fps=73 frame_ms=13.7` lines
+#[derive(Default)]
+pub struct FpsHistory {
+    samples: VecDeque<f64>,
+}
+
+/// Summary of the recent samples
+#[derive(Debug, PartialEq)]
+pub struct FpsStats {
+    pub current: f64,
+    pub average: f64,
+    pub min: f64,
+    /// Average of the slowest 1% of samples
+    pub low_1_percent: f64,
+}
+
+impl FpsHistory {
+    /// Record the frame rate of a game output line, if it's a PERF line
+    pub fn record_line(&mut self, line: &str) {
+        if let Some(fps) = parse_perf_line(line) {
+            if self.samples.len() == MAX_SAMPLES {
+                self.samples.pop_front();
+            }
+            self.samples.push_back(fps);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// The last `count` samples rounded for a sparkline, oldest first
+    pub fn recent(&self, count: usize) -> Vec<u64> {
+        let skip = self.samples.len().saturating_sub(count);
+        self.samples
+            .iter()
+            .skip(skip)
+            .map(|fps| fps.round() as u64)
+            .collect()
+    }
+
+    pub fn stats(&self) -> Option<FpsStats> {
+        let current = *self.samples.back()?;
+        let mut sorted: Vec<f64> = self.samples.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        let slowest = &sorted[..sorted.len().div_ceil(100)];
+        Some(FpsStats {
+            current,
+            average: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            min: sorted[0],
+            low_1_percent: slowest.iter().sum::<f64>() / slowest.len() as f64,
+        })
+    }
+}
+
+/// Frame rate of a `PERF` line, from `fps=` or else from `frame_ms=`
+fn parse_perf_line(line: &str) -> Option<f64> {
+    let fields = line.trim().strip_prefix("PERF ")?;
+    let field = |name: &str| {
+        fields
+            .split_whitespace()
+            .find_map(|field| field.strip_prefix(name)?.strip_prefix('='))
+            .and_then(|value| value.parse::<f64>().ok())
+            .filter(|value| value.is_finite() && *value > 0.0)
+    };
+    field("fps").or_else(|| field("frame_ms").map(|ms| 1000.0 / ms))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_perf_lines_give_fps_stats() {
+        assert_eq!(parse_perf_line("PERF fps=73 frame_ms=13.7"), Some(73.0));
+        assert_eq!(parse_perf_line("PERF frame_ms=20"), Some(50.0));
+        assert_eq!(parse_perf_line("Loaded level fps=73"), None);
+
+        let mut history = FpsHistory::default();
+        for fps in [60, 30, 90] {
+            history.record_line(&format!("PERF fps={fps}"));
+        }
+        history.record_line("not a perf line");
+        assert_eq!(
+            history.stats(),
+            Some(FpsStats {
+                current: 90.0,
+                average: 60.0,
+                min: 30.0,
+                low_1_percent: 30.0,
+            })
+        );
+        assert_eq!(history.recent(2), [30, 90]);
+    }
+}
@@ -1,19 +1,24 @@
-use ratatui::{prelude::*, style::Style, symbols, widgets::LineGauge};
+use ratatui::{prelude::*, style::Style, widgets::LineGauge};
 use tui_widget_list::{ListBuilder, ListState, ListView};
 use unicode_width::UnicodeWidthStr;
 
+use crate::ui::theme::{GaugeStyle, Theme};
+
 #[derive(Debug, Clone)]
 pub struct ListItem {
     pub title: Option<String>,
     pub text: String,
     pub style: Style,
     pub item_type: ItemType,
+    pub theme: Theme,
 }
 
 #[derive(Debug, Clone)]
 pub enum ItemType {
     Gauge(f64),
     Text,
+    /// Text with the marked characters highlighted
+    Highlighted(Vec<bool>),
 }
 
 impl ListItem {
@@ -23,6 +28,7 @@ impl ListItem {
             text: text.into(),
             style: Style::default(),
             item_type: ItemType::Text,
+            theme: Theme::default(),
         }
     }
 
@@ -32,6 +38,7 @@ impl ListItem {
             text: text.into(),
             style: Style::default(),
             item_type: ItemType::Text,
+            theme: Theme::default(),
         }
     }
 
@@ -41,8 +48,33 @@ impl ListItem {
             text: text.into(),
             style: Style::default(),
             item_type: ItemType::Gauge(ratio),
+            theme: Theme::default(),
+        }
+    }
+
+    pub fn highlighted<T: Into<String>, U: Into<String>>(
+        title: T,
+        text: U,
+        marks: Vec<bool>,
+    ) -> Self {
+        Self {
+            title: Some(title.into()),
+            text: text.into(),
+            style: Style::default(),
+            item_type: ItemType::Highlighted(marks),
+            theme: Theme::default(),
         }
     }
+
+    pub const fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub const fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
 }
 
 impl Widget for ListItem {
@@ -66,6 +98,26 @@ impl Widget for ListItem {
                 }
                 line.render(area, buf);
             }
+            ItemType::Highlighted(marks) => {
+                let marked = if self.theme.color {
+                    self.style.fg(Color::Yellow).bold()
+                } else {
+                    self.style.reversed()
+                };
+                let mut spans = Vec::new();
+                if let Some(title) = self.title {
+                    spans.push(Span::styled(format!("{title}: "), self.style.bold()));
+                }
+                for (index, ch) in self.text.chars().enumerate() {
+                    let style = if marks.get(index).copied().unwrap_or(false) {
+                        marked
+                    } else {
+                        self.style
+                    };
+                    spans.push(Span::styled(ch.to_string(), style));
+                }
+                Line::from(spans).render(area, buf);
+            }
             ItemType::Gauge(ratio) => {
                 let title = match self.title {
                     Some(t) => format!("{t}: "),
@@ -90,11 +142,23 @@ impl Widget for ListItem {
                         .style(self.style.bold())
                         .render(line_layout[0], buf);
                 }
-                LineGauge::default()
-                    .filled_style(self.style.fg(Color::Black).bg(Color::White))
-                    .line_set(symbols::line::THICK)
-                    .ratio(ratio)
-                    .render(line_layout[1], buf);
+                let theme = self.theme;
+                match theme.gauge {
+                    GaugeStyle::Line => LineGauge::default()
+                        .filled_style(if theme.color {
+                            self.style.fg(Color::Black).bg(Color::White)
+                        } else {
+                            self.style.reversed()
+                        })
+                        .line_set(theme.line_set())
+                        .ratio(ratio)
+                        .render(line_layout[1], buf),
+                    style => {
+                        Line::from(text_bar(ratio, line_layout[1].width, style))
+                            .style(self.style)
+                            .render(line_layout[1], buf);
+                    }
+                }
                 if !self.text.is_empty() {
                     Line::from(self.text)
                         .style(self.style)
@@ -105,6 +169,32 @@ impl Widget for ListItem {
     }
 }
 
+// Progress bar drawn with characters, filling `width` cells.
+// Partial glyphs give sub-cell precision to the styles that have them
+fn text_bar(ratio: f64, width: u16, style: GaugeStyle) -> String {
+    let (open, close, full, partial, empty): (&str, &str, &str, &[&str], &str) = match style {
+        GaugeStyle::Blocks => ("", " ", "█", &["▏", "▎", "▍", "▌", "▋", "▊", "▉"], "░"),
+        GaugeStyle::Braille => ("", " ", "⣿", &["⡀", "⡄", "⡆", "⡇", "⣇", "⣧", "⣷"], " "),
+        GaugeStyle::Ascii | GaugeStyle::Line => ("[", "] ", "#", &[], " "),
+    };
+    // Brackets are ASCII, so their byte length is their width
+    let cells = usize::from(width).saturating_sub(open.len() + close.len());
+    let steps = partial.len() + 1;
+    let filled = (ratio.clamp(0.0, 1.0) * (cells * steps) as f64).round() as usize;
+    let (full_cells, rest) = (filled / steps, filled % steps);
+
+    let mut bar = String::from(open);
+    bar.push_str(&full.repeat(full_cells));
+    let mut used = full_cells;
+    if rest > 0 {
+        bar.push_str(partial[rest - 1]);
+        used += 1;
+    }
+    bar.push_str(&empty.repeat(cells - used));
+    bar.push_str(close);
+    bar
+}
+
 pub struct App {
     state: ListState,
 }
@@ -148,3 +238,15 @@ impl Widget for &mut App {
         list.render(area, buf, state);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_bar_fills_width() {
+        assert_eq!(text_bar(0.5, 8, GaugeStyle::Ascii), "[###  ] ");
+        assert_eq!(text_bar(0.5, 4, GaugeStyle::Blocks), "█▌░ ");
+        assert_eq!(text_bar(1.0, 3, GaugeStyle::Braille), "⣿⣿ ");
+    }
+}
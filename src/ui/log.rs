@@ -1,10 +1,24 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Speed is measured over this much of the latest progress, so it follows
+/// changes of the connection instead of averaging the whole download
+const SPEED_WINDOW: Duration = Duration::from_secs(10);
+
 pub struct Log {
     pub local_hash_msg: Option<String>,
     pub remote_hash_msg: Option<String>,
     pub launcher_status_msg: Option<String>,
+    pub monthly_usage_msg: Option<String>,
+    pub connection_msg: Option<String>,
+    pub latency_msg: Option<String>,
+    pub usage_msg: Option<String>,
     pub game_download: Option<Download>,
     pub launcher_update: Option<Download>,
-    pub extra_log: Vec<String>,
+    pub extra_log: Vec<Entry>,
 }
 
 impl Log {
@@ -13,6 +27,10 @@ impl Log {
             local_hash_msg: None,
             remote_hash_msg: None,
             launcher_status_msg: None,
+            monthly_usage_msg: None,
+            connection_msg: None,
+            latency_msg: None,
+            usage_msg: None,
             game_download: None,
             launcher_update: None,
             extra_log: Vec::new(),
@@ -21,31 +39,34 @@ impl Log {
 
     // Add a titled entry to the log
     pub fn add_titled<T: Into<String>, U: Into<String>>(&mut self, title: T, text: U) {
-        self.push(Entry::titled_text(title, text));
+        self.push(Record::new(Severity::Info, Some(title.into()), text));
     }
 
     // Add a simple text entry to the log
     pub fn add_text<T: Into<String>>(&mut self, text: T) {
-        self.push_text(text.into());
+        self.push(Record::new(Severity::Info, None, text));
     }
 
-    fn push(&mut self, entry: Entry) {
-        // Store the raw string in extra_log
-        match entry {
-            Entry::Text(Some(title), text) => {
-                self.extra_log.push(format!("{title}: {text}"));
-            }
-            Entry::Text(None, text) => {
-                self.extra_log.push(text);
-            }
-            // Other entry types shouldn't go into extra_log directly
-            _ => {}
-        }
+    pub fn add_success<T: Into<String>, U: Into<String>>(&mut self, title: T, text: U) {
+        self.push(Record::new(Severity::Success, Some(title.into()), text));
+    }
+
+    pub fn add_warning<T: Into<String>, U: Into<String>>(&mut self, title: T, text: U) {
+        self.push(Record::new(Severity::Warn, Some(title.into()), text));
+    }
+
+    pub fn add_error<T: Into<String>, U: Into<String>>(&mut self, title: T, text: U) {
+        self.push(Record::new(Severity::Error, Some(title.into()), text));
     }
 
-    // Add a convenience method for pushing simple text
-    fn push_text(&mut self, text: String) {
-        self.extra_log.push(text);
+    /// Add a named divider, e.g. "started repro attempt 2"
+    pub fn add_marker<T: Into<String>>(&mut self, name: T) {
+        self.extra_log
+            .push(Entry::Marker(Record::new(Severity::Info, None, name)));
+    }
+
+    fn push(&mut self, record: Record) {
+        self.extra_log.push(Entry::Text(record));
     }
 
     pub fn entries(&self) -> Vec<Entry> {
@@ -63,11 +84,30 @@ impl Log {
         }
 
         // Add hash information
-        if let Some(remote_hash) = &self.remote_hash_msg {
-            accumulator.push(Entry::titled_text("Remote hash", remote_hash.clone()));
+        match (&self.remote_hash_msg, &self.local_hash_msg) {
+            (Some(remote), Some(local)) if is_hash(remote) && is_hash(local) && remote != local => {
+                accumulator.extend(hash_diff(remote, local));
+            }
+            (remote_hash, local_hash) => {
+                if let Some(remote_hash) = remote_hash {
+                    accumulator.push(Entry::titled_text("Remote hash", remote_hash.clone()));
+                }
+                if let Some(local_hash) = local_hash {
+                    accumulator.push(Entry::titled_text("Local hash", local_hash.clone()));
+                }
+            }
+        }
+        if let Some(connection) = &self.connection_msg {
+            accumulator.push(Entry::titled_text("Connection", connection.clone()));
         }
-        if let Some(local_hash) = &self.local_hash_msg {
-            accumulator.push(Entry::titled_text("Local hash", local_hash.clone()));
+        if let Some(latency) = &self.latency_msg {
+            accumulator.push(Entry::titled_text("Game server", latency.clone()));
+        }
+        if let Some(usage) = &self.usage_msg {
+            accumulator.push(Entry::titled_text("Game resources", usage.clone()));
+        }
+        if let Some(usage) = &self.monthly_usage_msg {
+            accumulator.push(Entry::titled_text("Downloaded this month", usage.clone()));
         }
 
         // Add game download status if present
@@ -77,14 +117,18 @@ impl Log {
         }
 
         // Add all other log entries
-        let extra_log_clone = self.extra_log.clone();
-        accumulator.append(&mut extra_log_clone.iter().map(Entry::text).collect());
+        accumulator.extend(self.extra_log.iter().cloned());
         accumulator
     }
     pub fn start_download(&mut self, total: Option<u64>) {
         self.game_download = Some(Download::new(total));
     }
-    pub const fn set_download_progress(&mut self, downloaded: u64) {
+    pub fn resume_download(&mut self, offset: u64) {
+        if let Some(download) = &mut self.game_download {
+            download.resume_from(offset);
+        }
+    }
+    pub fn set_download_progress(&mut self, downloaded: u64) {
         if let Some(download) = &mut self.game_download {
             download.set_progress(downloaded);
         }
@@ -101,25 +145,110 @@ impl Log {
     }
 }
 
-pub enum Entry {
-    Text(Option<String>, String), // Optional title, text content
-    Downloand(Download),
-    LauncherUpdate(Download),
-    GameDownload(Download),
+// The hash messages also hold states like "accessing"
+fn is_hash(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(|ch| ch.is_ascii_hexdigit())
+}
+
+/// Remote and local hash lines aligned under each other, with the characters
+/// that differ marked
+fn hash_diff(remote: &str, local: &str) -> [Entry; 2] {
+    let line = |title: &str, hash: &str, other: &str| {
+        // "Local hash" is one character shorter than "Remote hash"
+        let indent = "Remote hash".len() - title.len();
+        let mut marks = vec![false; indent];
+        let mut other = other.chars();
+        marks.extend(hash.chars().map(|ch| other.next() != Some(ch)));
+        Entry::HashDiff {
+            title: title.to_string(),
+            text: format!("{}{hash}", " ".repeat(indent)),
+            marks,
+        }
+    };
+    [
+        line("Remote hash", remote, local),
+        line("Local hash", local, remote),
+    ]
+}
+
+/// A line of game output, or a marker dropped into it
+#[derive(Debug, Clone)]
+pub enum OutputLine {
+    Text(String),
+    Marker(String),
+}
+
+impl fmt::Display for OutputLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text(text) => f.write_str(text),
+            Self::Marker(name) => write!(f, "{}", marker_line(name)),
+        }
+    }
+}
+
+/// Marker as a plain text divider, as it is written to files
+pub fn marker_line(name: &str) -> String {
+    format!("==== {name} ====")
+}
+
+/// How important a log entry is, ordered from the least to the most
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Severity {
+    Info,
+    Success,
+    Warn,
+    Error,
 }
 
-impl From<String> for Entry {
-    fn from(text: String) -> Self {
-        Self::Text(None, text)
+impl Severity {
+    /// Next minimum severity for the launcher log filter
+    pub const fn next_filter(self) -> Self {
+        match self {
+            Self::Info => Self::Warn,
+            Self::Success | Self::Warn => Self::Error,
+            Self::Error => Self::Info,
+        }
     }
 }
 
-impl From<&String> for Entry {
-    fn from(text: &String) -> Self {
-        Self::Text(None, text.clone())
+/// A text entry of the launcher log
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub title: Option<String>,
+    pub text: String,
+    pub severity: Severity,
+    /// When the entry was added, status lines that are updated in place have none
+    pub time: Option<DateTime<Local>>,
+}
+
+impl Record {
+    fn new<T: Into<String>>(severity: Severity, title: Option<String>, text: T) -> Self {
+        Self {
+            title,
+            text: text.into(),
+            severity,
+            time: Some(Local::now()),
+        }
     }
 }
 
+#[derive(Clone)]
+pub enum Entry {
+    Text(Record),
+    /// Divider dropped by the player to find their way in long sessions
+    Marker(Record),
+    Downloand(Download),
+    LauncherUpdate(Download),
+    GameDownload(Download),
+    /// A hash with the characters that differ from the other build's marked
+    HashDiff {
+        title: String,
+        text: String,
+        marks: Vec<bool>,
+    },
+}
+
 impl From<Download> for Entry {
     fn from(download: Download) -> Self {
         Self::Downloand(download)
@@ -128,12 +257,33 @@ impl From<Download> for Entry {
 
 // Helper functions for creating entries
 impl Entry {
+    // Status line without a timestamp
     pub fn titled_text<T: Into<String>, U: Into<String>>(title: T, text: U) -> Self {
-        Self::Text(Some(title.into()), text.into())
+        Self::Text(Record {
+            title: Some(title.into()),
+            text: text.into(),
+            severity: Severity::Info,
+            time: None,
+        })
     }
 
-    pub fn text<T: Into<String>>(text: T) -> Self {
-        Self::Text(None, text.into())
+    /// Markers are shown whatever the severity filter is
+    pub fn is_shown(&self, min_severity: Severity) -> bool {
+        matches!(self, Self::Marker(_)) || self.severity() >= min_severity
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::Text(record) | Self::Marker(record) => record.severity,
+            Self::HashDiff { .. } => Severity::Info,
+            Self::Downloand(download)
+            | Self::LauncherUpdate(download)
+            | Self::GameDownload(download) => match download.status() {
+                DownloadStatus::InProgress | DownloadStatus::Verifying => Severity::Info,
+                DownloadStatus::Comple => Severity::Success,
+                DownloadStatus::Errored(_) => Severity::Error,
+            },
+        }
     }
 }
 
@@ -141,24 +291,57 @@ impl Entry {
 pub struct Download {
     pub total: Option<u64>,
     pub current: u64,
+    /// Bytes already there from an interrupted download
+    pub resumed_from: u64,
     pub status: DownloadStatus,
+    /// Progress with the time it was reported, the oldest is from just
+    /// before the speed window
+    samples: VecDeque<(Instant, u64)>,
 }
 
 #[derive(Clone)]
 pub enum DownloadStatus {
     InProgress,
+    Verifying,
     Comple,
     Errored(String),
 }
 
 impl Download {
     // Create a new Download with the given total size
-    pub const fn new(total: Option<u64>) -> Self {
+    pub fn new(total: Option<u64>) -> Self {
         Self {
             total,
             current: 0,
+            resumed_from: 0,
             status: DownloadStatus::InProgress,
+            samples: VecDeque::from([(Instant::now(), 0)]),
+        }
+    }
+
+    // Speed in bytes per second over the last few seconds
+    pub fn speed_bps(&self) -> Option<u64> {
+        self.speed_at(Instant::now())
+    }
+
+    fn speed_at(&self, now: Instant) -> Option<u64> {
+        let (since, from) = *self.samples.front()?;
+        let elapsed = now.saturating_duration_since(since).as_secs_f64();
+        let transferred = self.current.saturating_sub(from);
+        if elapsed < 1.0 || transferred == 0 {
+            return None;
+        }
+        Some((transferred as f64 / elapsed) as u64)
+    }
+
+    // Estimated time until the download completes
+    pub fn eta(&self) -> Option<Duration> {
+        let remaining = self.total?.saturating_sub(self.current);
+        let speed = self.speed_bps()?;
+        if speed == 0 {
+            return None;
         }
+        Some(Duration::from_secs(remaining / speed))
     }
 
     pub const fn current(&self) -> u64 {
@@ -171,14 +354,42 @@ impl Download {
         &self.total
     }
 
-    pub const fn set_progress(&mut self, current: u64) {
+    // Progress reports can be received after the download finished,
+    // the count never goes back
+    pub fn set_progress(&mut self, current: u64) {
+        self.progress_at(Instant::now(), current);
+    }
+
+    fn progress_at(&mut self, now: Instant, current: u64) {
+        if current <= self.current {
+            return;
+        }
         self.current = current;
+        self.samples.push_back((now, current));
+        while self
+            .samples
+            .get(1)
+            .is_some_and(|(at, _)| now.saturating_duration_since(*at) >= SPEED_WINDOW)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    // The resumed bytes weren't transferred now, they don't count for the speed
+    pub fn resume_from(&mut self, offset: u64) {
+        self.resumed_from = offset;
+        self.current = self.current.max(offset);
+        self.samples = VecDeque::from([(Instant::now(), self.current)]);
     }
 
     pub const fn set_total(&mut self, total: Option<u64>) {
         self.total = total;
     }
 
+    pub fn mark_verifying(&mut self) {
+        self.status = DownloadStatus::Verifying;
+    }
+
     pub fn mark_complete(&mut self) {
         self.status = DownloadStatus::Comple;
     }
@@ -187,3 +398,25 @@ impl Download {
         self.status = DownloadStatus::Errored(error);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_speed_follows_the_latest_progress() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut download = Download::new(Some(1000));
+        download.samples = VecDeque::from([(start, 0)]);
+        assert_eq!(download.speed_at(start), None);
+
+        // Slow at first, then 50 bytes a second
+        download.progress_at(at(10), 10);
+        download.progress_at(at(20), 20);
+        download.progress_at(at(30), 520);
+        assert_eq!(download.speed_at(at(30)), Some(50));
+        // A stall slows it down until progress comes again
+        assert_eq!(download.speed_at(at(40)), Some(25));
+    }
+}
@@ -0,0 +1,52 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// Event loop measurements for the debug overlay, to find where the UI stutters
+pub struct PerfStats {
+    window_start: Instant,
+    counting: BTreeMap<&'static str, u32>,
+    /// Events per second by kind, over the last full second
+    pub rates: BTreeMap<&'static str, u32>,
+    pub last_draw: Duration,
+    /// Slowest draw over the last full second
+    pub max_draw: Duration,
+    slowest_in_window: Duration,
+    /// Events waiting in the channel by priority, when last checked
+    pub queue_depth: [usize; 4],
+}
+
+impl Default for PerfStats {
+    fn default() -> Self {
+        Self {
+            window_start: Instant::now(),
+            counting: BTreeMap::new(),
+            rates: BTreeMap::new(),
+            last_draw: Duration::ZERO,
+            max_draw: Duration::ZERO,
+            slowest_in_window: Duration::ZERO,
+            queue_depth: [0; 4],
+        }
+    }
+}
+
+impl PerfStats {
+    pub fn record_event(&mut self, kind: &'static str) {
+        self.roll_window();
+        *self.counting.entry(kind).or_default() += 1;
+    }
+
+    pub fn record_draw(&mut self, took: Duration) {
+        self.roll_window();
+        self.last_draw = took;
+        self.slowest_in_window = self.slowest_in_window.max(took);
+    }
+
+    fn roll_window(&mut self) {
+        if self.window_start.elapsed() < Duration::from_secs(1) {
+            return;
+        }
+        self.rates = std::mem::take(&mut self.counting);
+        self.max_draw = std::mem::take(&mut self.slowest_in_window);
+        self.window_start = Instant::now();
+    }
+}
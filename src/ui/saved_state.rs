@@ -0,0 +1,104 @@
+use color_eyre::Result;
+use eyre::WrapErr;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::dirs;
+use crate::ui::log::Severity;
+use crate::ui::{AppState, DisplayMode, FocusedLog};
+
+const UI_STATE: &str = "ui-state.json";
+
+/// The parts of the UI a user sets up for debugging, kept in
+/// `$XDG_STATE_HOME/GRAV` so they survive a restart of the launcher
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedState {
+    focused_log: FocusedLog,
+    fullscreen: bool,
+    min_severity: Severity,
+    show_previous_session: bool,
+    stdout_scroll: usize,
+    stderr_scroll: usize,
+    previous_session_scroll: usize,
+    launch_target: Option<String>,
+}
+
+impl Default for SavedState {
+    fn default() -> Self {
+        Self::from(&AppState::init())
+    }
+}
+
+impl From<&AppState> for SavedState {
+    fn from(app_state: &AppState) -> Self {
+        Self {
+            focused_log: app_state.focused_log,
+            fullscreen: app_state.display_mode != DisplayMode::Normal,
+            min_severity: app_state.min_severity,
+            show_previous_session: app_state.show_previous_session,
+            stdout_scroll: app_state.stdout_scroll,
+            stderr_scroll: app_state.stderr_scroll,
+            previous_session_scroll: app_state.previous_session_scroll,
+            launch_target: app_state.launch_target.clone(),
+        }
+    }
+}
+
+impl SavedState {
+    /// Scroll positions past the end are clamped when the panes are drawn
+    pub fn restore(self, app_state: &mut AppState) {
+        app_state.focused_log = self.focused_log;
+        if self.fullscreen {
+            app_state.enter_fullscreen(20); // Updated in draw
+        }
+        app_state.min_severity = self.min_severity;
+        app_state.show_previous_session = self.show_previous_session;
+        app_state.stdout_scroll = self.stdout_scroll;
+        app_state.stderr_scroll = self.stderr_scroll;
+        app_state.previous_session_scroll = self.previous_session_scroll;
+        app_state.launch_target = self.launch_target;
+    }
+}
+
+fn state_path() -> Result<std::path::PathBuf> {
+    dirs::base()
+        .place_state_file(UI_STATE)
+        .wrap_err("Can't create UI state path")
+}
+
+/// The state saved by the last run, if there is a readable one
+pub fn load() -> Option<SavedState> {
+    let saved = fs::read_to_string(state_path().ok()?).ok()?;
+    serde_json::from_str(&saved).ok()
+}
+
+pub fn save(app_state: &AppState) -> Result<()> {
+    let path = state_path()?;
+    let saved = serde_json::to_string_pretty(&SavedState::from(app_state))?;
+    fs::write(&path, saved).wrap_err_with(|| format!("Failed to write {path:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_saved_state_round_trips_and_fills_missing_fields() {
+        let mut app_state = AppState::init();
+        app_state.focused_log = FocusedLog::GameStderr;
+        app_state.min_severity = Severity::Warn;
+        app_state.stderr_scroll = 42;
+        let json = serde_json::to_string(&SavedState::from(&app_state)).unwrap();
+
+        let mut restored = AppState::init();
+        serde_json::from_str::<SavedState>(&json)
+            .unwrap()
+            .restore(&mut restored);
+        assert_eq!(SavedState::from(&restored), SavedState::from(&app_state));
+
+        let partial: SavedState = serde_json::from_str(r#"{"fullscreen":true}"#).unwrap();
+        assert!(partial.fullscreen);
+        assert_eq!(partial.focused_log, FocusedLog::LauncherLog);
+    }
+}
@@ -0,0 +1,159 @@
+use clap::ValueEnum;
+use ratatui::{
+    style::{Color, Style, Stylize},
+    symbols::{border, line, scrollbar},
+};
+use std::env;
+
+/// How download progress bars are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GaugeStyle {
+    /// Unicode line gauge
+    Line,
+    /// Plain `[####    ]` bar for terminals without unicode
+    Ascii,
+    /// Unicode block elements with eighth-cell precision
+    Blocks,
+    /// Braille dots, a thin bar with quarter-cell precision
+    Braille,
+}
+
+const ASCII_BORDER: border::Set = border::Set {
+    top_left: "+",
+    top_right: "+",
+    bottom_left: "+",
+    bottom_right: "+",
+    vertical_left: "|",
+    vertical_right: "|",
+    horizontal_top: "-",
+    horizontal_bottom: "-",
+};
+
+const ASCII_SCROLLBAR: scrollbar::Set = scrollbar::Set {
+    track: "|",
+    thumb: "#",
+    begin: "^",
+    end: "v",
+};
+
+/// Rendering capabilities of the terminal, all styled rendering goes through it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// Box drawing characters and other non-ASCII symbols render correctly
+    pub unicode: bool,
+    /// Colors are available. Only the 16 basic ANSI colors are used
+    pub color: bool,
+    pub gauge: GaugeStyle,
+}
+
+impl Default for Theme {
+    /// A fully capable terminal
+    fn default() -> Self {
+        Self {
+            unicode: true,
+            color: true,
+            gauge: GaugeStyle::Line,
+        }
+    }
+}
+
+impl Theme {
+    /// Guess the terminal capabilities from TERM, NO_COLOR and the locale.
+    /// The Linux console and dumb terminals get ASCII borders and plain gauges
+    pub fn detect() -> Self {
+        let term = env::var("TERM").unwrap_or_default();
+        let dumb = term.is_empty() || term == "dumb";
+        let console = term == "linux" || term.starts_with("vt");
+        let unicode = utf8_locale() && !dumb && !console;
+
+        Self {
+            unicode,
+            // https://no-color.org: any non-empty value disables colors
+            color: !dumb && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()),
+            gauge: if unicode {
+                GaugeStyle::Line
+            } else {
+                GaugeStyle::Ascii
+            },
+        }
+    }
+
+    /// Same capabilities without colors, emphasis is kept
+    pub const fn monochrome(self) -> Self {
+        Self {
+            color: false,
+            ..self
+        }
+    }
+
+    pub const fn border_set(&self) -> border::Set {
+        if self.unicode {
+            border::THICK
+        } else {
+            ASCII_BORDER
+        }
+    }
+
+    pub const fn popup_border_set(&self) -> border::Set {
+        if self.unicode {
+            border::ROUNDED
+        } else {
+            ASCII_BORDER
+        }
+    }
+
+    pub const fn scrollbar_set(&self) -> scrollbar::Set {
+        if self.unicode {
+            scrollbar::VERTICAL
+        } else {
+            ASCII_SCROLLBAR
+        }
+    }
+
+    pub const fn line_set(&self) -> line::Set {
+        if self.unicode {
+            line::THICK
+        } else {
+            line::NORMAL
+        }
+    }
+
+    /// Foreground color, dropped when colors aren't available
+    pub fn fg(&self, color: Color) -> Style {
+        if self.color {
+            Style::default().fg(color)
+        } else {
+            Style::default()
+        }
+    }
+
+    /// Style of a key or button name in control hints
+    pub fn key(&self, color: Color) -> Style {
+        self.fg(color).bold()
+    }
+
+    /// Border style of a pane, highlighting the focused one
+    pub fn focus_border(&self, focused: bool) -> Style {
+        match (focused, self.color) {
+            (false, _) => Style::default(),
+            (true, true) => Style::default().fg(Color::Green),
+            (true, false) => Style::default().bold(),
+        }
+    }
+
+    /// Unicode symbol, or its ASCII replacement
+    pub const fn symbol(&self, unicode: &'static str, ascii: &'static str) -> &'static str {
+        if self.unicode { unicode } else { ascii }
+    }
+}
+
+fn utf8_locale() -> bool {
+    // The first non-empty variable wins, as in setlocale(3)
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|name| env::var(name).ok().filter(|value| !value.is_empty()))
+        .is_some_and(|locale| {
+            let locale = locale.to_ascii_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        })
+}
@@ -1,34 +1,77 @@
+pub mod provider;
+
+use provider::Release;
+
 use color_eyre::{Result, eyre::eyre};
 use eyre::WrapErr;
 use std::env;
-use std::fs::{self, File};
-use std::io::{Read, Write};
-use std::os::unix::fs::PermissionsExt;
-use std::sync::mpsc;
-
-use crate::REPOSITORY;
-use crate::event::Event;
-
-/// The GitHub API endpoint for retrieving the latest release
-fn github_api_releases_url() -> String {
-    // Extract the repository owner and name from the full repository URL
-    // Expected format: "https://github.com/owner/repo"
-    let path = REPOSITORY.trim_start_matches("https://github.com/");
-    format!("https://api.github.com/repos/{path}/releases/latest")
+use std::ffi::CString;
+use std::fs;
+use std::io;
+use std::os::unix::ffi::OsStringExt;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::info_span;
+
+use crate::bus;
+use crate::downloads::{self, Job, JobKind, Progress};
+use crate::elf;
+use crate::event::UpdateEvent;
+use crate::file_mode;
+use crate::hash;
+
+/// Set by distribution packages that update the launcher themselves
+const DISABLE_SELFUPDATE_VAR: &str = "GRAV_LAUNCHER_DISABLE_SELFUPDATE";
+
+/// Why the launcher can't update itself
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NoSelfUpdate {
+    /// Installed by a package manager, or somewhere it can't replace itself
+    /// like /usr/bin, so it's left to whoever installed it
+    ManagedExternally,
+    /// Installed on a read-only filesystem, as system paths are on immutable
+    /// distributions. A copy at this per-user path can update itself
+    ReadOnly(PathBuf),
+    /// Turned off by the administrator's policy
+    Disabled,
 }
 
-/// Struct representing a GitHub release
-#[derive(serde::Deserialize)]
-struct GitHubRelease {
-    tag_name: String,
-    assets: Vec<GitHubAsset>,
+/// Never check for or install launcher updates
+pub fn disable_self_update() {
+    SELF_UPDATE_DISABLED.store(true, Ordering::Relaxed);
 }
 
-/// Struct representing a GitHub release asset
-#[derive(serde::Deserialize)]
-struct GitHubAsset {
-    name: String,
-    browser_download_url: String,
+static SELF_UPDATE_DISABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn self_update_blocked() -> Option<NoSelfUpdate> {
+    if SELF_UPDATE_DISABLED.load(Ordering::Relaxed) {
+        return Some(NoSelfUpdate::Disabled);
+    }
+    if env::var_os(DISABLE_SELFUPDATE_VAR).is_some_and(|value| !value.is_empty() && value != "0") {
+        return Some(NoSelfUpdate::ManagedExternally);
+    }
+    let Some(dir) = launcher_path()
+        .ok()
+        .and_then(|path| Some(path.parent()?.to_path_buf()))
+    else {
+        return Some(NoSelfUpdate::ManagedExternally);
+    };
+    let Ok(dir) = CString::new(dir.into_os_string().into_vec()) else {
+        return Some(NoSelfUpdate::ManagedExternally);
+    };
+    // SAFETY: the path is a valid NUL terminated string for the duration of the call
+    if unsafe { libc::access(dir.as_ptr(), libc::W_OK) } == 0 {
+        return None;
+    }
+    if io::Error::last_os_error().kind() == io::ErrorKind::ReadOnlyFilesystem
+        && let Some(home) = env::var_os("HOME")
+    {
+        let user_path = Path::new(&home).join(".local/bin").join(binary_name());
+        return Some(NoSelfUpdate::ReadOnly(user_path));
+    }
+    Some(NoSelfUpdate::ManagedExternally)
 }
 
 /// Check if a newer version of the launcher is available
@@ -37,128 +80,132 @@ pub fn check_for_update(current_version: &str) -> Result<Option<String>> {
     // Remove 'v' prefix if present for comparison
     let current_version = current_version.trim_start_matches('v');
 
-    // Fetch the latest release from GitHub
-    let client = reqwest::blocking::Client::new();
-    let response = client
-        .get(github_api_releases_url())
-        .header("User-Agent", "grav-launcher")
-        .send()
-        .wrap_err("Failed to connect to GitHub API")?;
-
-    if !response.status().is_success() {
-        return Err(eyre!("GitHub API returned error: {}", response.status()));
-    }
-
-    let release: GitHubRelease = response
-        .json()
-        .wrap_err("Failed to parse GitHub API response")?;
+    let release = provider::current().latest()?;
 
     // Extract the version number from the tag (remove 'v' prefix)
-    let latest_version = release.tag_name.trim_start_matches('v');
+    let latest_version = release.version.trim_start_matches('v');
 
     // Compare versions
     if is_newer_version(current_version, latest_version) {
-        Ok(Some(release.tag_name))
+        Ok(Some(release.version))
     } else {
         Ok(None)
     }
 }
 
-/// Download and apply the update
-pub fn update_launcher(version: &str, tx: &mpsc::Sender<Event>) -> Result<()> {
-    // Find the correct asset to download
-    let client = reqwest::blocking::Client::new();
-    let response = client
-        .get(github_api_releases_url())
-        .header("User-Agent", "grav-launcher")
-        .send()
-        .wrap_err("Failed to connect to GitHub API")?;
-
-    let release: GitHubRelease = response
-        .json()
-        .wrap_err("Failed to parse GitHub API response")?;
-
-    // Find the grav-launcher asset
-    let asset = release
-        .assets
-        .iter()
-        .find(|asset| asset.name == "grav-launcher")
-        .ok_or_else(|| eyre!("Could not find launcher binary in release assets"))?;
-
-    // Notify UI that download is starting
-    if tx.send(Event::StartDownloadingLauncherUpdate).is_err() {
-        return Err(eyre!(
-            "Channel disconnected when starting launcher download"
-        ));
-    }
-
-    // Download the new version
-    let binary_response = reqwest::blocking::get(&asset.browser_download_url)
-        .wrap_err("Failed to download launcher update")?;
-
-    let total_size = binary_response
-        .headers()
-        .get(reqwest::header::CONTENT_LENGTH)
-        .and_then(|ct_len| ct_len.to_str().ok()?.parse::<u64>().ok());
+/// Download the update and stage it next to the current executable
+/// Applying it is left to the user's choice, see `apply_update`
+pub fn update_launcher(version: &str, tx: &bus::Sender) -> Result<()> {
+    let _span = info_span!("update_launcher", version).entered();
+    let release = provider::current().latest()?;
+
+    // Stage the download next to the current executable
+    let temp_path = staged_update_path(version)?;
+    let job = Job {
+        kind: JobKind::Launcher,
+        url: release.binary_url.clone(),
+        destination: temp_path.clone(),
+        resume: false,
+    };
 
-    // Get the current executable path
-    let current_exe = env::current_exe().wrap_err("Failed to get current executable path")?;
+    // The game download has priority, the update may be queued behind it
+    let mut total_size = None;
+    downloads::run(&job, |progress| {
+        match progress {
+            Progress::Queued => tx.send(UpdateEvent::Queued),
+            Progress::Started(total) => {
+                total_size = total;
+                tx.send(UpdateEvent::StartDownloading)
+                    .and_then(|()| tx.send(UpdateEvent::DownloadProgress(0, total)))
+            }
+            Progress::Resumed(downloaded) | Progress::Transferred(downloaded) => {
+                tx.send(UpdateEvent::DownloadProgress(downloaded, total_size))
+            }
+        }
+        .map_err(|_| eyre!("Channel disconnected during launcher download"))
+    })?;
 
-    // Create a temporary file for the download
-    let temp_path = current_exe.with_file_name(format!("grav-launcher.{version}.new"));
-    let mut file = File::create(&temp_path)
-        .wrap_err_with(|| format!("Failed to create temporary file at {temp_path:?}"))?;
+    file_mode::make_executable(&temp_path)?;
 
-    // Stream the download
-    let mut downloaded: u64 = 0;
-    let mut resp = binary_response;
-    let mut buffer = [0u8; 8 * 1024];
+    // Verify the download before offering to apply it
+    if tx.send(UpdateEvent::Verifying).is_err() {
+        return Err(eyre!("Channel disconnected when verifying launcher update"));
+    }
+    if let Err(e) = verify_update(&release, &temp_path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
 
-    // Initial progress update with total size
-    if tx
-        .send(Event::LauncherDownloadProgress(0, total_size))
-        .is_err()
-    {
-        return Err(eyre!("Channel disconnected during launcher download"));
+    // Notify UI that download is complete
+    if tx.send(UpdateEvent::Downloaded).is_err() {
+        return Err(eyre!(
+            "Channel disconnected after launcher download completed"
+        ));
     }
 
-    loop {
-        let bytes_read = resp
-            .read(&mut buffer)
-            .wrap_err("Failed to read from HTTP stream")?;
+    Ok(())
+}
 
-        if bytes_read == 0 {
-            break;
-        }
+/// Check the SHA-256 checksum if the release publishes one
+fn verify_update(release: &Release, path: &Path) -> Result<()> {
+    elf::check_executable(path).wrap_err("Downloaded launcher update is broken")?;
+    let Some(checksum_url) = &release.checksum_url else {
+        return Ok(());
+    };
+
+    let expected = hash::fetch_hash(checksum_url, binary_name())
+        .wrap_err("Failed to fetch launcher checksum")?;
+    let actual = hash::compute_file_hash(path)?;
+    if expected != actual {
+        return Err(eyre!(
+            "Checksum mismatch: expected {expected}, got {actual}"
+        ));
+    }
+    Ok(())
+}
 
-        file.write_all(&buffer[..bytes_read])
-            .wrap_err("Failed to write binary file to disk")?;
+/// The AppImage the launcher runs from. Its `current_exe` is inside the
+/// read-only AppImage mount, updates replace the AppImage file instead
+fn appimage_path() -> Option<PathBuf> {
+    env::var_os("APPIMAGE")
+        .map(PathBuf::from)
+        .filter(|path| path.is_file())
+}
 
-        downloaded += bytes_read as u64;
+/// Release asset to update to, the AppImage is published next to the plain binary
+fn binary_name() -> &'static str {
+    if appimage_path().is_some() {
+        "grav-launcher.AppImage"
+    } else {
+        "grav-launcher"
+    }
+}
 
-        // Update UI with progress
-        if tx
-            .send(Event::LauncherDownloadProgress(downloaded, total_size))
-            .is_err()
-        {
-            return Err(eyre!("Channel disconnected during launcher download"));
-        }
+/// Path of the currently running launcher binary, or of its AppImage
+fn launcher_path() -> Result<PathBuf> {
+    if let Some(appimage) = appimage_path() {
+        return Ok(appimage);
     }
+    let current_exe = env::current_exe().wrap_err("Failed to get current executable path")?;
+    // Once the binary is replaced, /proc/self/exe points to "<path> (deleted)"
+    let path = current_exe.to_string_lossy();
+    Ok(path
+        .strip_suffix(" (deleted)")
+        .map_or(current_exe.clone(), PathBuf::from))
+}
 
-    // Make the file executable
-    let mut perms = fs::metadata(&temp_path)?.permissions();
-    perms.set_mode(0o755); // rwxr-xr-x permissions
-    fs::set_permissions(&temp_path, perms)?;
+/// Where a downloaded update for `version` waits to be applied
+fn staged_update_path(version: &str) -> Result<PathBuf> {
+    Ok(launcher_path()?.with_file_name(format!("grav-launcher.{version}.new")))
+}
 
-    // Notify UI that download is complete
-    if tx.send(Event::LauncherUpdateDownloaded).is_err() {
-        return Err(eyre!(
-            "Channel disconnected after launcher download completed"
-        ));
-    }
+/// Replace the running executable with the staged update
+pub fn apply_update(version: &str, tx: &bus::Sender) -> Result<()> {
+    let temp_path = staged_update_path(version)?;
+    let current_exe = launcher_path()?;
 
     // Notify UI that update is being applied
-    if tx.send(Event::LauncherApplyingUpdate).is_err() {
+    if tx.send(UpdateEvent::Applying).is_err() {
         return Err(eyre!("Channel disconnected when applying launcher update"));
     }
 
@@ -172,7 +219,7 @@ pub fn update_launcher(version: &str, tx: &mpsc::Sender<Event>) -> Result<()> {
     })?;
 
     // Notify the user that the update was applied
-    if tx.send(Event::LauncherUpdateApplied).is_err() {
+    if tx.send(UpdateEvent::Applied).is_err() {
         return Err(eyre!(
             "Channel disconnected when notifying about successful update"
         ));
@@ -181,6 +228,68 @@ pub fn update_launcher(version: &str, tx: &mpsc::Sender<Event>) -> Result<()> {
     Ok(())
 }
 
+/// Remove a downloaded update without applying it
+pub fn discard_update(version: &str) -> Result<()> {
+    let temp_path = staged_update_path(version)?;
+    fs::remove_file(&temp_path)
+        .wrap_err_with(|| format!("Failed to remove staged update {temp_path:?}"))
+}
+
+/// Apply an update staged with "apply on next start" by an earlier session
+/// Returns the version that was applied, if any. While self-update is
+/// blocked the staged update is removed instead
+pub fn apply_staged_update() -> Result<Option<String>> {
+    let blocked = self_update_blocked().is_some();
+    let current_exe = launcher_path()?;
+    let Some(dir) = current_exe.parent() else {
+        return Ok(None);
+    };
+
+    for entry in fs::read_dir(dir).wrap_err("Failed to read launcher directory")? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let Some(version) = name
+            .strip_prefix("grav-launcher.")
+            .and_then(|n| n.strip_suffix(".pending"))
+        else {
+            continue;
+        };
+        let version = version.to_string();
+        if blocked {
+            fs::remove_file(&path)
+                .wrap_err_with(|| format!("Failed to remove staged update {path:?}"))?;
+            continue;
+        }
+        fs::rename(&path, &current_exe).wrap_err_with(|| {
+            format!(
+                "Failed to replace executable: {} -> {}",
+                path.display(),
+                current_exe.display()
+            )
+        })?;
+        return Ok(Some(version));
+    }
+    Ok(None)
+}
+
+/// Mark the downloaded update to be applied on the next launcher start
+pub fn stage_update_for_next_start(version: &str) -> Result<()> {
+    let temp_path = staged_update_path(version)?;
+    let pending_path = temp_path.with_file_name(format!("grav-launcher.{version}.pending"));
+    fs::rename(&temp_path, &pending_path)
+        .wrap_err_with(|| format!("Failed to stage update at {pending_path:?}"))
+}
+
+/// Replace the current process with a fresh instance of the launcher binary,
+/// keeping the command line flags
+pub fn restart_launcher() -> Result<()> {
+    let current_exe = launcher_path()?;
+    let err = Command::new(&current_exe).args(env::args().skip(1)).exec();
+    Err(eyre!("Failed to restart {}: {err}", current_exe.display()))
+}
+
 /// Compare version strings to determine if the target version is newer
 fn is_newer_version(current: &str, target: &str) -> bool {
     let parse_version = |v: &str| -> Vec<u32> {
@@ -209,6 +318,48 @@ fn is_newer_version(current: &str, target: &str) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
+
+    fn version(parts: &[u32]) -> String {
+        parts
+            .iter()
+            .map(u32::to_string)
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    proptest! {
+        #[test]
+        fn test_newer_version_is_antisymmetric(a in "\\PC{0,12}", b in "\\PC{0,12}") {
+            prop_assert!(!(is_newer_version(&a, &b) && is_newer_version(&b, &a)));
+            prop_assert!(!is_newer_version(&a, &a));
+        }
+
+        #[test]
+        fn test_bumped_version_is_newer(
+            parts in prop::collection::vec(0..u32::MAX, 1..5),
+            index in any::<prop::sample::Index>(),
+        ) {
+            let index = index.index(parts.len());
+            let mut bumped = parts.clone();
+            bumped[index] += 1;
+            bumped.truncate(index + 1);
+            prop_assert!(is_newer_version(&version(&parts), &version(&bumped)));
+            prop_assert!(!is_newer_version(&version(&bumped), &version(&parts)));
+        }
+
+        #[test]
+        fn test_newer_version_is_transitive(
+            a in prop::collection::vec(0..4u32, 1..4),
+            b in prop::collection::vec(0..4u32, 1..4),
+            c in prop::collection::vec(0..4u32, 1..4),
+        ) {
+            let (a, b, c) = (version(&a), version(&b), version(&c));
+            if is_newer_version(&a, &b) && is_newer_version(&b, &c) {
+                prop_assert!(is_newer_version(&a, &c));
+            }
+        }
+    }
 
     #[test]
     fn test_higher_version_returns_true() {
@@ -0,0 +1,228 @@
+use clap::ValueEnum;
+use color_eyre::{Result, eyre::eyre};
+use eyre::WrapErr;
+use serde::Deserialize;
+use std::sync::OnceLock;
+
+use crate::REPOSITORY;
+use crate::http;
+use crate::net;
+
+/// A published launcher release
+pub struct Release {
+    /// Version tag, e.g. `v0.1.7`
+    pub version: String,
+    pub binary_url: String,
+    /// Where the SHA-256 of the binary is published, if it is
+    pub checksum_url: Option<String>,
+}
+
+/// Where launcher updates come from
+pub trait UpdateProvider: Send + Sync {
+    /// The latest published release
+    fn latest(&self) -> Result<Release>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ProviderKind {
+    /// GitHub releases
+    Github,
+    /// Gitea or Forgejo releases, e.g. on Codeberg
+    Gitea,
+    /// A JSON document like {"version": "v0.2.0", "url": "...", "sha256_url": "..."},
+    /// with `appimage_url` and `appimage_sha256_url` for the AppImage
+    Json,
+    /// A directory with a `latest` file holding the version and
+    /// `<version>/grav-launcher` (or `grav-launcher.AppImage`) and its
+    /// `.sha256` next to it
+    Directory,
+}
+
+static PROVIDER: OnceLock<Box<dyn UpdateProvider>> = OnceLock::new();
+
+/// Pick where updates come from, `url` is the repository, JSON document or
+/// directory. Without one GitHub releases of this launcher are used
+pub fn select(kind: ProviderKind, url: Option<String>) -> Result<()> {
+    let provider: Box<dyn UpdateProvider> = match (kind, url) {
+        (ProviderKind::Github, url) => {
+            Box::new(Forge::github(url.as_deref().unwrap_or(REPOSITORY))?)
+        }
+        (ProviderKind::Gitea, Some(url)) => Box::new(Forge::gitea(&url)?),
+        (ProviderKind::Json, Some(url)) => Box::new(JsonFeed { url }),
+        (ProviderKind::Directory, Some(url)) => Box::new(Directory {
+            url: url.trim_end_matches('/').to_string(),
+        }),
+        (kind, None) => {
+            return Err(eyre!(
+                "--update-url or update_url is needed for {kind:?} updates"
+            ));
+        }
+    };
+    let _ = PROVIDER.set(provider);
+    Ok(())
+}
+
+pub fn current() -> &'static dyn UpdateProvider {
+    PROVIDER
+        .get_or_init(|| {
+            Box::new(Forge::github(REPOSITORY).expect("the repository is a GitHub URL"))
+        })
+        .as_ref()
+}
+
+fn get(url: &str) -> Result<reqwest::blocking::Response> {
+    let response = net::get(url).wrap_err_with(|| format!("Failed to connect to {url}"))?;
+    Ok(http::check(response)?)
+}
+
+/// GitHub and Gitea/Forgejo, their release APIs answer in the same shape
+struct Forge {
+    api_url: String,
+}
+
+#[derive(Deserialize)]
+struct ForgeRelease {
+    tag_name: String,
+    assets: Vec<ForgeAsset>,
+}
+
+#[derive(Deserialize)]
+struct ForgeAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+impl Forge {
+    /// From a repository URL like `https://github.com/owner/repo`
+    fn github(repository: &str) -> Result<Self> {
+        let path = repo_path(repository, "https://github.com/")?;
+        Ok(Self {
+            api_url: format!("https://api.github.com/repos/{path}/releases/latest"),
+        })
+    }
+
+    /// From a repository URL like `https://codeberg.org/owner/repo`
+    fn gitea(repository: &str) -> Result<Self> {
+        let (host, path) = repository
+            .split_once("://")
+            .and_then(|(scheme, rest)| {
+                let (host, path) = rest.split_once('/')?;
+                Some((format!("{scheme}://{host}"), path.trim_end_matches('/')))
+            })
+            .ok_or_else(|| eyre!("{repository} isn't a repository URL"))?;
+        Ok(Self {
+            api_url: format!("{host}/api/v1/repos/{path}/releases/latest"),
+        })
+    }
+}
+
+fn repo_path<'a>(repository: &'a str, prefix: &str) -> Result<&'a str> {
+    repository
+        .strip_prefix(prefix)
+        .map(|path| path.trim_end_matches('/'))
+        .filter(|path| path.split('/').count() == 2)
+        .ok_or_else(|| eyre!("{repository} isn't a {prefix}owner/repo URL"))
+}
+
+impl UpdateProvider for Forge {
+    fn latest(&self) -> Result<Release> {
+        let release: ForgeRelease = get(&self.api_url)?
+            .json()
+            .wrap_err("Failed to parse the release")?;
+        let asset_url = |name: &str| {
+            release
+                .assets
+                .iter()
+                .find(|asset| asset.name == name)
+                .map(|asset| asset.browser_download_url.clone())
+        };
+        let binary = super::binary_name();
+        Ok(Release {
+            binary_url: asset_url(binary)
+                .ok_or_else(|| eyre!("Could not find {binary} in release assets"))?,
+            checksum_url: asset_url(&format!("{binary}.sha256")),
+            version: release.tag_name,
+        })
+    }
+}
+
+/// A JSON document maintained by a distribution or self-hoster
+struct JsonFeed {
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct JsonRelease {
+    version: String,
+    url: String,
+    sha256_url: Option<String>,
+    appimage_url: Option<String>,
+    appimage_sha256_url: Option<String>,
+}
+
+impl UpdateProvider for JsonFeed {
+    fn latest(&self) -> Result<Release> {
+        let release: JsonRelease = get(&self.url)?
+            .json()
+            .wrap_err("Failed to parse the release document")?;
+        if super::appimage_path().is_some() {
+            return Ok(Release {
+                version: release.version,
+                binary_url: release
+                    .appimage_url
+                    .ok_or_else(|| eyre!("The release document has no appimage_url"))?,
+                checksum_url: release.appimage_sha256_url,
+            });
+        }
+        Ok(Release {
+            version: release.version,
+            binary_url: release.url,
+            checksum_url: release.sha256_url,
+        })
+    }
+}
+
+/// A plain directory of builds served over HTTP
+struct Directory {
+    url: String,
+}
+
+impl UpdateProvider for Directory {
+    fn latest(&self) -> Result<Release> {
+        let version = get(&format!("{}/latest", self.url))?
+            .text()?
+            .trim()
+            .to_string();
+        if version.is_empty() || version.contains('/') {
+            return Err(eyre!("{}/latest doesn't hold a version", self.url));
+        }
+        let binary = super::binary_name();
+        Ok(Release {
+            binary_url: format!("{}/{version}/{binary}", self.url),
+            checksum_url: Some(format!("{}/{version}/{binary}.sha256", self.url)),
+            version,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forge_api_urls() {
+        assert_eq!(
+            Forge::github("https://github.com/ejiektpobehuk/grav-launcher")
+                .unwrap()
+                .api_url,
+            "https://api.github.com/repos/ejiektpobehuk/grav-launcher/releases/latest"
+        );
+        assert_eq!(
+            Forge::gitea("https://codeberg.org/grav/launcher/")
+                .unwrap()
+                .api_url,
+            "https://codeberg.org/api/v1/repos/grav/launcher/releases/latest"
+        );
+        assert!(Forge::github("https://codeberg.org/grav/launcher").is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use std::fs;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::bus;
+use crate::event::GameEvent;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Memory and CPU use of the game process, with the highest values since launch
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Report {
+    /// Resident memory in bytes
+    pub rss: u64,
+    /// Share of one CPU core, above 100 when several are busy
+    pub cpu_percent: f64,
+    pub peak_rss: u64,
+    pub peak_cpu_percent: f64,
+}
+
+/// Stops sampling when dropped, the last report then goes out as the final one
+pub struct Monitor {
+    stop: Arc<AtomicBool>,
+}
+
+impl Drop for Monitor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Sample `/proc/<pid>` of the running game. Children the game starts
+/// itself aren't counted
+pub fn monitor(pid: u32, tx: &bus::Sender) -> Monitor {
+    let stop = Arc::new(AtomicBool::new(false));
+    let tx = tx.clone();
+    thread::spawn({
+        let stop = stop.clone();
+        move || {
+            // SAFETY: sysconf only reads system configuration
+            let (ticks_per_second, page_size) = unsafe {
+                (
+                    libc::sysconf(libc::_SC_CLK_TCK).max(1) as f64,
+                    libc::sysconf(libc::_SC_PAGESIZE).max(1) as u64,
+                )
+            };
+            let mut report = Report::default();
+            let mut last: Option<(u64, Instant)> = None;
+            while !stop.load(Ordering::Relaxed) {
+                let Some((cpu_ticks, rss_pages)) = sample(pid) else {
+                    break;
+                };
+                let now = Instant::now();
+                report.rss = rss_pages * page_size;
+                if let Some((last_ticks, at)) = last {
+                    let busy = cpu_ticks.saturating_sub(last_ticks) as f64 / ticks_per_second;
+                    report.cpu_percent = busy / now.duration_since(at).as_secs_f64() * 100.0;
+                }
+                report.peak_rss = report.peak_rss.max(report.rss);
+                report.peak_cpu_percent = report.peak_cpu_percent.max(report.cpu_percent);
+                last = Some((cpu_ticks, now));
+                if tx.send(GameEvent::Usage(Some(report))).is_err() {
+                    return;
+                }
+                thread::sleep(SAMPLE_INTERVAL);
+            }
+            let _ = tx.send(GameEvent::Usage(None));
+        }
+    });
+    Monitor { stop }
+}
+
+/// CPU time in clock ticks and resident pages, `None` once the process is gone
+fn sample(pid: u32) -> Option<(u64, u64)> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    let statm = fs::read_to_string(format!("/proc/{pid}/statm")).ok()?;
+    Some((
+        cpu_ticks(&stat)?,
+        statm.split_whitespace().nth(1)?.parse().ok()?,
+    ))
+}
+
+// utime and stime, the 14th and 15th fields. The command name before them
+// is in parentheses and may contain spaces
+fn cpu_ticks(stat: &str) -> Option<u64> {
+    let mut fields = stat
+        .get(stat.rfind(')')? + 1..)?
+        .split_whitespace()
+        .skip(11);
+    let utime: u64 = fields.next()?.parse().ok()?;
+    let stime: u64 = fields.next()?.parse().ok()?;
+    Some(utime + stime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cpu_time_is_read_after_the_command_name() {
+        let stat = "4242 (GRAV (main)) S 1 4242 4242 0 -1 4194560 \
+                    5000 0 0 0 1500 250 0 0 20 0 12 0 100 0";
+        assert_eq!(cpu_ticks(stat), Some(1750));
+        assert_eq!(cpu_ticks("4242 (GRAV"), None);
+    }
+}
@@ -0,0 +1,155 @@
+use color_eyre::Result;
+use eyre::WrapErr;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::cache;
+use crate::dirs;
+use crate::install_record;
+use crate::known_good;
+use crate::profile;
+
+/// Downloaded builds kept in the cache, older ones are deleted
+static KEEP: AtomicUsize = AtomicUsize::new(3);
+
+/// Builds downloaded into the cache, newest first, kept per profile in
+/// `$XDG_STATE_HOME/GRAV` so an older one can be installed again
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Versions {
+    builds: Vec<Build>,
+    /// Build the player picked, updates are skipped while it's installed
+    pinned: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Build {
+    pub hash: String,
+    /// Unix time of the download
+    pub downloaded: i64,
+}
+
+/// Keep this many downloaded builds, at least one
+pub fn set_keep(count: usize) {
+    KEEP.store(count.max(1), Ordering::Relaxed);
+}
+
+fn state_path() -> Result<PathBuf> {
+    dirs::base()
+        .place_state_file(format!("{}.versions.json", profile::current().artifact()))
+        .wrap_err("Can't create version list path")
+}
+
+fn load() -> Versions {
+    state_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|saved| serde_json::from_str(&saved).ok())
+        .unwrap_or_default()
+}
+
+fn save(versions: &Versions) -> Result<()> {
+    let path = state_path()?;
+    let saved = serde_json::to_string_pretty(versions)?;
+    fs::write(&path, saved).wrap_err_with(|| format!("Failed to write {path:?}"))
+}
+
+/// Add a build that was just put into the cache, deleting the builds past
+/// the number to keep
+pub fn record(hash: &str) -> Result<()> {
+    let mut versions = load();
+    versions.builds.retain(|build| build.hash != hash);
+    let downloaded = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs() as i64);
+    versions.builds.insert(
+        0,
+        Build {
+            hash: hash.to_string(),
+            downloaded,
+        },
+    );
+
+    // Prefetched builds of other channels are recorded too, they mustn't
+    // push out the build a crashing update falls back to
+    let kept: Vec<String> = install_record::installed_hash()
+        .into_iter()
+        .chain(versions.pinned.clone())
+        .chain(known_good::last_good())
+        .collect();
+    let protected: Vec<&str> = kept.iter().map(String::as_str).collect();
+    let expired = expired(&versions.builds, KEEP.load(Ordering::Relaxed), &protected);
+    for hash in &expired {
+        cache::remove(hash)?;
+    }
+    versions
+        .builds
+        .retain(|build| !expired.contains(&build.hash));
+    save(&versions)
+}
+
+// Builds past the newest `keep`, except the protected ones
+fn expired(builds: &[Build], keep: usize, protected: &[&str]) -> Vec<String> {
+    builds
+        .iter()
+        .skip(keep)
+        .filter(|build| !protected.contains(&build.hash.as_str()))
+        .map(|build| build.hash.clone())
+        .collect()
+}
+
+/// Builds that can be installed again, newest first
+pub fn list() -> Vec<Build> {
+    load()
+        .builds
+        .into_iter()
+        .filter(|build| cache::lookup(&build.hash).ok().flatten().is_some())
+        .collect()
+}
+
+pub fn pinned() -> Option<String> {
+    load().pinned
+}
+
+/// Stay on this build instead of updating, or follow updates again with `None`
+pub fn pin(hash: Option<&str>) -> Result<()> {
+    let mut versions = load();
+    versions.pinned = hash.map(str::to_string);
+    save(&versions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_old_builds_expire_unless_installed() {
+        let builds: Vec<Build> = ["d", "c", "b", "a"]
+            .iter()
+            .map(|hash| Build {
+                hash: hash.to_string(),
+                downloaded: 0,
+            })
+            .collect();
+        assert_eq!(expired(&builds, 2, &["a"]), ["b"]);
+        assert!(expired(&builds, 4, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_prefetched_builds_dont_expire_the_last_good_one() {
+        // Nightly and beta prefetched after the update the player is on,
+        // the last good build is older than all of them
+        let builds: Vec<Build> = ["beta", "nightly", "installed", "good"]
+            .iter()
+            .map(|hash| Build {
+                hash: hash.to_string(),
+                downloaded: 0,
+            })
+            .collect();
+        assert!(expired(&builds, 2, &["installed", "good"]).is_empty());
+        assert_eq!(expired(&builds, 2, &["installed"]), ["good"]);
+    }
+}